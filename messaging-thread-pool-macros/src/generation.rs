@@ -24,11 +24,27 @@ pub fn generate_pool_item_impl(mut input: ItemImpl, args: PoolItemArgs) -> Token
         format_ident!("{}Init", struct_name)
     };
 
+    let key_type = args.key_type();
+    let named_fields = args.named_fields;
+    let batches = args.batch_method.is_some() || args.max_batch_size.is_some();
+
     let mut generated_items = Vec::new();
     let mut api_variants = Vec::new();
     let mut request_names = Vec::new();
+    let mut response_names = Vec::new();
+    let mut multi_request_names = Vec::new();
     let mut process_message_arms = Vec::new();
+    let mut process_message_stream_arms = Vec::new();
+    let mut stream_request_names = Vec::new();
+    let mut multi_item_arms = Vec::new();
     let mut type_aliases = Vec::new();
+    let mut client_methods = Vec::new();
+    let client_name = (!args.no_client).then(|| {
+        args.client_name
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Client", struct_name))
+    });
+    let mut fallible_request_names = Vec::new();
 
     for item in &mut input.items {
         if let ImplItem::Fn(method) = item {
@@ -42,25 +58,138 @@ pub fn generate_pool_item_impl(mut input: ItemImpl, args: PoolItemArgs) -> Token
 
             if let Some(index) = messaging_attr_index {
                 let attr = method.attrs.remove(index);
-                let args: MessagingArgs = match attr.parse_args() {
+                let mut args: MessagingArgs = match attr.parse_args() {
                     Ok(args) => args,
                     Err(e) => return e.to_compile_error(),
                 };
+                args.named_fields |= named_fields;
+
+                // `#[thread_resource]` parameters are borrowed from the pool thread's resources when
+                // the message is processed so they are not part of the request
+                let thread_resource_params = match take_thread_resource_params(&mut method.sig) {
+                    Ok(params) => params,
+                    Err(e) => return e.to_compile_error(),
+                };
+                if let Some((_, first, _)) = thread_resource_params.first() {
+                    if args.multi || args.stream {
+                        return syn::Error::new_spanned(
+                            first,
+                            "#[thread_resource] parameters are not supported by stream or multi methods",
+                        )
+                        .to_compile_error();
+                    }
+                }
 
-                let request_name = args.request_type;
-                let response_name = args.response_type;
+                let request_name = args.request_type.clone();
+                let response_name = args.response_type.clone();
                 let method_name = &method.sig.ident;
 
+                // a multi item method is an associated function whose first parameter receives
+                // the targeted pool items
+                if args.multi && !is_multi_item_signature(&method.sig) {
+                    return syn::Error::new_spanned(
+                        &method.sig,
+                        "multi methods must be associated functions whose first parameter is `&mut [&mut Self]`",
+                    )
+                    .to_compile_error();
+                }
+
                 // Parse arguments
-                let mut request_fields: Vec<Type> = Vec::new();
-                // Always add ID as first field
-                request_fields.push(syn::parse_quote!(u64));
-
-                for input in &method.sig.inputs {
-                    if let FnArg::Typed(pat_type) = input {
-                        let ty = &pat_type.ty;
-                        request_fields.push(*ty.clone());
+                // Always add ID as first field; a multi item request lists the ids of all of its targets.
+                // It is renamed if one of the method's parameters is already called `id` (or `ids`)
+                let id_name = if args.multi { "ids" } else { "id" };
+                let id_name = if method.sig.inputs.iter().any(|input| {
+                    matches!(input, FnArg::Typed(pat_type)
+                        if matches!(pat_type.pat.as_ref(), syn::Pat::Ident(pat_ident) if pat_ident.ident == id_name))
+                }) {
+                    format_ident!("pool_item_{}", id_name)
+                } else {
+                    format_ident!("{}", id_name)
+                };
+                let mut request_fields: Vec<(Ident, Type)> = Vec::new();
+                if args.multi {
+                    request_fields.push((id_name, syn::parse_quote!(Vec<#key_type>)));
+                } else {
+                    request_fields.push((id_name, key_type.clone()));
+                }
+
+                // a streaming method can push its items into a trailing `&mut StreamSink<T>` parameter;
+                // the sink is supplied by the pool so it is not part of the request
+                let stream_sink_item = if args.stream {
+                    stream_sink_item_type(&method.sig)
+                } else {
+                    None
+                };
+
+                let typed_inputs = method.sig.inputs.iter().filter_map(|input| match input {
+                    FnArg::Typed(pat_type) => Some(pat_type),
+                    FnArg::Receiver(_) => None,
+                });
+                let field_count = if stream_sink_item.is_some() {
+                    typed_inputs.clone().count() - 1
+                } else {
+                    typed_inputs.clone().count()
+                };
+                let mut routing_key_field = None;
+                let mut call_args = Vec::new();
+                // the arguments taken straight from the request by stream and multi item methods
+                let mut field_args = Vec::new();
+                // how the request's fields are accessed; by name or by position
+                let mut request_members = vec![if args.named_fields {
+                    syn::Member::Named(request_fields[0].0.clone())
+                } else {
+                    syn::Member::Unnamed(syn::Index::from(0))
+                }];
+                let skip_count = usize::from(args.multi);
+                for (position, pat_type) in
+                    typed_inputs.enumerate().take(field_count).skip(skip_count)
+                {
+                    if let Some((resource, _, _)) = thread_resource_params
+                        .iter()
+                        .find(|(_, _, param_position)| *param_position == position)
+                    {
+                        call_args.push(quote! { #resource });
+                        continue;
+                    }
+                    let name = if let syn::Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+                        pat_ident.ident.clone()
+                    } else {
+                        format_ident!("arg{}", request_fields.len())
+                    };
+                    // the request owns its fields; a borrowed parameter is passed a reference to one
+                    let (field_type, borrowed) = match owned_field_type(&pat_type.ty) {
+                        Ok(field_type) => field_type,
+                        Err(e) => return e.to_compile_error(),
+                    };
+                    let reference = if borrowed {
+                        quote! { & }
+                    } else {
+                        quote! {}
+                    };
+                    let member = if args.named_fields {
+                        syn::Member::Named(name.clone())
+                    } else {
+                        syn::Member::Unnamed(syn::Index::from(request_fields.len()))
+                    };
+                    field_args.push(quote! { #reference request.#member });
+                    // named fields are destructured from the request before the call
+                    if args.named_fields {
+                        call_args.push(quote! { #reference #name });
+                    } else {
+                        call_args.push(quote! { #reference request.#member });
                     }
+                    if args.routing_key.as_ref() == Some(&name) {
+                        routing_key_field = Some(member.clone());
+                    }
+                    request_members.push(member);
+                    request_fields.push((name, field_type));
+                }
+                if let (Some(routing_key), None) = (&args.routing_key, &routing_key_field) {
+                    return syn::Error::new_spanned(
+                        routing_key,
+                        "routing_key must name one of the method's parameters",
+                    )
+                    .to_compile_error();
                 }
 
                 // Parse return type
@@ -69,22 +198,72 @@ pub fn generate_pool_item_impl(mut input: ItemImpl, args: PoolItemArgs) -> Token
                     ReturnType::Type(_, ty) => Some(ty.clone()),
                 };
 
+                // the type of each item of a streaming method comes from its sink or its return type
+                let stream_item = if args.stream {
+                    match stream_sink_item
+                        .clone()
+                        .or_else(|| return_type.as_deref().and_then(stream_return_item_type))
+                    {
+                        Some(item) => Some(item),
+                        None => {
+                            return syn::Error::new_spanned(
+                                &method.sig,
+                                "stream methods must return `impl Iterator<Item = T>`, `impl IntoIterator<Item = T>` or `Vec<T>`, or take a final `&mut StreamSink<T>` parameter",
+                            )
+                            .to_compile_error();
+                        }
+                    }
+                } else {
+                    None
+                };
+
                 generated_items.push(generate_request_struct(
-                    &request_name,
+                    &args,
                     &request_fields,
                     struct_name,
-                    &response_name,
                     &api_name,
+                    routing_key_field,
+                    &key_type,
+                    generics,
+                ));
+
+                let result_type = if let Some(item) = &stream_item {
+                    syn::parse_quote!(messaging_thread_pool::StreamChunk<#item>)
+                } else if args.multi {
+                    let return_type = return_type.unwrap_or_else(|| syn::parse_quote!(()));
+                    syn::parse_quote!(Result<#return_type, messaging_thread_pool::MultiItemError<#key_type>>)
+                } else {
+                    return_type.unwrap_or_else(|| syn::parse_quote!(()))
+                };
+                // the client has its own `new` and `pool`
+                let clashes = method_name == "new" || method_name == "pool";
+                if let (Some(client_name), true) = (&client_name, clashes) {
+                    return syn::Error::new_spanned(
+                        method_name,
+                        format!(
+                            "`#[messaging]` method `{method_name}` clashes with `{client_name}::{method_name}`; \
+rename the method or opt out of the client with `NoClient`"
+                        ),
+                    )
+                    .to_compile_error();
+                }
+                client_methods.push(generate_client_methods(
+                    method_name,
+                    &method.attrs,
+                    &args,
+                    &request_fields,
+                    stream_item.as_ref().unwrap_or(&result_type),
+                    struct_name,
                     generics,
                 ));
 
-                let result_type = return_type.unwrap_or_else(|| syn::parse_quote!(()));
                 generated_items.push(generate_response_struct(
                     &response_name,
                     &result_type,
                     struct_name,
                     &api_name,
                     &request_name,
+                    &key_type,
                     generics,
                 ));
 
@@ -125,39 +304,224 @@ pub fn generate_pool_item_impl(mut input: ItemImpl, args: PoolItemArgs) -> Token
                     #request_name(#alias_name #ty_generics)
                 });
                 request_names.push(request_name.clone());
-
-                process_message_arms.push(generate_process_message_arm(
-                    &api_name,
-                    &request_name,
-                    method_name,
-                    &request_fields,
-                    &response_name,
-                    generics,
-                ));
+                response_names.push(response_name.clone());
+
+                if args.multi {
+                    multi_request_names.push(request_name.clone());
+                    process_message_arms.push(quote! {
+                        #api_name::#request_name(_) => {
+                            panic!("multi item requests are processed by process_multi_item_message")
+                        }
+                    });
+                    multi_item_arms.push(generate_multi_item_arms(
+                        &api_name,
+                        &request_name,
+                        method_name,
+                        &request_members[0],
+                        &field_args,
+                        &response_name,
+                        generics,
+                    ));
+                } else if let Some(item) = &stream_item {
+                    // a batch is processed by `process_batch`, which cannot stream its responses
+                    if batches {
+                        return syn::Error::new_spanned(
+                            &attr,
+                            "stream methods cannot be used with `Batch` or `MaxBatchSize`",
+                        )
+                        .to_compile_error();
+                    }
+                    stream_request_names.push(request_name.clone());
+                    generated_items.push(generate_stream_response_impl(
+                        &response_name,
+                        item,
+                        generics,
+                    ));
+                    process_message_arms.push(quote! {
+                        #api_name::#request_name(_) => {
+                            panic!("stream requests are processed by process_message_stream")
+                        }
+                    });
+                    process_message_stream_arms.push(generate_process_message_stream_arm(
+                        &api_name,
+                        &request_name,
+                        method_name,
+                        &field_args,
+                        &response_name,
+                        stream_sink_item.as_ref(),
+                        generics,
+                    ));
+                } else {
+                    // the errors of methods that return a `Result` are reported to the pool item
+                    if !args.no_error_report && returns_result(&method.sig.output) {
+                        fallible_request_names.push(request_name.clone());
+                    }
+                    let mut call =
+                        generate_method_call(method_name, &call_args, &thread_resource_params);
+                    // named fields are destructured by name before the call
+                    if args.named_fields && request_members.len() > 1 {
+                        let fields = &request_members[1..];
+                        call = quote! {
+                            {
+                                let #request_name { #(#fields),*, .. } = request;
+                                #call
+                            }
+                        };
+                    }
+                    process_message_arms.push(generate_process_message_arm(
+                        &api_name,
+                        &request_name,
+                        &call,
+                        &response_name,
+                        generics,
+                    ));
+                }
             }
         }
     }
 
+    // the messages of each `#[pool_protocol]` that the pool item implements are held by a variant
+    // named after the protocol's trait
+    let mut protocol_variants = Vec::new();
+    for protocol in &args.implements {
+        let Some(variant) = protocol
+            .segments
+            .last()
+            .map(|segment| segment.ident.clone())
+        else {
+            return syn::Error::new_spanned(
+                protocol,
+                "Expected the path of a #[pool_protocol] trait",
+            )
+            .to_compile_error();
+        };
+        let protocol_api = protocol_item_path(protocol, "Api");
+        api_variants.push(quote! {
+            #variant(#protocol_api<#key_type>)
+        });
+        process_message_arms.push(quote! {
+            #api_name::#variant(message) => messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                #api_name::#variant(message.process(self))
+            ),
+        });
+        generated_items.push(generate_implements_impl(
+            self_ty, &api_name, &variant, protocol, &key_type, generics,
+        ));
+        protocol_variants.push(variant);
+    }
+
     generated_items.push(generate_api_enum(
         &api_name,
         &type_aliases,
         &api_variants,
-        &request_names,
+        &[&request_names[..], &protocol_variants[..]].concat(),
+        &key_type,
         generics,
     ));
+    let api_response_name = args
+        .api_response_name
+        .clone()
+        .unwrap_or_else(|| format_ident!("{}Response", struct_name));
+    if !request_names.is_empty() || !protocol_variants.is_empty() {
+        // the enum of the API's responses cannot share its name with one of the pool item's
+        // response types
+        if let Some(clash) = response_names
+            .iter()
+            .find(|name| **name == api_response_name)
+        {
+            return syn::Error::new_spanned(
+                clash,
+                format!(
+                    "`{clash}` is also the name of the enum of {struct_name}'s responses; \
+name the enum with `#[pool_item(ApiResponse = \"{struct_name}Message\")]` or rename the response"
+                ),
+            )
+            .to_compile_error();
+        }
+        generated_items.push(generate_api_response(
+            struct_name,
+            &api_name,
+            &api_response_name,
+            &request_names,
+            &response_names,
+            &multi_request_names,
+            &protocol_variants,
+            &args.implements,
+            &key_type,
+            generics,
+        ));
+    }
+
+    if let Some(client_name) = &client_name {
+        generated_items.push(generate_client(
+            struct_name,
+            client_name,
+            &client_methods,
+            generics,
+        ));
+    }
 
+    let new_sig = input.items.iter().find_map(|item| match item {
+        ImplItem::Fn(method) if method.sig.ident == "new" => Some(&method.sig),
+        _ => None,
+    });
+    let init = match init_request(
+        init_name,
+        new_sig,
+        args.init_type.is_some(),
+        &key_type,
+        named_fields,
+    ) {
+        Ok(init) => init,
+        Err(e) => return e.to_compile_error(),
+    };
     if args.init_type.is_none() {
-        generated_items.push(generate_init_struct(&init_name, struct_name, generics));
+        generated_items.push(generate_init_struct(
+            &init,
+            struct_name,
+            &key_type,
+            generics,
+        ));
+    }
+
+    // only pool items with streaming or multi item methods need to override the trait's defaults
+    let mut overrides = Vec::new();
+    if !process_message_stream_arms.is_empty() {
+        overrides.push(generate_process_message_stream_fn(
+            &process_message_stream_arms,
+        ));
+        overrides.push(generate_is_stream_fn(&api_name, &stream_request_names));
+    }
+    if !multi_item_arms.is_empty() {
+        overrides.push(generate_multi_item_fns(
+            &multi_item_arms,
+            &key_type,
+            args.multi_item_rejected_method.is_none(),
+        ));
+    }
+    if !request_names.is_empty() || !protocol_variants.is_empty() {
+        overrides.push(generate_message_type_fn(
+            &api_name,
+            &request_names,
+            &protocol_variants,
+        ));
+    }
+    if !fallible_request_names.is_empty() || !protocol_variants.is_empty() {
+        overrides.push(generate_response_error_fn(
+            &api_name,
+            &fallible_request_names,
+            &protocol_variants,
+        ));
     }
 
     generated_items.push(generate_pool_item_trait_impl(
         self_ty,
-        &init_name,
+        &init,
         &api_name,
         &process_message_arms,
+        &overrides,
         generics,
-        &args.init_type,
-        &args.shutdown_method,
+        &args,
     ));
 
     quote! {
@@ -167,13 +531,16 @@ pub fn generate_pool_item_impl(mut input: ItemImpl, args: PoolItemArgs) -> Token
 }
 
 fn generate_request_struct(
-    request_name: &Ident,
-    request_fields: &[Type],
+    args: &MessagingArgs,
+    request_fields: &[(Ident, Type)],
     struct_name: &Ident,
-    response_name: &Ident,
     api_name: &Ident,
+    routing_key_field: Option<syn::Member>,
+    key_type: &Type,
     generics: &syn::Generics,
 ) -> TokenStream {
+    let request_name = &args.request_type;
+    let response_name = &args.response_type;
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let phantom_data = if !generics.params.is_empty() {
         quote! { pub std::marker::PhantomData #ty_generics, }
@@ -181,29 +548,149 @@ fn generate_request_struct(
         quote! {}
     };
 
+    // only override the trait's default priority when one has been specified
+    let priority_const = if let Some(priority) = &args.priority {
+        quote! { const PRIORITY: messaging_thread_pool::Priority = messaging_thread_pool::Priority::#priority; }
+    } else {
+        quote! {}
+    };
+
+    // a multi item request is identified by (and by default routed by) the first id that it targets;
+    // the generated constructor rejects an empty list of ids, so there is always a first id
+    let id_field = if args.named_fields {
+        let (id_name, _) = &request_fields[0];
+        syn::Member::Named(id_name.clone())
+    } else {
+        syn::Member::Unnamed(syn::Index::from(0))
+    };
+    let (id_body, variant) = if args.multi {
+        (
+            quote! {
+                self.#id_field
+                    .first()
+                    .cloned()
+                    .expect("a multi item request must target at least one pool item")
+            },
+            format_ident!("MessagePoolItems"),
+        )
+    } else {
+        (
+            quote! { self.#id_field.clone() },
+            format_ident!("MessagePoolItem"),
+        )
+    };
+
+    // by default requests are routed by id
+    let routing_key_fn = if let Some(field) = routing_key_field {
+        quote! {
+            fn routing_key(&self) -> u64 {
+                messaging_thread_pool::PoolKey::routing_hash(&self.#field)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // the constructor of a multi item request rejects an empty list of ids
+    let (new_output, new_check, new_ok) = if args.multi {
+        let (ids_name, _) = &request_fields[0];
+        (
+            quote! { Result<Self, messaging_thread_pool::MultiItemError<#key_type>> },
+            quote! {
+                if #ids_name.is_empty() {
+                    return Err(messaging_thread_pool::MultiItemError::NoIds);
+                }
+            },
+            quote! { Ok },
+        )
+    } else {
+        (quote! { Self }, quote! {}, quote! {})
+    };
+    let field_types: Vec<&Type> = request_fields.iter().map(|(_, ty)| ty).collect();
+    let field_names: Vec<&Ident> = request_fields.iter().map(|(name, _)| name).collect();
+    let request_struct = if args.named_fields {
+        let (phantom_field, phantom_init) = if generics.params.is_empty() {
+            (quote! {}, quote! {})
+        } else {
+            (
+                quote! { pub phantom: std::marker::PhantomData #ty_generics, },
+                quote! { phantom: std::marker::PhantomData, },
+            )
+        };
+        quote! {
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct #request_name #impl_generics #where_clause {
+                #(pub #field_names: #field_types,)*
+                #phantom_field
+            }
+
+            impl #impl_generics #request_name #ty_generics #where_clause {
+                #[allow(clippy::too_many_arguments)]
+                pub fn new(#(#field_names: #field_types),*) -> #new_output {
+                    #new_check
+                    #new_ok(Self { #(#field_names,)* #phantom_init })
+                }
+            }
+        }
+    } else {
+        let multi_new = if args.multi {
+            let phantom_init = if generics.params.is_empty() {
+                quote! {}
+            } else {
+                quote! { std::marker::PhantomData }
+            };
+            quote! {
+                impl #impl_generics #request_name #ty_generics #where_clause {
+                    #[allow(clippy::too_many_arguments)]
+                    pub fn new(#(#field_names: #field_types),*) -> #new_output {
+                        #new_check
+                        Ok(Self(#(#field_names,)* #phantom_init))
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+        quote! {
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct #request_name #impl_generics ( #(pub #field_types),*, #phantom_data ) #where_clause;
+
+            #multi_new
+        }
+    };
+
     quote! {
-        #[derive(Debug, Clone, PartialEq)]
-        pub struct #request_name #impl_generics ( #(pub #request_fields),*, #phantom_data ) #where_clause;
+        #request_struct
 
-        impl #impl_generics messaging_thread_pool::IdTargeted for #request_name #ty_generics #where_clause {
-            fn id(&self) -> u64 {
-                self.0
+        impl #impl_generics messaging_thread_pool::IdTargeted<#key_type> for #request_name #ty_generics #where_clause {
+            fn id(&self) -> #key_type {
+                #id_body
             }
+            #routing_key_fn
         }
 
         impl #impl_generics messaging_thread_pool::RequestWithResponse<#struct_name #ty_generics> for #request_name #ty_generics #where_clause {
             type Response = #response_name #ty_generics;
+            #priority_const
         }
 
         impl #impl_generics From<#request_name #ty_generics> for messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics> #where_clause {
             fn from(request: #request_name #ty_generics) -> Self {
-                messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                messaging_thread_pool::ThreadRequestResponse::#variant(
                     #api_name::#request_name(
                         messaging_thread_pool::request_response::RequestResponse::Request(request)
                     )
                 )
             }
         }
+
+        impl #impl_generics From<#request_name #ty_generics> for #api_name #ty_generics #where_clause {
+            fn from(request: #request_name #ty_generics) -> Self {
+                #api_name::#request_name(
+                    messaging_thread_pool::request_response::RequestResponse::Request(request)
+                )
+            }
+        }
     }
 }
 
@@ -213,6 +700,7 @@ fn generate_response_struct(
     struct_name: &Ident,
     api_name: &Ident,
     request_name: &Ident,
+    key_type: &Type,
     generics: &syn::Generics,
 ) -> TokenStream {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
@@ -225,7 +713,7 @@ fn generate_response_struct(
     quote! {
         #[derive(Debug, Clone, PartialEq)]
         pub struct #response_name #impl_generics #where_clause {
-            pub id: u64,
+            pub id: #key_type,
             pub result: #result_type,
             #phantom_data
         }
@@ -268,21 +756,50 @@ fn generate_from_response_impl(
     }
 }
 
+/// A `#[thread_resource]` parameter; the name it is bound to when borrowed, the resource's type and
+/// the position of the parameter (excluding any receiver)
+type ThreadResourceParam = (Ident, Type, usize);
+
+/// Removes the `#[thread_resource]` attribute from the method's parameters, returning the parameters
+/// that had it
+///
+/// A thread resource parameter must be a reference; the type it refers to is the resource's type.
+fn take_thread_resource_params(sig: &mut syn::Signature) -> syn::Result<Vec<ThreadResourceParam>> {
+    let mut params = Vec::new();
+    let typed_inputs = sig.inputs.iter_mut().filter_map(|input| match input {
+        FnArg::Typed(pat_type) => Some(pat_type),
+        FnArg::Receiver(_) => None,
+    });
+    for (position, pat_type) in typed_inputs.enumerate() {
+        let attr_count = pat_type.attrs.len();
+        pat_type
+            .attrs
+            .retain(|attr| !attr.path().is_ident("thread_resource"));
+        if pat_type.attrs.len() == attr_count {
+            continue;
+        }
+        let Type::Reference(reference) = pat_type.ty.as_ref() else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.ty,
+                "#[thread_resource] parameters must be references, such as `&mut Cache`",
+            ));
+        };
+        params.push((
+            format_ident!("thread_resource_{}", position),
+            *reference.elem.clone(),
+            position,
+        ));
+    }
+    Ok(params)
+}
+
 fn generate_process_message_arm(
     api_name: &Ident,
     request_name: &Ident,
-    method_name: &Ident,
-    request_fields: &[Type],
+    call: &TokenStream,
     response_name: &Ident,
     generics: &syn::Generics,
 ) -> TokenStream {
-    let call_args = if request_fields.len() > 1 {
-        let indices = (1..request_fields.len()).map(syn::Index::from);
-        quote! { #(request.#indices),* }
-    } else {
-        quote! {}
-    };
-
     let phantom_init = if !generics.params.is_empty() {
         quote! { phantom: std::marker::PhantomData, }
     } else {
@@ -296,273 +813,2076 @@ fn generate_process_message_arm(
                 _ => panic!("Unexpected message in process_message (expected Request)"),
             };
             let id = messaging_thread_pool::IdTargeted::id(&request);
-            let result = self.#method_name(#call_args);
+            let result = #call;
             #response_name { id, result, #phantom_init }.into()
         }
     }
 }
 
-fn generate_api_enum(
-    api_name: &Ident,
-    type_aliases: &[TokenStream],
-    api_variants: &[TokenStream],
-    request_names: &[Ident],
-    generics: &syn::Generics,
-) -> TokenStream {
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-
-    quote! {
-        #(#type_aliases)*
+/// The type of the request field for a method parameter, and whether the method is passed a
+/// reference to the field
+///
+/// A request owns its fields, so a borrowed parameter is given an owned field: `&str` becomes
+/// `String`, `&[T]` becomes `Vec<T>` and any other `&T` becomes `T`. `&'static` references are
+/// kept as they are.
+pub(crate) fn owned_field_type(ty: &Type) -> syn::Result<(Type, bool)> {
+    let Type::Reference(reference) = ty else {
+        check_owned_field_type(ty)?;
+        return Ok((ty.clone(), false));
+    };
+    match &reference.lifetime {
+        Some(lifetime) if lifetime.ident == "static" => {
+            check_owned_field_type(&reference.elem)?;
+            return Ok((ty.clone(), false));
+        }
+        Some(lifetime) if lifetime.ident != "_" => {
+            return Err(syn::Error::new_spanned(
+                lifetime,
+                "#[messaging] parameters cannot borrow with a named lifetime; the request owns its fields",
+            ));
+        }
+        _ => (),
+    }
+    if reference.mutability.is_some() {
+        return Err(syn::Error::new_spanned(
+            ty,
+            "#[messaging] parameters cannot be mutable references; take the value instead",
+        ));
+    }
 
-        #[derive(Debug, PartialEq, Clone)]
-        pub enum #api_name #impl_generics #where_clause {
-            #(#api_variants),*
+    let owned: Type = match reference.elem.as_ref() {
+        Type::Path(path) if path.path.is_ident("str") => syn::parse_quote!(String),
+        Type::Slice(slice) => {
+            let elem = &slice.elem;
+            syn::parse_quote!(Vec<#elem>)
         }
+        Type::TraitObject(_) => {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "#[messaging] parameters cannot be trait objects; use a concrete type or a `Box<dyn Trait + Send>`",
+            ));
+        }
+        elem => elem.clone(),
+    };
+    check_owned_field_type(&owned)?;
+    Ok((owned, true))
+}
 
-        impl #impl_generics messaging_thread_pool::IdTargeted for #api_name #ty_generics #where_clause {
-            fn id(&self) -> u64 {
-                match self {
-                    #(
-                        #api_name::#request_names(req) => req.id(),
-                    )*
+/// Returns an error spanning the first part of the type that a request cannot own: a reference,
+/// a non `'static` lifetime or an `impl Trait`
+fn check_owned_field_type(ty: &Type) -> syn::Result<()> {
+    match ty {
+        Type::Reference(reference)
+            if reference
+                .lifetime
+                .as_ref()
+                .is_some_and(|lifetime| lifetime.ident == "static") =>
+        {
+            check_owned_field_type(&reference.elem)
+        }
+        Type::Reference(_) => Err(syn::Error::new_spanned(
+            ty,
+            "#[messaging] parameters cannot contain references other than `&'static`; the request owns its fields",
+        )),
+        Type::ImplTrait(_) => Err(syn::Error::new_spanned(
+            ty,
+            "#[messaging] parameters cannot be `impl Trait`; use a concrete type",
+        )),
+        Type::Path(type_path) => {
+            if let Some(qself) = &type_path.qself {
+                check_owned_field_type(&qself.ty)?;
+            }
+            for segment in &type_path.path.segments {
+                let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+                    continue;
+                };
+                for argument in &arguments.args {
+                    match argument {
+                        syn::GenericArgument::Lifetime(lifetime) if lifetime.ident != "static" => {
+                            return Err(syn::Error::new_spanned(
+                                lifetime,
+                                "#[messaging] parameters cannot contain lifetimes other than `'static`; the request owns its fields",
+                            ));
+                        }
+                        syn::GenericArgument::Type(ty) => check_owned_field_type(ty)?,
+                        syn::GenericArgument::AssocType(assoc) => {
+                            check_owned_field_type(&assoc.ty)?
+                        }
+                        _ => (),
+                    }
                 }
             }
+            Ok(())
         }
+        Type::Tuple(tuple) => tuple.elems.iter().try_for_each(check_owned_field_type),
+        Type::Array(array) => check_owned_field_type(&array.elem),
+        Type::Slice(slice) => check_owned_field_type(&slice.elem),
+        Type::Paren(paren) => check_owned_field_type(&paren.elem),
+        Type::Group(group) => check_owned_field_type(&group.elem),
+        _ => Ok(()),
     }
 }
 
-fn generate_init_struct(
-    init_name: &Ident,
-    struct_name: &Ident,
+/// Generates the call of the messaging method; each thread resource is borrowed around the call
+fn generate_method_call(
+    method_name: &Ident,
+    call_args: &[TokenStream],
+    thread_resource_params: &[ThreadResourceParam],
+) -> TokenStream {
+    thread_resource_params.iter().rev().fold(
+        quote! { self.#method_name(#(#call_args),*) },
+        |call, (resource, resource_type, _)| {
+            quote! {
+                messaging_thread_pool::with_thread_resource(|#resource: &mut #resource_type| #call)
+            }
+        },
+    )
+}
+
+/// Returns true if the method has no receiver and its first parameter is `&mut [&mut Self]`
+fn is_multi_item_signature(sig: &syn::Signature) -> bool {
+    if sig.receiver().is_some() {
+        return false;
+    }
+    let Some(FnArg::Typed(pat_type)) = sig.inputs.first() else {
+        return false;
+    };
+    let Type::Reference(slice_reference) = pat_type.ty.as_ref() else {
+        return false;
+    };
+    let Type::Slice(slice) = slice_reference.elem.as_ref() else {
+        return false;
+    };
+    let Type::Reference(item_reference) = slice.elem.as_ref() else {
+        return false;
+    };
+    slice_reference.mutability.is_some()
+        && item_reference.mutability.is_some()
+        && matches!(item_reference.elem.as_ref(), Type::Path(path) if path.path.is_ident("Self"))
+}
+
+/// The match arms of a multi item method for `multi_item_ids`, `process_multi_item_message` and
+/// `multi_item_rejected` respectively
+struct MultiItemArms {
+    ids: TokenStream,
+    process: TokenStream,
+    rejected: TokenStream,
+}
+
+fn generate_multi_item_arms(
+    api_name: &Ident,
+    request_name: &Ident,
+    method_name: &Ident,
+    ids: &syn::Member,
+    call_args: &[TokenStream],
+    response_name: &Ident,
     generics: &syn::Generics,
+) -> MultiItemArms {
+    let phantom_init = if !generics.params.is_empty() {
+        quote! { phantom: std::marker::PhantomData, }
+    } else {
+        quote! {}
+    };
+
+    let request_pattern = quote! {
+        #api_name::#request_name(messaging_thread_pool::request_response::RequestResponse::Request(request))
+    };
+
+    MultiItemArms {
+        ids: quote! {
+            #request_pattern => request.#ids.clone(),
+        },
+        process: quote! {
+            #request_pattern => {
+                let id = messaging_thread_pool::IdTargeted::id(&request);
+                let result = Ok(Self::#method_name(items, #(#call_args),*));
+                #response_name { id, result, #phantom_init }.into()
+            }
+        },
+        rejected: quote! {
+            #request_pattern => {
+                let id = messaging_thread_pool::IdTargeted::id(&request);
+                #response_name { id, result: Err(error), #phantom_init }.into()
+            }
+        },
+    }
+}
+
+/// Generates the multi item functions; `multi_item_rejected` is left out if the pool item
+/// provides its own
+fn generate_multi_item_fns(
+    arms: &[MultiItemArms],
+    key_type: &Type,
+    with_rejected: bool,
 ) -> TokenStream {
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-    let phantom_data = if !generics.params.is_empty() {
-        quote! { pub std::marker::PhantomData #ty_generics, }
+    let ids_arms = arms.iter().map(|arms| &arms.ids);
+    let process_arms = arms.iter().map(|arms| &arms.process);
+    let rejected_arms = arms.iter().map(|arms| &arms.rejected);
+    let rejected_fn = if with_rejected {
+        quote! {
+            fn multi_item_rejected(
+                request: Self::Api,
+                error: messaging_thread_pool::MultiItemError<#key_type>,
+            ) -> messaging_thread_pool::ThreadRequestResponse<Self> {
+                match request {
+                    #(#rejected_arms)*
+                    request => panic!("{:?} is not a multi item request", request),
+                }
+            }
+        }
     } else {
         quote! {}
     };
 
     quote! {
-        #[derive(Debug, PartialEq, Clone)]
-        pub struct #init_name #impl_generics (pub u64, #phantom_data) #where_clause;
-
-        impl #impl_generics messaging_thread_pool::IdTargeted for #init_name #ty_generics #where_clause {
-            fn id(&self) -> u64 {
-                self.0
+        fn multi_item_ids(request: &Self::Api) -> Vec<#key_type> {
+            match request {
+                #(#ids_arms)*
+                request => vec![messaging_thread_pool::IdTargeted::id(request)],
             }
         }
 
-        impl #impl_generics messaging_thread_pool::RequestWithResponse<#struct_name #ty_generics> for #init_name #ty_generics #where_clause {
-            type Response = messaging_thread_pool::thread_request_response::AddResponse;
+        fn process_multi_item_message(
+            items: &mut [&mut Self],
+            request: Self::Api,
+        ) -> messaging_thread_pool::ThreadRequestResponse<Self> {
+            match request {
+                #(#process_arms)*
+                request => panic!("{:?} is not a multi item request", request),
+            }
         }
 
-        impl #impl_generics From<#init_name #ty_generics> for messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics> #where_clause {
-            fn from(request: #init_name #ty_generics) -> Self {
-                messaging_thread_pool::ThreadRequestResponse::AddPoolItem(
-                    messaging_thread_pool::request_response::RequestResponse::Request(request)
-                )
+        #rejected_fn
+    }
+}
+
+fn generate_message_type_fn(
+    api_name: &Ident,
+    request_names: &[Ident],
+    protocol_variants: &[Ident],
+) -> TokenStream {
+    quote! {
+        fn message_type(request: &Self::Api) -> &'static str {
+            match request {
+                #(
+                    #api_name::#request_names(_) => stringify!(#request_names),
+                )*
+                #(
+                    #api_name::#protocol_variants(message) => message.message_type(),
+                )*
             }
         }
     }
 }
 
-fn generate_pool_item_trait_impl(
+/// The path of an item generated by `#[pool_protocol]` alongside the protocol's trait
+/// (`Health` gives `HealthApi` for the suffix `Api`)
+fn protocol_item_path(protocol: &syn::Path, suffix: &str) -> syn::Path {
+    let mut path = protocol.clone();
+    if let Some(segment) = path.segments.last_mut() {
+        segment.ident = format_ident!("{}{}", segment.ident, suffix);
+        segment.arguments = syn::PathArguments::None;
+    }
+    path
+}
+
+fn generate_implements_impl(
     self_ty: &Type,
-    init_name: &Ident,
     api_name: &Ident,
-    process_message_arms: &[TokenStream],
+    variant: &Ident,
+    protocol: &syn::Path,
+    key_type: &Type,
     generics: &syn::Generics,
-    custom_init_type: &Option<Type>,
-    shutdown_method: &Option<Ident>,
 ) -> TokenStream {
-    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-
-    let init_type_def = if let Some(init_type) = custom_init_type {
-        quote! { #init_type }
-    } else {
-        quote! { #init_name #ty_generics }
-    };
-
-    let new_pool_item_body = if custom_init_type.is_some() {
-        quote! { Ok(Self::new(request)) }
-    } else {
-        quote! { Ok(Self::new(request.0)) }
-    };
-
-    let shutdown_body = if let Some(method_name) = shutdown_method {
-        quote! { self.#method_name() }
-    } else {
-        quote! { Vec::default() }
-    };
+    let (impl_generics, _ty_generics, where_clause) = generics.split_for_impl();
+    let protocol_name = protocol_item_path(protocol, "Protocol");
+    let protocol_api = protocol_item_path(protocol, "Api");
 
     quote! {
-        impl #impl_generics messaging_thread_pool::PoolItem for #self_ty #where_clause {
-            type Init = #init_type_def;
-            type Api = #api_name #ty_generics;
-            type ThreadStartInfo = ();
-
-            fn process_message(&mut self, request: Self::Api) -> messaging_thread_pool::ThreadRequestResponse<Self> {
-                match request {
-                    #(#process_message_arms)*
-                    _ => panic!("Unexpected message or response in process_message"),
-                }
-            }
-
-            fn name() -> &'static str {
-                stringify!(#self_ty)
-            }
-
-            fn new_pool_item(request: Self::Init) -> Result<Self, messaging_thread_pool::pool_item::NewPoolItemError> {
-                #new_pool_item_body
+        impl #impl_generics messaging_thread_pool::Implements<#protocol_name> for #self_ty #where_clause {
+            fn into_api(message: #protocol_api<#key_type>) -> Self::Api {
+                #api_name::#variant(message)
             }
 
-            fn shutdown_pool(&self) -> Vec<messaging_thread_pool::thread_request_response::ThreadShutdownResponse> {
-                #shutdown_body
+            #[allow(unreachable_patterns)]
+            fn from_api(api: Self::Api) -> Option<#protocol_api<#key_type>> {
+                match api {
+                    #api_name::#variant(message) => Some(message),
+                    _ => None,
+                }
             }
         }
     }
 }
 
+/// Generates `response_error`, with an arm per method that returns a `Result` and one per protocol
+/// (whose API picks out its own fallible responses)
+fn generate_response_error_fn(
+    api_name: &Ident,
+    request_names: &[Ident],
+    protocol_variants: &[Ident],
+) -> TokenStream {
+    quote! {
+        fn response_error(response: &messaging_thread_pool::ThreadRequestResponse<Self>) -> Option<&dyn std::fmt::Debug> {
+            match response {
+                #(
+                    messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                        #api_name::#request_names(messaging_thread_pool::request_response::RequestResponse::Response(response))
+                    ) => response.result.as_ref().err().map(|error| error as &dyn std::fmt::Debug),
+                )*
+                #(
+                    messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                        #api_name::#protocol_variants(message)
+                    ) => message.response_error(),
+                )*
+                _ => None,
+            }
+        }
+    }
+}
+
+fn generate_is_stream_fn(api_name: &Ident, stream_request_names: &[Ident]) -> TokenStream {
+    quote! {
+        fn is_stream(request: &Self::Api) -> bool {
+            matches!(request, #( #api_name::#stream_request_names(_) )|*)
+        }
+    }
+}
+
+fn generate_process_message_stream_fn(arms: &[TokenStream]) -> TokenStream {
+    quote! {
+        fn process_message_stream(
+            &mut self,
+            request: Self::Api,
+            send: &mut dyn FnMut(messaging_thread_pool::ThreadRequestResponse<Self>) -> std::ops::ControlFlow<()>,
+        ) {
+            match request {
+                #(#arms)*
+                request => {
+                    let _ = send(self.process_message(request));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the item type `T` if the last parameter of the method is a `&mut StreamSink<T>`
+fn stream_sink_item_type(sig: &syn::Signature) -> Option<Type> {
+    let FnArg::Typed(pat_type) = sig.inputs.last()? else {
+        return None;
+    };
+    let Type::Reference(reference) = pat_type.ty.as_ref() else {
+        return None;
+    };
+    reference.mutability?;
+    let Type::Path(type_path) = reference.elem.as_ref() else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "StreamSink" {
+        return None;
+    }
+    last_generic_type_argument(&segment.arguments)
+}
+
+/// Returns the item type `T` of a stream method that returns `impl Iterator<Item = T>`,
+/// `impl IntoIterator<Item = T>` or `Vec<T>`
+fn stream_return_item_type(return_type: &Type) -> Option<Type> {
+    match return_type {
+        Type::ImplTrait(impl_trait) => impl_trait.bounds.iter().find_map(|bound| {
+            let syn::TypeParamBound::Trait(trait_bound) = bound else {
+                return None;
+            };
+            let syn::PathArguments::AngleBracketed(arguments) =
+                &trait_bound.path.segments.last()?.arguments
+            else {
+                return None;
+            };
+            arguments.args.iter().find_map(|argument| match argument {
+                syn::GenericArgument::AssocType(assoc) if assoc.ident == "Item" => {
+                    Some(assoc.ty.clone())
+                }
+                _ => None,
+            })
+        }),
+        Type::Path(type_path) => {
+            let segment = type_path.path.segments.last()?;
+            if segment.ident != "Vec" {
+                return None;
+            }
+            last_generic_type_argument(&segment.arguments)
+        }
+        _ => None,
+    }
+}
+
+fn last_generic_type_argument(arguments: &syn::PathArguments) -> Option<Type> {
+    let syn::PathArguments::AngleBracketed(arguments) = arguments else {
+        return None;
+    };
+    arguments
+        .args
+        .iter()
+        .rev()
+        .find_map(|argument| match argument {
+            syn::GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        })
+}
+
+fn generate_stream_response_impl(
+    response_name: &Ident,
+    item: &Type,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics messaging_thread_pool::StreamResponse for #response_name #ty_generics #where_clause {
+            type Item = #item;
+
+            fn into_chunk(self) -> messaging_thread_pool::StreamChunk<#item> {
+                self.result
+            }
+        }
+    }
+}
+
+fn generate_process_message_stream_arm(
+    api_name: &Ident,
+    request_name: &Ident,
+    method_name: &Ident,
+    field_args: &[TokenStream],
+    response_name: &Ident,
+    stream_sink_item: Option<&Type>,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let mut call_args = field_args.to_vec();
+
+    let phantom_init = if !generics.params.is_empty() {
+        quote! { phantom: std::marker::PhantomData, }
+    } else {
+        quote! {}
+    };
+
+    let produce_items = if let Some(item) = stream_sink_item {
+        call_args.push(quote! { &mut sink });
+        quote! {
+            let mut push = |item: #item| {
+                send(#response_name { id, result: messaging_thread_pool::StreamChunk::Item(item), #phantom_init }.into())
+            };
+            let mut sink = messaging_thread_pool::StreamSink::new(&mut push);
+            self.#method_name(#(#call_args),*);
+            if sink.is_closed() {
+                return;
+            }
+        }
+    } else {
+        quote! {
+            for item in self.#method_name(#(#call_args),*) {
+                if send(#response_name { id, result: messaging_thread_pool::StreamChunk::Item(item), #phantom_init }.into()).is_break() {
+                    return;
+                }
+            }
+        }
+    };
+
+    quote! {
+        #api_name::#request_name(request_response) => {
+            let request = match request_response {
+                messaging_thread_pool::request_response::RequestResponse::Request(r) => r,
+                _ => panic!("Unexpected message in process_message_stream (expected Request)"),
+            };
+            let id = messaging_thread_pool::IdTargeted::id(&request);
+            {
+                #produce_items
+            }
+            let _ = send(#response_name { id, result: messaging_thread_pool::StreamChunk::End, #phantom_init }.into());
+        }
+    }
+}
+
+/// Generates the client's methods for one messaging method
+///
+/// Each method sends a single request and returns its result; all but streaming methods also have a
+/// `_batch` variant that sends one request per item of its iterator. `result_type` is the type of
+/// the items of a streaming method.
+fn generate_client_methods(
+    method_name: &Ident,
+    method_attrs: &[syn::Attribute],
+    args: &MessagingArgs,
+    params: &[(Ident, Type)],
+    result_type: &Type,
+    struct_name: &Ident,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let request_name = &args.request_type;
+    let response_name = &args.response_type;
+    let docs = method_attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"));
+    let names: Vec<&Ident> = params.iter().map(|(name, _)| name).collect();
+    let types: Vec<&Type> = params.iter().map(|(_, ty)| ty).collect();
+    let phantom_data = if generics.params.is_empty() {
+        quote! {}
+    } else {
+        quote! { std::marker::PhantomData, }
+    };
+    let request = if args.named_fields {
+        let phantom_init = if generics.params.is_empty() {
+            quote! {}
+        } else {
+            quote! { phantom: std::marker::PhantomData, }
+        };
+        quote! { #request_name { #(#names,)* #phantom_init } }
+    } else {
+        quote! { #request_name(#(#names),*, #phantom_data) }
+    };
+    let pool_error = quote! { messaging_thread_pool::PoolError<#struct_name #ty_generics> };
+
+    if args.stream {
+        return quote! {
+            #(#docs)*
+            pub fn #method_name(&self, #(#names: #types),*) -> Result<Box<dyn Iterator<Item = #result_type> + '_>, #pool_error> {
+                self.pool.send_and_receive_stream(#request)
+            }
+        };
+    }
+
+    // a batch of requests with a single field (just the id) is an iterator of ids rather than of
+    // single element tuples
+    let (batch_item, batch_pattern) = if let [(name, ty)] = params {
+        (quote! { #ty }, quote! { #name })
+    } else {
+        (quote! { (#(#types),*) }, quote! { (#(#names),*) })
+    };
+    let batch_name = format_ident!("{}_batch", method_name);
+    let batch_doc = format!(
+        "Sends a `{}` for each item, returning the responses in the order that they complete",
+        request_name
+    );
+    quote! {
+        #(#docs)*
+        pub fn #method_name(&self, #(#names: #types),*) -> Result<#result_type, #pool_error> {
+            self.pool
+                .send_and_receive_one(#request)
+                .map(|response| response.result)
+        }
+
+        #[doc = #batch_doc]
+        pub fn #batch_name(
+            &self,
+            requests: impl IntoIterator<Item = #batch_item>,
+        ) -> Result<Vec<#response_name #ty_generics>, #pool_error> {
+            Ok(self
+                .pool
+                .send_and_receive(requests.into_iter().map(|#batch_pattern| #request))?
+                .collect())
+        }
+    }
+}
+
+/// Generates the client (`{Item}Client` by default), a typed handle that sends the pool item's
+/// messages through any `SenderAndReceiver`
+fn generate_client(
+    struct_name: &Ident,
+    client_name: &Ident,
+    client_methods: &[TokenStream],
+    generics: &syn::Generics,
+) -> TokenStream {
+    let (_, ty_generics, _) = generics.split_for_impl();
+
+    // the client borrows the pool (`'a`) and is generic over its type (`S`)
+    let mut client_generics = generics.clone();
+    client_generics.params.insert(0, syn::parse_quote!('a));
+    client_generics.params.push(syn::parse_quote!(S));
+    client_generics.make_where_clause().predicates.push(
+        syn::parse_quote!(S: messaging_thread_pool::SenderAndReceiver<#struct_name #ty_generics>),
+    );
+    let (client_impl_generics, client_ty_generics, client_where_clause) =
+        client_generics.split_for_impl();
+    let struct_where_clause = &generics.where_clause;
+    let client_doc = format!(
+        "A typed handle for sending messages to `{}` pool items through a pool (or a mock)",
+        struct_name
+    );
+
+    quote! {
+        #[doc = #client_doc]
+        pub struct #client_name #client_impl_generics #struct_where_clause {
+            pool: &'a S,
+            pool_item: std::marker::PhantomData<fn() -> #struct_name #ty_generics>,
+        }
+
+        impl #client_impl_generics Clone for #client_name #client_ty_generics #client_where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #client_impl_generics Copy for #client_name #client_ty_generics #client_where_clause {}
+
+        impl #client_impl_generics #client_name #client_ty_generics #client_where_clause {
+            /// Creates a client that sends its requests through the given pool
+            pub fn new(pool: &'a S) -> Self {
+                Self {
+                    pool,
+                    pool_item: std::marker::PhantomData,
+                }
+            }
+
+            /// The pool that the client sends its requests through
+            pub fn pool(&self) -> &'a S {
+                self.pool
+            }
+
+            #(#client_methods)*
+        }
+    }
+}
+
+fn generate_api_enum(
+    api_name: &Ident,
+    type_aliases: &[TokenStream],
+    api_variants: &[TokenStream],
+    request_names: &[Ident],
+    key_type: &Type,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        #(#type_aliases)*
+
+        #[derive(Debug, PartialEq, Clone)]
+        pub enum #api_name #impl_generics #where_clause {
+            #(#api_variants),*
+        }
+
+        impl #impl_generics messaging_thread_pool::IdTargeted<#key_type> for #api_name #ty_generics #where_clause {
+            fn id(&self) -> #key_type {
+                match self {
+                    #(
+                        #api_name::#request_names(req) => messaging_thread_pool::IdTargeted::id(req),
+                    )*
+                }
+            }
+
+            fn routing_key(&self) -> u64 {
+                match self {
+                    #(
+                        #api_name::#request_names(req) => messaging_thread_pool::IdTargeted::routing_key(req),
+                    )*
+                }
+            }
+        }
+    }
+}
+
+/// The enum (`{Item}Response` by default) holding a response to any of the requests of the pool
+/// item's API, and the conversions that let the API itself be sent as a request
+///
+/// A variant is named after the response type that it holds; the messages of each protocol are held
+/// by a variant named after the protocol's trait, as they are in the API.
+#[allow(clippy::too_many_arguments)]
+fn generate_api_response(
+    struct_name: &Ident,
+    api_name: &Ident,
+    response_enum_name: &Ident,
+    request_names: &[Ident],
+    response_names: &[Ident],
+    multi_request_names: &[Ident],
+    protocol_variants: &[Ident],
+    protocols: &[syn::Path],
+    key_type: &Type,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let protocol_apis: Vec<syn::Path> = protocols
+        .iter()
+        .map(|protocol| protocol_item_path(protocol, "Api"))
+        .collect();
+
+    // multi item requests are sent to all of the pool items that they target
+    let send_variant = if multi_request_names.is_empty() {
+        quote! { messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(api) }
+    } else {
+        quote! {
+            if matches!(api, #(#api_name::#multi_request_names(_))|*) {
+                messaging_thread_pool::ThreadRequestResponse::MessagePoolItems(api)
+            } else {
+                messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(api)
+            }
+        }
+    };
+
+    quote! {
+        #[derive(Debug, PartialEq, Clone)]
+        pub enum #response_enum_name #impl_generics #where_clause {
+            #(#response_names(#response_names #ty_generics),)*
+            #(#protocol_variants(#protocol_apis<#key_type>),)*
+        }
+
+        impl #impl_generics From<messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics>> for #response_enum_name #ty_generics #where_clause {
+            fn from(response: messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics>) -> Self {
+                match response {
+                    #(
+                        messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                            #api_name::#request_names(
+                                messaging_thread_pool::request_response::RequestResponse::Response(response)
+                            )
+                        ) => #response_enum_name::#response_names(response),
+                    )*
+                    #(
+                        messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                            #api_name::#protocol_variants(message)
+                        ) => #response_enum_name::#protocol_variants(message),
+                    )*
+                    _ => panic!("Unexpected response type"),
+                }
+            }
+        }
+
+        impl #impl_generics From<#response_enum_name #ty_generics> for messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics> #where_clause {
+            fn from(response: #response_enum_name #ty_generics) -> Self {
+                match response {
+                    #(
+                        #response_enum_name::#response_names(response) => response.into(),
+                    )*
+                    #(
+                        #response_enum_name::#protocol_variants(message) => {
+                            messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(#api_name::#protocol_variants(message))
+                        }
+                    )*
+                }
+            }
+        }
+
+        impl #impl_generics From<#api_name #ty_generics> for messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics> #where_clause {
+            fn from(api: #api_name #ty_generics) -> Self {
+                #send_variant
+            }
+        }
+
+        impl #impl_generics messaging_thread_pool::RequestWithResponse<#struct_name #ty_generics> for #api_name #ty_generics #where_clause {
+            type Response = #response_enum_name #ty_generics;
+        }
+    }
+}
+
+/// The generated Init request; the fields that it holds and the arguments that `new` is called with
+struct InitRequest {
+    name: Ident,
+    fields: Vec<(Ident, Type)>,
+    new_args: Vec<TokenStream>,
+    /// The fields are named after the parameters of `new`
+    named_fields: bool,
+    /// `new` returns a `Result` whose error is passed on in the `AddResponse`
+    fallible: bool,
+}
+
+/// Builds the Init request from the parameters of the pool item's `new` function
+///
+/// The first parameter is the pool item's id; the others become fields of the request, owned as
+/// they are for a `#[messaging]` method. If `new` is not in the impl block, or it is passed a
+/// custom init type, the request only holds the id. A request that only holds the id stays a tuple
+/// struct (`{Item}Init(id)`) even when the fields are named.
+fn init_request(
+    name: Ident,
+    new_sig: Option<&syn::Signature>,
+    custom_init: bool,
+    key_type: &Type,
+    named_fields: bool,
+) -> syn::Result<InitRequest> {
+    let fallible = new_sig.is_some_and(|sig| returns_result(&sig.output));
+    let params: Vec<&syn::PatType> = new_sig
+        .filter(|_| !custom_init)
+        .into_iter()
+        .flat_map(|sig| &sig.inputs)
+        .filter_map(|input| match input {
+            FnArg::Typed(pat_type) => Some(pat_type),
+            FnArg::Receiver(_) => None,
+        })
+        .skip(1)
+        .collect();
+    let named_fields = named_fields && !params.is_empty();
+
+    let names: Vec<Ident> = params
+        .iter()
+        .enumerate()
+        .map(|(position, pat_type)| match pat_type.pat.as_ref() {
+            syn::Pat::Ident(pat_ident) => pat_ident.ident.clone(),
+            _ => format_ident!("arg{}", position + 1),
+        })
+        .collect();
+    // the id is renamed if one of the other parameters is already called `id`
+    let id_name = if names.iter().any(|name| name == "id") {
+        format_ident!("pool_item_id")
+    } else {
+        format_ident!("id")
+    };
+
+    let member = |position: usize, name: &Ident| {
+        if named_fields {
+            syn::Member::Named(name.clone())
+        } else {
+            syn::Member::Unnamed(syn::Index::from(position))
+        }
+    };
+    let id_member = member(0, &id_name);
+    let mut fields = vec![(id_name, key_type.clone())];
+    let mut new_args = vec![quote! { request.#id_member }];
+    for (name, pat_type) in names.into_iter().zip(params) {
+        // a borrowed parameter is passed a reference to the field that owns it
+        let (field_type, borrowed) = owned_field_type(&pat_type.ty)?;
+        let reference = if borrowed {
+            quote! { & }
+        } else {
+            quote! {}
+        };
+        let field = member(fields.len(), &name);
+        new_args.push(quote! { #reference request.#field });
+        fields.push((name, field_type));
+    }
+
+    Ok(InitRequest {
+        name,
+        fields,
+        new_args,
+        named_fields,
+        fallible,
+    })
+}
+
+/// Whether a function returns a `Result` (or an alias of one, such as `io::Result`)
+///
+/// Only the last segment of the return type's path is looked at, so any type called `Result` is
+/// taken to be one; a `#[messaging]` method returning some other `Result` type opts out of having
+/// its errors reported with `no_error_report`.
+pub(crate) fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => matches!(ty.as_ref(), Type::Path(type_path)
+            if type_path.path.segments.last().is_some_and(|segment| segment.ident == "Result")),
+        ReturnType::Default => false,
+    }
+}
+
+fn generate_init_struct(
+    init: &InitRequest,
+    struct_name: &Ident,
+    key_type: &Type,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let init_name = &init.name;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let field_names: Vec<&Ident> = init.fields.iter().map(|(name, _)| name).collect();
+    let field_types: Vec<&Type> = init.fields.iter().map(|(_, ty)| ty).collect();
+
+    let (init_struct, id_field) = if init.named_fields {
+        let (phantom_field, phantom_init) = if generics.params.is_empty() {
+            (quote! {}, quote! {})
+        } else {
+            (
+                quote! { pub phantom: std::marker::PhantomData #ty_generics, },
+                quote! { phantom: std::marker::PhantomData, },
+            )
+        };
+        let id_name = field_names[0];
+        (
+            quote! {
+                #[derive(Debug, PartialEq, Clone)]
+                pub struct #init_name #impl_generics #where_clause {
+                    #(pub #field_names: #field_types,)*
+                    #phantom_field
+                }
+
+                impl #impl_generics #init_name #ty_generics #where_clause {
+                    #[allow(clippy::too_many_arguments)]
+                    pub fn new(#(#field_names: #field_types),*) -> Self {
+                        Self { #(#field_names,)* #phantom_init }
+                    }
+                }
+            },
+            quote! { #id_name },
+        )
+    } else {
+        let phantom_data = if !generics.params.is_empty() {
+            quote! { pub std::marker::PhantomData #ty_generics, }
+        } else {
+            quote! {}
+        };
+        (
+            quote! {
+                #[derive(Debug, PartialEq, Clone)]
+                pub struct #init_name #impl_generics (#(pub #field_types),*, #phantom_data) #where_clause;
+            },
+            quote! { 0 },
+        )
+    };
+
+    quote! {
+        #init_struct
+
+        impl #impl_generics messaging_thread_pool::IdTargeted<#key_type> for #init_name #ty_generics #where_clause {
+            fn id(&self) -> #key_type {
+                self.#id_field.clone()
+            }
+        }
+
+        impl #impl_generics messaging_thread_pool::RequestWithResponse<#struct_name #ty_generics> for #init_name #ty_generics #where_clause {
+            type Response = messaging_thread_pool::thread_request_response::AddResponse<#key_type>;
+        }
+
+        impl #impl_generics From<#init_name #ty_generics> for messaging_thread_pool::ThreadRequestResponse<#struct_name #ty_generics> #where_clause {
+            fn from(request: #init_name #ty_generics) -> Self {
+                messaging_thread_pool::ThreadRequestResponse::AddPoolItem(
+                    messaging_thread_pool::request_response::RequestResponse::Request(request)
+                )
+            }
+        }
+    }
+}
+
+fn generate_pool_item_trait_impl(
+    self_ty: &Type,
+    init: &InitRequest,
+    api_name: &Ident,
+    process_message_arms: &[TokenStream],
+    overrides: &[TokenStream],
+    generics: &syn::Generics,
+    args: &PoolItemArgs,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let custom_init_type = &args.init_type;
+    let shutdown_method = &args.shutdown_method;
+    let key_type = args.key_type();
+
+    let init_name = &init.name;
+    let init_type_def = if let Some(init_type) = custom_init_type {
+        quote! { #init_type }
+    } else {
+        quote! { #init_name #ty_generics }
+    };
+
+    let new_call = if custom_init_type.is_some() {
+        quote! { Self::new(request) }
+    } else {
+        let new_args = &init.new_args;
+        quote! { Self::new(#(#new_args),*) }
+    };
+    // the error of a fallible `new` is kept in the `NewPoolItemError` so that callers can inspect it
+    let new_pool_item_body = if init.fallible {
+        quote! { #new_call.map_err(messaging_thread_pool::pool_item::NewPoolItemError::from_error) }
+    } else {
+        quote! { Ok(#new_call) }
+    };
+
+    let shutdown_body = if let Some(method_name) = shutdown_method {
+        quote! { self.#method_name() }
+    } else {
+        quote! { Vec::default() }
+    };
+
+    let max_batch_size_fn = if let Some(max_batch_size) = &args.max_batch_size {
+        quote! {
+            fn max_batch_size() -> usize {
+                #max_batch_size
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let thread_start_info_type = args
+        .thread_start_info_type
+        .clone()
+        .unwrap_or_else(|| syn::parse_quote!(()));
+
+    // the hooks that are forwarded to a function named in `#[pool_item]`; the trait's default is
+    // kept for any that are not named (so batching is only enabled for pool items that ask for it)
+    let hook_fns = [
+        forward_hook(
+            &args.batch_method,
+            quote! { fn process_batch(&mut self, requests: Vec<Self::Api>) -> Vec<messaging_thread_pool::ThreadRequestResponse<Self>> },
+            quote! { self. },
+            quote! { requests },
+        ),
+        forward_hook(
+            &args.on_shutdown_method,
+            quote! { fn on_shutdown(&mut self, context: &mut messaging_thread_pool::ShutdownContext<'_, Self>) },
+            quote! { self. },
+            quote! { context },
+        ),
+        forward_hook(
+            &args.added_method,
+            quote! { fn on_added(&mut self) },
+            quote! { self. },
+            quote! {},
+        ),
+        forward_hook(
+            &args.removed_method,
+            quote! { fn on_removed(&mut self, reason: messaging_thread_pool::RemovalReason) },
+            quote! { self. },
+            quote! { reason },
+        ),
+        forward_hook(
+            &args.message_error_method,
+            quote! { fn on_message_error(&mut self, error: messaging_thread_pool::MessageError<Self::Key>) },
+            quote! { self. },
+            quote! { error },
+        ),
+        forward_hook(
+            &args.thread_start_method,
+            quote! { fn thread_start() -> Option<Self::ThreadStartInfo> },
+            quote! { Self:: },
+            quote! {},
+        ),
+        forward_hook(
+            &args.thread_stop_method,
+            quote! { fn thread_stop(thread_start_info: Self::ThreadStartInfo) -> Option<messaging_thread_pool::ThreadStopData> },
+            quote! { Self:: },
+            quote! { thread_start_info },
+        ),
+        forward_hook(
+            &args.error_response_method,
+            quote! {
+                fn pool_item_error_response(
+                    pool_item_id: &Self::Key,
+                    message_type: &'static str,
+                    error: &dyn std::fmt::Debug,
+                    thread_start_info: Option<&mut Self::ThreadStartInfo>,
+                )
+            },
+            quote! { Self:: },
+            quote! { pool_item_id, message_type, error, thread_start_info },
+        ),
+        forward_hook(
+            &args.id_not_found_method,
+            quote! { fn id_not_found(request: &Self::Api) -> messaging_thread_pool::ThreadRequestResponse<Self> },
+            quote! { Self:: },
+            quote! { request },
+        ),
+        forward_hook(
+            &args.pre_process_method,
+            quote! { fn pool_item_pre_process(pool_item_id: Self::Key, thread_start_info: &mut Self::ThreadStartInfo) },
+            quote! { Self:: },
+            quote! { pool_item_id, thread_start_info },
+        ),
+        forward_hook(
+            &args.post_process_method,
+            quote! { fn pool_item_post_process(pool_item_id: Self::Key, thread_start_info: &mut Self::ThreadStartInfo) },
+            quote! { Self:: },
+            quote! { pool_item_id, thread_start_info },
+        ),
+        forward_hook(
+            &args.id_thread_router_method,
+            quote! { fn id_thread_router(id: u64, thread_count: usize) -> u64 },
+            quote! { Self:: },
+            quote! { id, thread_count },
+        ),
+        forward_hook(
+            &args.multi_item_rejected_method,
+            quote! {
+                fn multi_item_rejected(
+                    request: Self::Api,
+                    error: messaging_thread_pool::MultiItemError<Self::Key>,
+                ) -> messaging_thread_pool::ThreadRequestResponse<Self>
+            },
+            quote! { Self:: },
+            quote! { request, error },
+        ),
+    ];
+    let name = match &args.name {
+        Some(name) => quote! { #name },
+        None => quote! { stringify!(#self_ty) },
+    };
+    let scheduling_fn = if let Some(budget) = &args.fair_budget {
+        quote! {
+            fn scheduling() -> messaging_thread_pool::Scheduling {
+                messaging_thread_pool::Scheduling::Fair { budget: #budget }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        impl #impl_generics messaging_thread_pool::PoolItem for #self_ty #where_clause {
+            type Key = #key_type;
+            type Init = #init_type_def;
+            type Api = #api_name #ty_generics;
+            type ThreadStartInfo = #thread_start_info_type;
+
+            fn process_message(&mut self, request: Self::Api) -> messaging_thread_pool::ThreadRequestResponse<Self> {
+                match request {
+                    #(#process_message_arms)*
+                    _ => panic!("Unexpected message or response in process_message"),
+                }
+            }
+
+            #(#overrides)*
+
+            #max_batch_size_fn
+
+            #scheduling_fn
+
+            fn name() -> &'static str {
+                #name
+            }
+
+            fn new_pool_item(request: Self::Init) -> Result<Self, messaging_thread_pool::pool_item::NewPoolItemError> {
+                #new_pool_item_body
+            }
+
+            fn shutdown_pool(&self) -> Vec<messaging_thread_pool::thread_request_response::ThreadShutdownResponse> {
+                #shutdown_body
+            }
+
+            #(#hook_fns)*
+        }
+    }
+}
+
+/// Implements a `PoolItem` hook by calling the function named for it in `#[pool_item]`
+///
+/// The hook is given by its `signature`; its body calls the named function on `receiver`
+/// (`self.` or `Self::`) with `arguments`. Nothing is generated if no function was named, so the
+/// trait's default is kept.
+fn forward_hook(
+    method: &Option<Ident>,
+    signature: TokenStream,
+    receiver: TokenStream,
+    arguments: TokenStream,
+) -> TokenStream {
+    match method {
+        Some(method) => quote! {
+            #signature {
+                #receiver #method(#arguments)
+            }
+        },
+        None => quote! {},
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use syn::parse_quote;
 
     #[test]
-    fn test_generate_pool_item_impl_basic() {
+    fn test_forward_hook() {
+        let hook = |method: Option<Ident>| {
+            forward_hook(
+                &method,
+                quote! { fn on_removed(&mut self, reason: RemovalReason) },
+                quote! { self. },
+                quote! { reason },
+            )
+            .to_string()
+        };
+
+        assert_eq!(
+            "fn on_removed (& mut self , reason : RemovalReason) { self . removed (reason) }",
+            hook(Some(parse_quote!(removed)))
+        );
+        assert!(hook(None).is_empty());
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_basic() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(MyRequest, MyResponse)]
+                pub fn my_method(&self, arg1: u32) -> bool {
+                    true
+                }
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        // Check for key generated components
+        assert!(output_str.contains("struct MyRequest"));
+        assert!(output_str.contains("struct MyResponse"));
+        assert!(output_str.contains("enum MyStructApi"));
+        assert!(output_str.contains("impl messaging_thread_pool :: PoolItem for MyStruct"));
+        assert!(output_str.contains("MyStruct_MyRequest_RequestResponse"));
+        assert!(output_str.contains("MyStructApi :: MyRequest (_) => stringify ! (MyRequest)"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_multiple_methods() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req1, Resp1)]
+                pub fn method1(&self) {}
+
+                #[messaging(Req2, Resp2)]
+                pub fn method2(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("struct Req1"));
+        assert!(output_str.contains("struct Resp1"));
+        assert!(output_str.contains("struct Req2"));
+        assert!(output_str.contains("struct Resp2"));
+        assert!(output_str.contains("Req1 (MyStruct_Req1_RequestResponse)"));
+        assert!(output_str.contains("Req2 (MyStruct_Req2_RequestResponse)"));
+        // Check that method call is generated
+        assert!(output_str.contains("self . method1"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_priority() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(UrgentReq, UrgentResp, priority = high)]
+                pub fn urgent(&self) {}
+
+                #[messaging(Req, Resp)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "const PRIORITY : messaging_thread_pool :: Priority = messaging_thread_pool :: Priority :: High ;"
+        ));
+        // only the request with a priority specified overrides the default
+        assert_eq!(1, output_str.matches("const PRIORITY").count());
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_routing_key() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp, routing_key = user_id)]
+                pub fn method(&self, name: String, user_id: u64) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "fn routing_key (& self) -> u64 { messaging_thread_pool :: PoolKey :: routing_hash (& self . 2) }"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_unknown_routing_key() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp, routing_key = user)]
+                pub fn method(&self, user_id: u64) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("routing_key must name one of the method's parameters"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_multi() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(TransferReq, TransferResp, multi)]
+                pub fn transfer(items: &mut [&mut Self], amount: i64) -> bool { true }
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("pub struct TransferReq (pub Vec < u64 > , pub i64 ,)"));
+        assert!(output_str.contains("ThreadRequestResponse :: MessagePoolItems"));
+        assert!(output_str.contains(
+            "pub result : Result < bool , messaging_thread_pool :: MultiItemError < u64 > >"
+        ));
+        assert!(output_str.contains("fn multi_item_ids"));
+        assert!(output_str.contains("Self :: transfer (items , request . 1)"));
+        assert!(output_str.contains("fn multi_item_rejected"));
+        // the request's id is its first id, and its constructor rejects an empty list of ids
+        assert!(!output_str.contains("unwrap_or_default"));
+        assert!(output_str.contains(
+            "pub fn new (ids : Vec < u64 > , amount : i64) -> Result < Self , messaging_thread_pool :: MultiItemError < u64 >>"
+        ));
+        assert!(
+            output_str.contains("return Err (messaging_thread_pool :: MultiItemError :: NoIds)")
+        );
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_multi_with_multi_item_rejected() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(TransferReq, TransferResp, multi)]
+                pub fn transfer(items: &mut [&mut Self], amount: i64) -> bool { true }
+            }
+        };
+        let args = PoolItemArgs {
+            multi_item_rejected_method: Some(parse_quote!(rejected)),
+            ..Default::default()
+        };
+
+        let output_str = generate_pool_item_impl(input, args).to_string();
+
+        // the pool item's own function replaces the generated one
+        assert_eq!(1, output_str.matches("fn multi_item_rejected").count());
+        assert!(output_str.contains("{ Self :: rejected (request , error) }"));
+        assert!(output_str.contains("fn multi_item_ids"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_multi_requires_items_parameter() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(TransferReq, TransferResp, multi)]
+                pub fn transfer(&mut self, amount: i64) -> bool { true }
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("compile_error"));
+        assert!(output_str.contains("multi methods must be associated functions"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_stream() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(StreamReq, StreamResp, stream)]
+                pub fn items(&self, count: usize) -> impl Iterator<Item = u32> + '_ {
+                    (0..count as u32)
+                }
+
+                #[messaging(Req, Resp)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("pub result : messaging_thread_pool :: StreamChunk < u32 >"));
+        assert!(output_str.contains("impl messaging_thread_pool :: StreamResponse for StreamResp"));
+        assert!(output_str.contains("fn process_message_stream"));
+        assert!(output_str.contains("for item in self . items (request . 1)"));
+        assert!(output_str.contains("matches ! (request , MyStructApi :: StreamReq (_))"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_stream_with_batch() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(StreamReq, StreamResp, stream)]
+                pub fn items(&self) -> Vec<u32> { vec![] }
+            }
+        };
+
+        let args = PoolItemArgs {
+            max_batch_size: Some(parse_quote!(16)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("compile_error"));
+        assert!(output_str.contains("stream methods cannot be used with `Batch` or `MaxBatchSize`"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_stream_sink() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(StreamReq, StreamResp, stream)]
+                pub fn items(&self, count: usize, sink: &mut StreamSink<String>) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        // the sink is not part of the request
+        assert!(output_str.contains("pub struct StreamReq (pub u64 , pub usize ,)"));
+        assert!(output_str.contains("StreamChunk < String >"));
+        assert!(output_str.contains("self . items (request . 1 , & mut sink)"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_stream_invalid_return_type() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(StreamReq, StreamResp, stream)]
+                pub fn items(&self) -> u32 { 0 }
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("compile_error"));
+        assert!(output_str.contains("stream methods must return"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_no_stream_no_process_message_stream() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(!output_str.contains("fn process_message_stream"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_no_return_type() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("pub result : ()"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_thread_resource_params() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&self, #[thread_resource] cache: &mut Cache, key: u32, #[thread_resource] names: &Names) -> bool {
+                    true
+                }
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        // the resources are not part of the request and the attribute is removed from the method
+        assert!(output_str.contains("pub struct Req (pub u64 , pub u32 ,)"));
+        assert!(output_str.contains(
+            "pub fn method (& self , cache : & mut Cache , key : u32 , names : & Names)"
+        ));
+        assert!(output_str.contains(
+            "let result = messaging_thread_pool :: with_thread_resource (| thread_resource_0 : & mut Cache | messaging_thread_pool :: with_thread_resource (| thread_resource_2 : & mut Names | self . method (thread_resource_0 , request . 1 , thread_resource_2))) ;"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_thread_resource_errors() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&self, #[thread_resource] cache: Cache) {}
+            }
+        };
+        let output_str = generate_pool_item_impl(input, PoolItemArgs::default()).to_string();
+        assert!(output_str.contains("#[thread_resource] parameters must be references"));
+
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp, stream)]
+                pub fn method(&self, #[thread_resource] cache: &mut Cache) -> Vec<u32> { vec![] }
+            }
+        };
+        let output_str = generate_pool_item_impl(input, PoolItemArgs::default()).to_string();
+        assert!(output_str.contains(
+            "#[thread_resource] parameters are not supported by stream or multi methods"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_error_not_struct() {
+        // This is hard to test directly because parse_macro_input! expects ItemImpl which has a Type.
+        // But we can pass an ItemImpl where self_ty is not a path.
+        // However, syn::parse_quote! usually produces valid types.
+        // Let's try to construct one manually or just skip this edge case if it's hard to trigger with parse_quote.
+        // Actually, `impl &str` is valid syntax but not a struct path.
+        let input: ItemImpl = parse_quote! {
+            impl &str {
+                #[messaging(Req, Resp)]
+                fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+        assert!(output_str.contains("compile_error ! (\"Expected struct type\")"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_invalid_attr() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(OnlyOneArg)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+        assert!(output_str.contains("compile_error"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_ignore_const() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                const MY_CONST: u32 = 0;
+
+                #[messaging(Req, Resp)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+        // Should still generate for method
+        assert!(output_str.contains("struct Req"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_no_attr() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                pub fn method(&self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+        // Should generate empty Api enum and PoolItem impl
+        assert!(output_str.contains("enum MyStructApi"));
+        assert!(output_str.contains("impl messaging_thread_pool :: PoolItem for MyStruct"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_generic() {
+        let input: ItemImpl = parse_quote! {
+            impl<T> MyGenericStruct<T> {
+                #[messaging(Req, Resp)]
+                pub fn method(&self, arg: T) -> T {
+                    arg
+                }
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("struct Req < T >"));
+        assert!(output_str.contains("struct Resp < T >"));
+        assert!(output_str.contains("enum MyGenericStructApi < T >"));
+        assert!(output_str
+            .contains("impl < T > messaging_thread_pool :: PoolItem for MyGenericStruct < T >"));
+        // Check for PhantomData presence, might be fully qualified
+        assert!(output_str.contains("PhantomData"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_custom_init() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&self) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            init_type: Some(syn::parse_quote!(MyCustomInit)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("type Init = MyCustomInit ;"));
+        assert!(!output_str.contains("struct MyStructInit")); // Should not generate default init
+        assert!(output_str.contains("Ok (Self :: new (request))")); // Should pass request directly
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_custom_key() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&self, arg: u32) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            key_type: Some(syn::parse_quote!(String)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("type Key = String ;"));
+        assert!(output_str.contains("pub struct Req (pub String , pub u32 ,)"));
+        assert!(output_str.contains("pub struct MyStructInit (pub String ,)"));
+        assert!(output_str.contains("pub id : String ,"));
+        assert!(output_str.contains("messaging_thread_pool :: IdTargeted < String > for Req"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_batch() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            batch_method: Some(parse_quote!(apply_batch)),
+            max_batch_size: Some(parse_quote!(16)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(
+            output_str.contains("fn process_batch (& mut self , requests : Vec < Self :: Api >)")
+        );
+        assert!(output_str.contains("self . apply_batch (requests)"));
+        assert!(output_str.contains("fn max_batch_size () -> usize { 16 }"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_fair_budget() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            fair_budget: Some(parse_quote!(4)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "fn scheduling () -> messaging_thread_pool :: Scheduling { messaging_thread_pool :: Scheduling :: Fair { budget : 4 } }"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_on_shutdown() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            on_shutdown_method: Some(parse_quote!(release)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "fn on_shutdown (& mut self , context : & mut messaging_thread_pool :: ShutdownContext < '_ , Self >) { self . release (context) }"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_lifecycle_hooks() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            added_method: Some(parse_quote!(added)),
+            removed_method: Some(parse_quote!(removed)),
+            message_error_method: Some(parse_quote!(failed)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("fn on_added (& mut self) { self . added () }"));
+        assert!(output_str.contains(
+            "fn on_removed (& mut self , reason : messaging_thread_pool :: RemovalReason) { self . removed (reason) }"
+        ));
+        assert!(output_str.contains(
+            "fn on_message_error (& mut self , error : messaging_thread_pool :: MessageError < Self :: Key >) { self . failed (error) }"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_thread_lifecycle() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
+            }
+        };
+
+        let args = PoolItemArgs {
+            thread_start_info_type: Some(parse_quote!(Stats)),
+            thread_start_method: Some(parse_quote!(start)),
+            thread_stop_method: Some(parse_quote!(stop)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains("type ThreadStartInfo = Stats ;"));
+        assert!(output_str.contains(
+            "fn thread_start () -> Option < Self :: ThreadStartInfo > { Self :: start () }"
+        ));
+        assert!(output_str.contains(
+            "fn thread_stop (thread_start_info : Self :: ThreadStartInfo) -> Option < messaging_thread_pool :: ThreadStopData > { Self :: stop (thread_start_info) }"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_fallible_new() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                pub fn new(id: u64, limit: u32) -> Result<Self, LimitError> { todo!() }
+
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
+            }
+        };
+
+        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "Self :: new (request . 0 , request . 1) . map_err (messaging_thread_pool :: pool_item :: NewPoolItemError :: from_error)"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_fallible_new_with_custom_init() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                pub fn new(request: MyInit) -> Result<Self, String> { todo!() }
+            }
+        };
+
+        let args = PoolItemArgs {
+            init_type: Some(parse_quote!(MyInit)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "Self :: new (request) . map_err (messaging_thread_pool :: pool_item :: NewPoolItemError :: from_error)"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_result_methods_report_errors() {
         let input: ItemImpl = parse_quote! {
             impl MyStruct {
-                #[messaging(MyRequest, MyResponse)]
-                pub fn my_method(&self, arg1: u32) -> bool {
-                    true
-                }
+                #[messaging(SendReq, SendResp)]
+                pub fn send(&mut self) -> Result<usize, SendError> { Ok(0) }
+
+                #[messaging(ReadReq, ReadResp)]
+                pub fn read(&self) -> std::io::Result<u8> { Ok(0) }
+
+                #[messaging(CountReq, CountResp)]
+                pub fn count(&self) -> usize { 0 }
+
+                #[messaging(CheckReq, CheckResp, no_error_report)]
+                pub fn check(&self) -> validation::Result { validation::Result::Pass }
             }
         };
 
         let output = generate_pool_item_impl(input, PoolItemArgs::default());
         let output_str = output.to_string();
 
-        // Check for key generated components
-        assert!(output_str.contains("struct MyRequest"));
-        assert!(output_str.contains("struct MyResponse"));
-        assert!(output_str.contains("enum MyStructApi"));
-        assert!(output_str.contains("impl messaging_thread_pool :: PoolItem for MyStruct"));
-        assert!(output_str.contains("MyStruct_MyRequest_RequestResponse"));
+        // only the methods that return a `Result` (and have not opted out) have an arm in
+        // `response_error`
+        let response_error = &output_str[output_str
+            .find("fn response_error")
+            .expect("response_error to be generated")..];
+        let response_error = &response_error[..response_error.find("_ => None").unwrap()];
+        assert!(response_error.contains("MyStructApi :: SendReq"));
+        assert!(response_error.contains("MyStructApi :: ReadReq"));
+        assert!(!response_error.contains("MyStructApi :: CountReq"));
+        assert!(!response_error.contains("MyStructApi :: CheckReq"));
+        // an infallible `new` is wrapped in `Ok`
+        assert!(!output_str.contains("map_err"));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_multiple_methods() {
+    fn test_generate_pool_item_impl_implements_protocols() {
         let input: ItemImpl = parse_quote! {
             impl MyStruct {
-                #[messaging(Req1, Resp1)]
-                pub fn method1(&self) {}
-
-                #[messaging(Req2, Resp2)]
-                pub fn method2(&self) {}
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
             }
         };
 
-        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let args = PoolItemArgs {
+            implements: vec![parse_quote!(Health), parse_quote!(protocols::Snapshot)],
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
         let output_str = output.to_string();
 
-        assert!(output_str.contains("struct Req1"));
-        assert!(output_str.contains("struct Resp1"));
-        assert!(output_str.contains("struct Req2"));
-        assert!(output_str.contains("struct Resp2"));
-        assert!(output_str.contains("Req1 (MyStruct_Req1_RequestResponse)"));
-        assert!(output_str.contains("Req2 (MyStruct_Req2_RequestResponse)"));
-        // Check that method call is generated
-        assert!(output_str.contains("self . method1"));
+        assert!(output_str.contains("Health (HealthApi < u64 >)"));
+        assert!(output_str.contains("Snapshot (protocols :: SnapshotApi < u64 >)"));
+        assert!(output_str.contains(
+            "impl messaging_thread_pool :: Implements < protocols :: SnapshotProtocol > for MyStruct"
+        ));
+        assert!(output_str.contains(
+            "MyStructApi :: Health (message) => messaging_thread_pool :: ThreadRequestResponse :: MessagePoolItem (MyStructApi :: Health (message . process (self)))"
+        ));
+        assert!(output_str.contains("MyStructApi :: Health (message) => message . message_type ()"));
+        // the protocols report the errors of their own responses
+        assert!(output_str.contains(
+            "messaging_thread_pool :: ThreadRequestResponse :: MessagePoolItem (MyStructApi :: Health (message)) => message . response_error ()"
+        ));
+        assert!(output_str.contains(
+            "MyStructApi :: Snapshot (req) => messaging_thread_pool :: IdTargeted :: id (req)"
+        ));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_no_return_type() {
+    fn test_generate_pool_item_impl_error_response_hook() {
         let input: ItemImpl = parse_quote! {
             impl MyStruct {
                 #[messaging(Req, Resp)]
-                pub fn method(&self) {}
+                pub fn method(&mut self) {}
             }
         };
 
-        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let args = PoolItemArgs {
+            error_response_method: Some(parse_quote!(failed)),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
         let output_str = output.to_string();
 
-        assert!(output_str.contains("pub result : ()"));
+        assert!(output_str.contains(
+            "{ Self :: failed (pool_item_id , message_type , error , thread_start_info) }"
+        ));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_error_not_struct() {
-        // This is hard to test directly because parse_macro_input! expects ItemImpl which has a Type.
-        // But we can pass an ItemImpl where self_ty is not a path.
-        // However, syn::parse_quote! usually produces valid types.
-        // Let's try to construct one manually or just skip this edge case if it's hard to trigger with parse_quote.
-        // Actually, `impl &str` is valid syntax but not a struct path.
+    fn test_generate_pool_item_impl_routing_and_processing_hooks() {
         let input: ItemImpl = parse_quote! {
-            impl &str {
+            impl MyStruct {
                 #[messaging(Req, Resp)]
-                fn method(&self) {}
+                pub fn method(&mut self) {}
             }
         };
 
-        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let args = PoolItemArgs {
+            id_not_found_method: Some(parse_quote!(missing)),
+            pre_process_method: Some(parse_quote!(enter)),
+            post_process_method: Some(parse_quote!(exit)),
+            id_thread_router_method: Some(parse_quote!(route)),
+            multi_item_rejected_method: Some(parse_quote!(rejected)),
+            name: Some(parse_quote!("sessions")),
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
         let output_str = output.to_string();
-        assert!(output_str.contains("compile_error ! (\"Expected struct type\")"));
+
+        assert!(output_str.contains(
+            "fn id_not_found (request : & Self :: Api) -> messaging_thread_pool :: ThreadRequestResponse < Self > { Self :: missing (request) }"
+        ));
+        assert!(output_str.contains(
+            "fn pool_item_pre_process (pool_item_id : Self :: Key , thread_start_info : & mut Self :: ThreadStartInfo) { Self :: enter (pool_item_id , thread_start_info) }"
+        ));
+        assert!(output_str.contains(
+            "fn pool_item_post_process (pool_item_id : Self :: Key , thread_start_info : & mut Self :: ThreadStartInfo) { Self :: exit (pool_item_id , thread_start_info) }"
+        ));
+        assert!(output_str.contains(
+            "fn id_thread_router (id : u64 , thread_count : usize) -> u64 { Self :: route (id , thread_count) }"
+        ));
+        assert!(output_str.contains("{ Self :: rejected (request , error) }"));
+        assert!(output_str.contains("fn name () -> & 'static str { \"sessions\" }"));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_invalid_attr() {
+    fn test_generate_pool_item_impl_without_batch_or_scheduling_keeps_defaults() {
         let input: ItemImpl = parse_quote! {
             impl MyStruct {
-                #[messaging(OnlyOneArg)]
-                pub fn method(&self) {}
+                #[messaging(Req, Resp)]
+                pub fn method(&mut self) {}
             }
         };
 
         let output = generate_pool_item_impl(input, PoolItemArgs::default());
         let output_str = output.to_string();
-        assert!(output_str.contains("compile_error"));
+
+        assert!(!output_str.contains("fn process_batch"));
+        assert!(!output_str.contains("fn max_batch_size"));
+        assert!(!output_str.contains("fn scheduling"));
+        assert!(!output_str.contains("fn on_shutdown"));
+        assert!(!output_str.contains("fn on_added"));
+        assert!(!output_str.contains("fn on_removed"));
+        assert!(!output_str.contains("fn on_message_error"));
+        assert!(!output_str.contains("fn response_error"));
+        assert!(!output_str.contains("fn pool_item_error_response"));
+        assert!(!output_str.contains("fn id_not_found"));
+        assert!(!output_str.contains("fn pool_item_pre_process"));
+        assert!(!output_str.contains("fn pool_item_post_process"));
+        assert!(!output_str.contains("fn id_thread_router"));
+        assert!(output_str.contains("type ThreadStartInfo = () ;"));
+        assert!(!output_str.contains("fn thread_start"));
+        assert!(!output_str.contains("fn thread_stop"));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_ignore_const() {
+    fn test_generate_pool_item_impl_generic_with_bounds() {
         let input: ItemImpl = parse_quote! {
-            impl MyStruct {
-                const MY_CONST: u32 = 0;
-
+            impl<T: Debug> MyGenericStruct<T> {
                 #[messaging(Req, Resp)]
-                pub fn method(&self) {}
+                pub fn method(&self, arg: T) -> T {
+                    arg
+                }
             }
         };
 
         let output = generate_pool_item_impl(input, PoolItemArgs::default());
         let output_str = output.to_string();
-        // Should still generate for method
-        assert!(output_str.contains("struct Req"));
+
+        assert!(output_str.contains("struct Req < T : Debug >"));
+        assert!(output_str.contains(
+            "impl < T : Debug > messaging_thread_pool :: PoolItem for MyGenericStruct < T >"
+        ));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_no_attr() {
+    fn test_generate_pool_item_impl_client() {
         let input: ItemImpl = parse_quote! {
-            impl MyStruct {
-                pub fn method(&self) {}
+            impl Counter {
+                /// Adds to the counter
+                #[messaging(IncrementRequest, IncrementResponse)]
+                pub fn increment(&mut self, amount: i64) -> i64 {
+                    self.value += amount;
+                    self.value
+                }
+
+                #[messaging(ResetRequest, ResetResponse)]
+                pub fn reset(&mut self, id: u64) {}
             }
         };
 
         let output = generate_pool_item_impl(input, PoolItemArgs::default());
         let output_str = output.to_string();
-        // Should generate empty Api enum and PoolItem impl
-        assert!(output_str.contains("enum MyStructApi"));
-        assert!(output_str.contains("impl messaging_thread_pool :: PoolItem for MyStruct"));
+
+        assert!(output_str.contains("pub struct CounterClient < 'a , S >"));
+        assert!(output_str.contains("S : messaging_thread_pool :: SenderAndReceiver < Counter >"));
+        assert!(output_str.contains(
+            "# [doc = r\" Adds to the counter\"] pub fn increment (& self , id : u64 , amount : i64) -> Result < i64 ,"
+        ));
+        assert!(output_str.contains("send_and_receive_one (IncrementRequest (id , amount ,))"));
+        assert!(output_str.contains(
+            "pub fn increment_batch (& self , requests : impl IntoIterator < Item = (u64 , i64) > ,)"
+        ));
+        // a parameter called id does not clash with the pool item's id
+        assert!(output_str.contains("pub fn reset (& self , pool_item_id : u64 , id : u64)"));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_generic() {
+    fn test_generate_pool_item_impl_client_name() {
         let input: ItemImpl = parse_quote! {
-            impl<T> MyGenericStruct<T> {
-                #[messaging(Req, Resp)]
-                pub fn method(&self, arg: T) -> T {
-                    arg
+            impl Counter {
+                #[messaging(ResetRequest, ResetResponse)]
+                pub fn reset(&mut self) {}
+            }
+        };
+        let args = PoolItemArgs {
+            client_name: Some(parse_quote!(Tally)),
+            ..Default::default()
+        };
+
+        let output_str = generate_pool_item_impl(input, args).to_string();
+
+        assert!(output_str.contains("pub struct Tally < 'a , S >"));
+        assert!(!output_str.contains("CounterClient"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_client_method_clash() {
+        let input: ItemImpl = parse_quote! {
+            impl Counter {
+                #[messaging(PoolRequest, PoolResponse)]
+                pub fn pool(&self) -> u64 {
+                    0
+                }
+            }
+        };
+
+        let output_str =
+            generate_pool_item_impl(input.clone(), PoolItemArgs::default()).to_string();
+        assert!(output_str.contains("compile_error"));
+        assert!(
+            output_str.contains("`#[messaging]` method `pool` clashes with `CounterClient::pool`")
+        );
+
+        // without a client there is nothing to clash with
+        let args = PoolItemArgs {
+            no_client: true,
+            ..Default::default()
+        };
+        let output_str = generate_pool_item_impl(input, args).to_string();
+        assert!(!output_str.contains("compile_error"));
+        assert!(!output_str.contains("CounterClient"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_named_fields() {
+        let input: ItemImpl = parse_quote! {
+            impl ChatRoom {
+                #[messaging(PostRequest, PostResponse, named_fields, routing_key = user)]
+                pub fn post(&mut self, user: u64, text: String) -> usize {
+                    0
+                }
+
+                #[messaging(GetRequest, GetResponse)]
+                pub fn get(&self, index: usize) -> String {
+                    String::new()
                 }
             }
         };
@@ -570,54 +2890,251 @@ mod tests {
         let output = generate_pool_item_impl(input, PoolItemArgs::default());
         let output_str = output.to_string();
 
-        assert!(output_str.contains("struct Req < T >"));
-        assert!(output_str.contains("struct Resp < T >"));
-        assert!(output_str.contains("enum MyGenericStructApi < T >"));
-        assert!(output_str
-            .contains("impl < T > messaging_thread_pool :: PoolItem for MyGenericStruct < T >"));
-        // Check for PhantomData presence, might be fully qualified
-        assert!(output_str.contains("PhantomData"));
+        assert!(output_str.contains(
+            "pub struct PostRequest { pub id : u64 , pub user : u64 , pub text : String , }"
+        ));
+        assert!(output_str.contains("pub fn new (id : u64 , user : u64 , text : String) -> Self"));
+        assert!(output_str.contains("self . id . clone ()"));
+        assert!(output_str.contains("routing_hash (& self . user)"));
+        // the arm destructures the request by name
+        assert!(output_str.contains(
+            "let PostRequest { user , text , .. } = request ; self . post (user , text)"
+        ));
+        // the client builds the request by name
+        assert!(output_str.contains("PostRequest { id , user , text , }"));
+        // methods that do not ask for named fields keep their tuple structs
+        assert!(output_str.contains("pub struct GetRequest (pub u64 , pub usize ,) ;"));
+        assert!(output_str.contains("self . get (request . 1)"));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_custom_init() {
+    fn test_generate_pool_item_impl_named_fields_for_every_method() {
         let input: ItemImpl = parse_quote! {
             impl MyStruct {
                 #[messaging(Req, Resp)]
-                pub fn method(&self) {}
+                pub fn method(&self, id: u32) {}
             }
         };
 
         let args = PoolItemArgs {
-            init_type: Some(syn::parse_quote!(MyCustomInit)),
-            shutdown_method: None,
+            named_fields: true,
+            ..Default::default()
         };
-
         let output = generate_pool_item_impl(input, args);
         let output_str = output.to_string();
 
-        assert!(output_str.contains("type Init = MyCustomInit ;"));
-        assert!(!output_str.contains("struct MyStructInit")); // Should not generate default init
-        assert!(output_str.contains("Ok (Self :: new (request))")); // Should pass request directly
+        assert!(output_str.contains("pub struct Req { pub pool_item_id : u64 , pub id : u32 , }"));
+        assert!(output_str.contains("self . pool_item_id . clone ()"));
     }
 
     #[test]
-    fn test_generate_pool_item_impl_generic_with_bounds() {
+    fn test_generate_pool_item_impl_borrowed_params() {
         let input: ItemImpl = parse_quote! {
-            impl<T: Debug> MyGenericStruct<T> {
+            impl MyStruct {
                 #[messaging(Req, Resp)]
-                pub fn method(&self, arg: T) -> T {
-                    arg
+                pub fn method(&self, name: &str, bytes: &[u8], config: &Config, count: &'_ u32, label: &'static str, owned: Vec<u8>) {}
+
+                #[messaging(NamedReq, NamedResp, named_fields)]
+                pub fn named(&self, name: &str) {}
+            }
+        };
+
+        let output_str = generate_pool_item_impl(input, PoolItemArgs::default()).to_string();
+
+        assert!(output_str.contains(
+            "pub struct Req (pub u64 , pub String , pub Vec < u8 > , pub Config , pub u32 , pub & 'static str , pub Vec < u8 > ,)"
+        ));
+        assert!(output_str.contains(
+            "self . method (& request . 1 , & request . 2 , & request . 3 , & request . 4 , request . 5 , request . 6)"
+        ));
+        assert!(output_str.contains("let NamedReq { name , .. } = request ; self . named (& name)"));
+        // the method keeps its borrowed parameters
+        assert!(output_str.contains("pub fn named (& self , name : & str)"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_unsupported_params() {
+        let errors = [
+            (
+                quote! { pub fn method<'a>(&self, name: &'a str) {} },
+                "cannot borrow with a named lifetime",
+            ),
+            (
+                quote! { pub fn method(&self, name: &mut String) {} },
+                "cannot be mutable references",
+            ),
+            (
+                quote! { pub fn method(&self, name: impl Display) {} },
+                "cannot be `impl Trait`",
+            ),
+            (
+                quote! { pub fn method(&self, name: &dyn Display) {} },
+                "cannot be trait objects",
+            ),
+            (
+                quote! { pub fn method(&self, names: Vec<&str>) {} },
+                "cannot contain references other than `&'static`",
+            ),
+            (
+                quote! { pub fn method<'a>(&self, name: Cow<'a, str>) {} },
+                "cannot contain lifetimes other than `'static`",
+            ),
+        ];
+
+        for (method, error) in errors {
+            let input: ItemImpl = parse_quote! {
+                impl MyStruct {
+                    #[messaging(Req, Resp)]
+                    #method
+                }
+            };
+            let output_str = generate_pool_item_impl(input, PoolItemArgs::default()).to_string();
+            assert!(output_str.contains("compile_error"), "{method}");
+            assert!(output_str.contains(error), "{method}");
+        }
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_init_from_new() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                pub fn new(id: u64, config: Config, name: &str) -> Self {
+                    Self { id }
                 }
             }
         };
 
-        let output = generate_pool_item_impl(input, PoolItemArgs::default());
+        let output_str =
+            generate_pool_item_impl(input.clone(), PoolItemArgs::default()).to_string();
+        assert!(
+            output_str.contains("pub struct MyStructInit (pub u64 , pub Config , pub String ,) ;")
+        );
+        assert!(output_str.contains("Ok (Self :: new (request . 0 , request . 1 , & request . 2))"));
+
+        let args = PoolItemArgs {
+            named_fields: true,
+            ..Default::default()
+        };
+        let output_str = generate_pool_item_impl(input, args).to_string();
+        assert!(output_str.contains(
+            "pub struct MyStructInit { pub id : u64 , pub config : Config , pub name : String , }"
+        ));
+        assert!(output_str
+            .contains("Ok (Self :: new (request . id , request . config , & request . name))"));
+        assert!(output_str.contains("self . id . clone ()"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_named_fields_init_of_only_the_id() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                pub fn new(id: u64) -> Self {
+                    Self { id }
+                }
+            }
+        };
+        let args = PoolItemArgs {
+            named_fields: true,
+            ..Default::default()
+        };
+
+        let output_str = generate_pool_item_impl(input, args).to_string();
+
+        assert!(output_str.contains("pub struct MyStructInit (pub u64 ,) ;"));
+        assert!(output_str.contains("Ok (Self :: new (request . 0))"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_init_without_new_or_with_custom_init() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {}
+        };
+        let output_str = generate_pool_item_impl(input, PoolItemArgs::default()).to_string();
+        assert!(output_str.contains("pub struct MyStructInit (pub u64 ,) ;"));
+        assert!(output_str.contains("Ok (Self :: new (request . 0))"));
+
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                pub fn new(init: &mut MyInit) -> Self {
+                    Self {}
+                }
+            }
+        };
+        let args = PoolItemArgs {
+            init_type: Some(parse_quote!(MyInit)),
+            ..Default::default()
+        };
+        let output_str = generate_pool_item_impl(input, args).to_string();
+        assert!(!output_str.contains("struct MyStructInit"));
+        assert!(output_str.contains("Ok (Self :: new (request))"));
+    }
+
+    #[test]
+    fn test_generate_pool_item_impl_api_response() {
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {
+                #[messaging(GetRequest, GetResponse, routing_key = group)]
+                pub fn get(&self, group: u64) -> u32 { 0 }
+
+                #[messaging(MoveRequest, MoveResponse, multi)]
+                pub fn transfer(items: &mut [&mut Self], amount: u32) -> bool { true }
+            }
+        };
+
+        let args = PoolItemArgs {
+            implements: vec![parse_quote!(Health)],
+            ..Default::default()
+        };
+
+        let output = generate_pool_item_impl(input, args);
         let output_str = output.to_string();
 
-        assert!(output_str.contains("struct Req < T : Debug >"));
         assert!(output_str.contains(
-            "impl < T : Debug > messaging_thread_pool :: PoolItem for MyGenericStruct < T >"
+            "pub enum MyStructResponse { GetResponse (GetResponse) , MoveResponse (MoveResponse) , Health (HealthApi < u64 >) , }"
+        ));
+        assert!(output_str.contains(
+            "impl messaging_thread_pool :: RequestWithResponse < MyStruct > for MyStructApi { type Response = MyStructResponse ; }"
+        ));
+        assert!(output_str.contains("impl From < GetRequest > for MyStructApi"));
+        assert!(output_str
+            .contains("MyStructApi :: Health (message)) => MyStructResponse :: Health (message)"));
+        // multi item requests are sent to all of their targets; others to a single pool item
+        assert!(output_str.contains(
+            "if matches ! (api , MyStructApi :: MoveRequest (_)) { messaging_thread_pool :: ThreadRequestResponse :: MessagePoolItems (api) }"
+        ));
+        // messages of the API are routed by the routing key of the request that they hold
+        assert!(output_str.contains(
+            "MyStructApi :: GetRequest (req) => messaging_thread_pool :: IdTargeted :: routing_key (req)"
         ));
     }
+
+    #[test]
+    fn test_generate_pool_item_impl_api_response_name() {
+        // the default name is taken by one of the responses
+        let input: ItemImpl = parse_quote! {
+            impl Relay {
+                #[messaging(RelayRequest, RelayResponse)]
+                pub fn relay(&self) {}
+            }
+        };
+        let output_str =
+            generate_pool_item_impl(input.clone(), PoolItemArgs::default()).to_string();
+        assert!(output_str.contains("compile_error"));
+        assert!(output_str.contains("`RelayResponse` is also the name of the enum of Relay's responses; name the enum with `#[pool_item(ApiResponse = \\\"RelayMessage\\\")]`"));
+        assert!(!output_str.contains("pub enum RelayResponse"));
+
+        let args = PoolItemArgs {
+            api_response_name: Some(parse_quote!(RelayMessage)),
+            ..Default::default()
+        };
+        let output_str = generate_pool_item_impl(input, args).to_string();
+        assert!(output_str.contains("pub enum RelayMessage { RelayResponse (RelayResponse) , }"));
+
+        // a pool item without any messages has no responses
+        let input: ItemImpl = parse_quote! {
+            impl MyStruct {}
+        };
+        let output_str = generate_pool_item_impl(input, PoolItemArgs::default()).to_string();
+        assert!(!output_str.contains("MyStructResponse"));
+    }
 }
@@ -51,8 +51,11 @@
 //! For a struct named `MyItem` with the above implementation, the macro generates:
 //!
 //! ### Initialization
-//! - `MyItemInit(u64)` - Request struct to create a new pool item
-//! - Implementation of `PoolItem::new_pool_item` that calls your `new` function
+//! - `MyItemInit(u64)` - Request struct to create a new pool item, holding the parameters of `new`
+//!   (a `new(id: u64, config: Config, options: &Options)` gives `MyItemInit(u64, Config, Options)`)
+//! - Implementation of `PoolItem::new_pool_item` that calls your `new` function. If `new` returns
+//!   `Result<Self, E>` its error is kept in the `NewPoolItemError` (`E` must implement `Display`),
+//!   so the caller can get it back from the `AddResponse` with `error().and_then(|e| e.downcast_ref::<E>())`
 //!
 //! ### For each `#[messaging]` method
 //! - Request struct: `SetDataRequest(u64, String)` - ID + method parameters
@@ -60,7 +63,22 @@
 //! - Conversions to/from `ThreadRequestResponse<MyItem>`
 //!
 //! ### API Enum
-//! - `MyItemApi` - Enum with variants for each message type, used for routing
+//! - `MyItemApi` - Enum with variants for each message type, used for routing. Each request converts
+//!   into it (`MyItemApi::from(SetDataRequest(1, value))`) and it can itself be sent as a request, so a
+//!   batch passed to `ThreadPool::send_and_receive_api` can mix request types
+//! - `MyItemResponse` - Enum with a variant per response type (`MyItemResponse::SetDataResponse(..)`),
+//!   the response to a `MyItemApi` request. If one of the responses is already called
+//!   `MyItemResponse`, name the enum with `ApiResponse = "Name"`
+//!
+//! ### Client
+//! - `MyItemClient<'a, S>` - A typed handle over any `S: SenderAndReceiver<MyItem>` (a pool or a mock)
+//!   with a method per `#[messaging]` method, for example
+//!   `client.set_data(1, "value".to_string()) -> Result<(), PoolError<MyItem>>`
+//! - `client.set_data_batch(requests)` - Sends a request per `(id, data)` item and collects the responses
+//!   (streaming methods have no batch variant and return an iterator over their items)
+//! - The client is named with `Client = "Name"`, or not generated with `NoClient`. A `#[messaging]`
+//!   method called `new` or `pool` clashes with the client's own methods and is an error unless
+//!   the client is not generated
 //!
 //! ## The `#[messaging]` Attribute
 //!
@@ -78,16 +96,113 @@
 //! - `CalculateRequest(u64, i32, i32)` - tuple struct with (id, x, y)
 //! - `CalculateResponse { id: u64, result: i64 }` - struct with id and return value
 //!
+//! ### Priority
+//!
+//! Requests are sent on the normal priority lane unless the method specifies otherwise:
+//!
+//! ```rust,ignore
+//! #[messaging(CancelRequest, CancelResponse, priority = high)]
+//! pub fn cancel(&mut self) { /* ... */ }
+//! ```
+//!
+//! `priority` accepts `high`, `normal` or `low` and sets `RequestWithResponse::PRIORITY`
+//! for the generated request.
+//!
+//! ### Routing Key
+//!
+//! Requests are sent to the thread chosen by the pool item's id unless the method names one of its
+//! parameters as the routing key:
+//!
+//! ```rust,ignore
+//! #[messaging(AddSessionRequest, AddSessionResponse, routing_key = user_id)]
+//! pub fn add_session(&mut self, user_id: u64) { /* ... */ }
+//! ```
+//!
+//! The parameter may be of any `PoolKey` type; its `routing_hash` is returned by
+//! `IdTargeted::routing_key` for the generated request. The pool item itself must also have been created on that thread, for example
+//! by sending its init request wrapped in `Routed`.
+//!
+//! ### Multi Item Requests
+//!
+//! A method marked with `multi` acts on several co-located pool items in one step. It is an
+//! associated function whose first parameter receives the targeted pool items:
+//!
+//! ```rust,ignore
+//! #[messaging(TransferRequest, TransferResponse, multi, routing_key = customer)]
+//! pub fn transfer(accounts: &mut [&mut Self], customer: u64, amount: i64) { /* ... */ }
+//! ```
+//!
+//! The first field of the generated request is a `Vec<u64>` of the targeted ids
+//! (`TransferRequest(vec![1, 2], 7, 30)`), in the order that the pool items are passed to the method.
+//! The request is identified by the first of its ids, so it must target at least one pool item;
+//! `TransferRequest::new` returns `Err(MultiItemError::NoIds)` for an empty list of ids, and sending a
+//! request built directly with an empty list panics.
+//! The response's `result` is a `Result<T, MultiItemError>`; if any of the pool items are not on the
+//! thread that the request was routed to the request is rejected and the method is not called.
+//!
+//! ### Streaming
+//!
+//! A method marked with `stream` sends back many responses for a single request. It either returns
+//! `impl Iterator<Item = T>` (`impl IntoIterator<Item = T>` or `Vec<T>` also work), or pushes its
+//! items into a final `&mut StreamSink<T>` parameter:
+//!
+//! ```rust,ignore
+//! #[messaging(HistoryRequest, HistoryResponse, stream)]
+//! pub fn history(&self) -> impl Iterator<Item = String> + '_ {
+//!     self.history.iter().cloned()
+//! }
+//!
+//! #[messaging(CountdownRequest, CountdownResponse, stream)]
+//! pub fn countdown(&self, from: u64, sink: &mut StreamSink<u64>) {
+//!     for i in (0..=from).rev() {
+//!         if !sink.push(i) {
+//!             return; // the caller has gone away
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Each item is sent as soon as it is produced in a response whose `result` is a
+//! `StreamChunk::Item`, and a final `StreamChunk::End` marks the end of the stream. The sink is
+//! supplied by the pool and is not part of the request. Use `send_and_receive_stream` to receive
+//! the items as an iterator.
+//!
+//! ### Named Fields
+//!
+//! Requests are tuple structs (`PostRequest(1, user, text)`) unless the method is marked with
+//! `named_fields`, or the pool item with `#[pool_item(NamedFields)]`, in which case the request's
+//! fields are named after the method's parameters:
+//!
+//! ```rust,ignore
+//! #[messaging(PostRequest, PostResponse, named_fields)]
+//! pub fn post(&mut self, user: String, text: String) -> usize { /* ... */ }
+//!
+//! let request = PostRequest { id: 1, user, text };
+//! let request = PostRequest::new(1, user, text);
+//! ```
+//!
+//! The id field is `id` (`ids` for a multi item request), or `pool_item_id` if one of the method's
+//! parameters is already called `id`. A parameter that is not a plain identifier is named `argN`.
+//! `#[pool_item(NamedFields)]` also names the fields of the init request after the parameters of `new`,
+//! unless `new` only takes the id, in which case the init request stays `MyItemInit(id)`.
+//!
 //! ### Method Requirements
 //! - Must take `&self` or `&mut self` as first parameter
 //! - Additional parameters become fields in the request struct
-//! - Return type (or `()`) becomes the `result` field in the response struct
+//! - Borrowed parameters become owned fields (`&str` a `String`, `&[T]` a `Vec<T>` and any other
+//!   `&T` a `T`) and the method is passed a reference to the field; `&'static` references are kept.
+//!   Mutable references, named lifetimes, `impl Trait` and trait objects are rejected
+//! - Return type (or `()`) becomes the `result` field in the response struct. The errors of methods
+//!   that return a `Result` are also passed to `PoolItem::pool_item_error_response` (see
+//!   [Error Responses](#error-responses)). Any return type whose name is `Result` is taken to be
+//!   one; mark a method returning some other `Result` type with `no_error_report` to leave it out
 //!
 //! ## Optional Parameters
 //!
 //! ### Custom Initialization Type
 //!
-//! For complex constructors that need more than just an ID:
+//! The parameters of `new` after the id become fields of the generated init request, so a custom
+//! init type is only needed when the request itself needs custom behaviour, such as its own routing:
 //!
 //! ```rust,ignore
 //! // Your custom init request
@@ -140,6 +255,210 @@
 //! }
 //! ```
 //!
+//! To release a child pool that is shared between pool items, or to use the thread's state while
+//! shutting down, use `OnShutdown` instead. The method is called for every pool item on the thread
+//! and is passed the `ShutdownContext`:
+//!
+//! ```rust,ignore
+//! #[pool_item(OnShutdown = "release")]
+//! impl MyItem {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//!
+//!     pub fn release(&mut self, context: &mut ShutdownContext<'_, Self>) {
+//!         // shut down once no pool item holds it any longer
+//!         context.release_child_pool(Arc::clone(&self.child_pool));
+//!     }
+//! }
+//! ```
+//!
+//! ### Thread Resources
+//!
+//! A `#[messaging]` method can borrow a resource shared by every pool item on its thread by marking a
+//! reference parameter with `#[thread_resource]`. The parameter is not part of the request; the
+//! resource (which must implement `Default`) is borrowed with `with_thread_resource` when the message
+//! is processed:
+//!
+//! ```rust,ignore
+//! #[messaging(LookupRequest, LookupResponse)]
+//! pub fn lookup(&self, key: String, #[thread_resource] cache: &mut Cache) -> Option<String> {
+//!     cache.get(&key)
+//! }
+//! ```
+//!
+//! Stream and multi methods cannot take thread resources.
+//!
+//! ### Lifecycle Hooks
+//!
+//! `Added`, `Removed` and `MessageError` name methods that are called once the pool item has been
+//! added to its thread, before it is removed (with the `RemovalReason`) and when processing one of
+//! its messages panics. A `MessageError` method that returns rather than continuing the panic keeps
+//! the pool item and its thread running:
+//!
+//! ```rust,ignore
+//! #[pool_item(Removed = "removed", MessageError = "failed")]
+//! impl MyItem {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//!
+//!     pub fn removed(&mut self, reason: RemovalReason) {
+//!         // flush any state before the pool item is dropped
+//!     }
+//!
+//!     pub fn failed(&mut self, error: MessageError<u64>) {
+//!         tracing::error!("{error}");
+//!     }
+//! }
+//! ```
+//!
+//! ### Routing and Processing Hooks
+//!
+//! `IdThreadRouter` names an associated function that chooses the thread for a routing key (in
+//! place of `id % thread_count`) and `IdNotFound` one that answers requests for pool items that do
+//! not exist (in place of a panic). `PreProcess` and `PostProcess` name associated functions that
+//! are passed the pool item's key and the thread's state before and after each of its messages:
+//!
+//! ```rust,ignore
+//! #[pool_item(IdThreadRouter = "route", ThreadStartInfo = "Span", ThreadStart = "start", PreProcess = "enter", PostProcess = "exit")]
+//! impl MyItem {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//!
+//!     pub fn route(id: u64, thread_count: usize) -> u64 {
+//!         (id / 100) % thread_count as u64
+//!     }
+//!
+//!     pub fn enter(id: u64, span: &mut Span) { /* ... */ }
+//!
+//!     pub fn exit(id: u64, span: &mut Span) { /* ... */ }
+//! }
+//! ```
+//!
+//! An option that `#[pool_item]` does not recognise is reported as an error.
+//!
+//! ### Error Responses
+//!
+//! `ErrorResponse` names an associated function that is called with the error of every response
+//! whose method returned an `Err`, so that failures can be logged or counted in one place. It is
+//! passed the thread's state when `ThreadStart` created some:
+//!
+//! ```rust,ignore
+//! #[pool_item(ErrorResponse = "error_response")]
+//! impl MyItem {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//!
+//!     #[messaging(SendRequest, SendResponse)]
+//!     pub fn send(&mut self, bytes: usize) -> Result<usize, SendError> { /* ... */ }
+//!
+//!     pub fn error_response(id: &u64, message_type: &'static str, error: &dyn Debug, _: Option<&mut ()>) {
+//!         tracing::warn!("{message_type} for {id} failed: {error:?}");
+//!     }
+//! }
+//! ```
+//!
+//! ### Thread Start and Stop
+//!
+//! `ThreadStart` names an associated function that creates the state of each pool thread (of type
+//! `ThreadStartInfo`) and `ThreadStop` one that is passed that state when the thread stops. Any
+//! `ThreadStopData` returned by `ThreadStop` is forwarded in the thread's `ThreadShutdownResponse`:
+//!
+//! ```rust,ignore
+//! #[pool_item(ThreadStartInfo = "WriteBuffer", ThreadStart = "start", ThreadStop = "stop")]
+//! impl MyItem {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//!
+//!     pub fn start() -> Option<WriteBuffer> {
+//!         Some(WriteBuffer::default())
+//!     }
+//!
+//!     pub fn stop(buffer: WriteBuffer) -> Option<ThreadStopData> {
+//!         Some(ThreadStopData::new(buffer.flush()))
+//!     }
+//! }
+//! ```
+//!
+//! ### Custom Key Type
+//!
+//! Pool items are identified by a `u64` by default. Any `PoolKey` (for example a `String` or a
+//! UUID newtype) can be used instead; it becomes the first field of the init struct and of every
+//! generated request, and the `id` of every generated response:
+//!
+//! ```rust,ignore
+//! #[pool_item(Key = "String")]
+//! impl Document {
+//!     pub fn new(path: String) -> Self { /* ... */ }
+//!
+//!     #[messaging(AppendRequest, AppendResponse)]
+//!     pub fn append(&mut self, line: String) -> usize { /* ... */ }
+//! }
+//!
+//! pool.send_and_receive_once(DocumentInit("readme.md".to_string()))?;
+//! pool.send_and_receive_once(AppendRequest("readme.md".to_string(), "# Readme".to_string()))?;
+//! ```
+//!
+//! Pool items with a key other than a `u64` are routed by the hash of their key.
+//!
+//! ### Batching
+//!
+//! When several messages for the same pool item are queued one after another, the pool thread
+//! can hand them to the pool item together so that work such as a database flush is done once
+//! per batch:
+//!
+//! ```rust,ignore
+//! #[pool_item(Batch = "apply_batch", MaxBatchSize = 32)]
+//! impl Ledger {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//!
+//!     #[messaging(PostRequest, PostResponse)]
+//!     pub fn post(&mut self, amount: i64) { /* ... */ }
+//! }
+//!
+//! impl Ledger {
+//!     // must return one response per request, in order
+//!     fn apply_batch(&mut self, requests: Vec<LedgerApi>) -> Vec<ThreadRequestResponse<Self>> {
+//!         let responses = requests.into_iter().map(|r| self.process_message(r)).collect();
+//!         self.flush();
+//!         responses
+//!     }
+//! }
+//! ```
+//!
+//! `MaxBatchSize` defaults to 1, which disables batching. A pool item that batches cannot have `stream` methods.
+//!
+//! ### Fair Scheduling
+//!
+//! By default a thread processes its messages strictly in the order they arrive. With
+//! `FairBudget` the thread keeps a mailbox per pool item and takes turns between the pool items
+//! with messages waiting, processing at most the budget from each per turn:
+//!
+//! ```rust,ignore
+//! #[pool_item(FairBudget = 8)]
+//! impl Sensor {
+//!     // ...
+//! }
+//! ```
+//!
+//! ### Protocols
+//!
+//! `#[pool_protocol]` on a trait generates requests and responses that any pool item can accept,
+//! generic over the pool item's key (`HealthRequest<K = u64>(K)`). A pool item that implements the
+//! trait lists it with `Implements`, which adds a `Health(HealthApi<Key>)` variant to its API enum
+//! and implements `Implements<HealthProtocol>`; list several protocols by repeating `Implements`:
+//!
+//! ```rust,ignore
+//! #[pool_protocol]
+//! pub trait Health {
+//!     #[messaging(HealthRequest, HealthResponse)]
+//!     fn health(&self) -> bool;
+//! }
+//!
+//! impl Health for MyItem {
+//!     fn health(&self) -> bool { true }
+//! }
+//!
+//! #[pool_item(Implements = Health)]
+//! impl MyItem {
+//!     pub fn new(id: u64) -> Self { /* ... */ }
+//! }
+//! ```
+//!
 //! ### Combining Parameters
 //!
 //! ```rust,ignore
@@ -189,10 +508,11 @@
 
 mod generation;
 mod parsing;
+mod protocol;
 
-use parsing::PoolItemArgs;
+use parsing::{PoolItemArgs, ProtocolArgs};
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, ItemImpl};
+use syn::{parse_macro_input, ItemImpl, ItemTrait};
 
 /// Attribute macro that generates `PoolItem` implementation and message types.
 ///
@@ -215,12 +535,47 @@ use syn::{parse_macro_input, ItemImpl};
 /// - `Init = "TypeName"` - Use a custom initialization request type instead of
 ///   generating `{StructName}Init`
 /// - `Shutdown = "method_name"` - Specify a method to call during pool shutdown
+/// - `Key = "TypeName"` - Identify pool items by a `PoolKey` other than `u64`
+/// - `Batch = "method_name"` - Specify a method that processes a batch of messages at once
+/// - `MaxBatchSize = 32` - The maximum number of queued messages to process as one batch
+/// - `FairBudget = 8` - Schedule fairly between pool items, processing at most 8 messages per turn
+/// - `OnShutdown = "method_name"` - Specify a method that is passed the `ShutdownContext` when the
+///   pool item's thread shuts down
+/// - `Added = "method_name"` - Specify a method to call once the pool item has been added to its thread
+/// - `Removed = "method_name"` - Specify a method that is passed the `RemovalReason` before the pool
+///   item is removed from its thread
+/// - `MessageError = "method_name"` - Specify a method that is passed the `MessageError` when
+///   processing a message panics
+/// - `ThreadStartInfo = "TypeName"` - The per-thread state created by `ThreadStart` (`()` by default)
+/// - `ThreadStart = "function_name"` - Specify an associated function that creates each thread's state
+/// - `ThreadStop = "function_name"` - Specify an associated function that is passed each thread's
+///   state when the thread stops, optionally returning `ThreadStopData` for the shutdown response
+/// - `PreProcess = "function_name"` - Specify an associated function that is passed the pool item's
+///   key and the thread's state before each message for the pool item is processed
+/// - `PostProcess = "function_name"` - As `PreProcess`, after each message is processed
+/// - `IdNotFound = "function_name"` - Specify an associated function that answers requests for pool
+///   items that do not exist, instead of panicking
+/// - `IdThreadRouter = "function_name"` - Specify an associated function that chooses the thread for a
+///   routing key and the number of threads
+/// - `MultiItemRejected = "function_name"` - Specify an associated function that answers multi item
+///   requests whose pool items are not all on the thread they were routed to
+/// - `Name = "name"` - The name returned by `PoolItem::name` (the pool item's type by default)
+/// - `ErrorResponse = "function_name"` - Specify an associated function that is passed the error of
+///   each response whose method returned an `Err`
+/// - `Implements = TraitName` - Accept the messages of a `#[pool_protocol]` trait that the pool item
+///   implements; repeat for each protocol
+/// - `ApiResponse = "TypeName"` - The name of the enum of the API's responses (`{Item}Response` by default)
+/// - `Client = "TypeName"` - The name of the generated client (`{Item}Client` by default)
+/// - `NoClient` - Do not generate the client; cannot be combined with `Client`
+/// - `NamedFields` (or `named_fields`) - Give every request struct, and the init request (unless
+///   `new` only takes the id), fields named after the parameters of their method
 ///
 /// # Generated Types
 ///
 /// For a struct `Foo` with method `bar`:
 /// - `FooInit(u64)` - Initialization request (unless custom `Init` specified)
 /// - `FooApi` - Enum containing all message variants
+/// - `FooResponse` - Enum containing a variant per response type, the response to a `FooApi` request
 /// - `BarRequest(...)` - Request struct for the method
 /// - `BarResponse { id, result }` - Response struct for the method
 ///
@@ -235,3 +590,43 @@ pub fn pool_item(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as ItemImpl);
     generation::generate_pool_item_impl(input, args).into()
 }
+
+/// Attribute macro that generates a protocol; requests and responses shared by every pool item
+/// that implements the trait.
+///
+/// # Usage
+///
+/// Apply to a trait whose methods are marked with `#[messaging]`:
+///
+/// ```rust,ignore
+/// #[pool_protocol]
+/// pub trait Health {
+///     #[messaging(HealthRequest, HealthResponse)]
+///     fn health(&self, verbose: bool) -> String;
+/// }
+/// ```
+///
+/// The methods take the same `#[messaging]` options as those of a `#[pool_item]`, except that they
+/// cannot be `stream` or `multi` methods.
+///
+/// # Parameters
+///
+/// - `NamedFields` (or `named_fields`) - Give every request struct fields named after the method's
+///   parameters
+///
+/// # Generated Types
+///
+/// For a trait `Health` with method `health`:
+/// - `HealthProtocol` - Identifies the protocol, as in `P: Implements<HealthProtocol>`
+/// - `HealthApi<K = u64>` - Enum containing the protocol's requests and responses
+/// - `HealthRequest<K = u64>(K, bool)` - Request struct for the method, for pool items keyed by `K`
+/// - `HealthResponse<K = u64> { id, result }` - Response struct for the method
+///
+/// A pool item accepts the protocol's requests once it implements the trait and lists it with
+/// `#[pool_item(Implements = Health)]`.
+#[proc_macro_attribute]
+pub fn pool_protocol(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as ProtocolArgs);
+    let input = parse_macro_input!(item as ItemTrait);
+    protocol::generate_pool_protocol(input, args).into()
+}
@@ -8,26 +8,264 @@ use syn::{
 pub struct MessagingArgs {
     pub request_type: Ident,
     pub response_type: Ident,
+    /// The `Priority` variant that the request is sent on by default (e.g. `High`)
+    pub priority: Option<Ident>,
+    /// True if the method streams its results back as multiple responses
+    pub stream: bool,
+    /// True if the method acts on several co-located pool items at once
+    pub multi: bool,
+    /// The name of the method parameter whose value is used to choose the thread (defaults to the id)
+    pub routing_key: Option<Ident>,
+    /// True if the request struct has fields named after the method's parameters rather than
+    /// being a tuple struct
+    pub named_fields: bool,
+    /// True if the method's errors are not reported through `PoolItem::response_error`, even though
+    /// it appears to return a `Result`
+    pub no_error_report: bool,
 }
 
 impl Parse for MessagingArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let vars = Punctuated::<Ident, Token![,]>::parse_terminated(input)?;
-        if vars.len() != 2 {
+        let vars = Punctuated::<syn::Meta, Token![,]>::parse_terminated(input)?;
+
+        let mut types = Vec::new();
+        let mut priority = None;
+        let mut stream = false;
+        let mut multi = false;
+        let mut named_fields = false;
+        let mut no_error_report = false;
+        let mut routing_key = None;
+        for meta in vars {
+            match meta {
+                syn::Meta::Path(path) if path.is_ident("stream") => {
+                    stream = true;
+                }
+                syn::Meta::Path(path) if path.is_ident("multi") => {
+                    multi = true;
+                }
+                syn::Meta::Path(path) if path.is_ident("named_fields") => {
+                    named_fields = true;
+                }
+                syn::Meta::Path(path) if path.is_ident("no_error_report") => {
+                    no_error_report = true;
+                }
+                syn::Meta::Path(path) if path.get_ident().is_some() => {
+                    types.push(path.get_ident().unwrap().clone());
+                }
+                syn::Meta::NameValue(nv) if nv.path.is_ident("priority") => {
+                    priority = Some(parse_priority(&nv.value)?);
+                }
+                syn::Meta::NameValue(nv) if nv.path.is_ident("routing_key") => {
+                    routing_key = Some(parse_routing_key(&nv.value)?);
+                }
+                syn::Meta::NameValue(nv) => {
+                    return Err(syn::Error::new_spanned(
+                        nv.path,
+                        "Unknown messaging option; expected `priority` or `routing_key`",
+                    ));
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "Expected a type name, `stream`, `multi`, `named_fields`, `no_error_report`, `priority = high|normal|low` or `routing_key = parameter`",
+                    ));
+                }
+            }
+        }
+
+        if stream && multi {
+            return Err(input.error("`stream` and `multi` cannot be combined"));
+        }
+        if types.len() != 2 {
             return Err(input.error("Expected exactly 2 arguments: (RequestType, ResponseType)"));
         }
-        let mut iter = vars.into_iter();
+        let mut iter = types.into_iter();
         Ok(MessagingArgs {
             request_type: iter.next().unwrap(),
             response_type: iter.next().unwrap(),
+            priority,
+            stream,
+            multi,
+            routing_key,
+            named_fields,
+            no_error_report,
         })
     }
 }
 
+/// Maps `high`, `normal` or `low` onto the name of the corresponding `Priority` variant
+fn parse_priority(value: &syn::Expr) -> syn::Result<Ident> {
+    let variant = match value {
+        syn::Expr::Path(path) => path.path.get_ident().and_then(|ident| {
+            match ident.to_string().as_str() {
+                "high" => Some("High"),
+                "normal" => Some("Normal"),
+                "low" => Some("Low"),
+                _ => None,
+            }
+            .map(|variant| Ident::new(variant, ident.span()))
+        }),
+        _ => None,
+    };
+
+    variant.ok_or_else(|| {
+        syn::Error::new_spanned(
+            value,
+            "Expected one of `high`, `normal` or `low` for priority",
+        )
+    })
+}
+
+/// Parses the name of the parameter that supplies the routing key
+fn parse_routing_key(value: &syn::Expr) -> syn::Result<Ident> {
+    match value {
+        syn::Expr::Path(path) => path.path.get_ident().cloned(),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        syn::Error::new_spanned(
+            value,
+            "Expected the name of a method parameter for routing_key",
+        )
+    })
+}
+
+/// Parses the name of a method given as an identifier or a string literal
+fn parse_method_name(value: syn::Expr, arg: &str) -> syn::Result<Ident> {
+    match value {
+        syn::Expr::Path(path) => path.path.get_ident().cloned().ok_or_else(|| {
+            syn::Error::new_spanned(path, format!("Expected an identifier for {arg}"))
+        }),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit_str),
+            ..
+        }) => lit_str.parse(),
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!("Expected an identifier or string literal for {arg}"),
+        )),
+    }
+}
+
+/// Parses a string literal
+fn parse_str(value: syn::Expr, arg: &str) -> syn::Result<syn::LitStr> {
+    match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit_str),
+            ..
+        }) => Ok(lit_str),
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!("Expected a string literal for {arg}"),
+        )),
+    }
+}
+
+/// Parses a `usize` given as an integer literal, keeping the literal
+fn parse_usize(value: syn::Expr, arg: &str) -> syn::Result<syn::LitInt> {
+    match value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(lit_int),
+            ..
+        }) => {
+            lit_int.base10_parse::<usize>()?;
+            Ok(lit_int)
+        }
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!("Expected an integer literal for {arg}"),
+        )),
+    }
+}
+
+/// Parses a path given as a path or a string literal
+fn parse_path(value: syn::Expr, arg: &str) -> syn::Result<syn::Path> {
+    match value {
+        syn::Expr::Path(path) => Ok(path.path),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit_str),
+            ..
+        }) => lit_str.parse(),
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!("Expected a path or string literal for {arg}"),
+        )),
+    }
+}
+
+/// True if the path is the flag that names the fields of every request, written `NamedFields` like
+/// the other options or `named_fields` as on `#[messaging]`
+fn is_named_fields(path: &syn::Path) -> bool {
+    path.is_ident("NamedFields") || path.is_ident("named_fields")
+}
+
+/// Parses a type given as a path or a string literal
+fn parse_type(value: syn::Expr, arg: &str) -> syn::Result<Type> {
+    match value {
+        syn::Expr::Path(path) => Ok(Type::Path(syn::TypePath {
+            qself: None,
+            path: path.path,
+        })),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit_str),
+            ..
+        }) => lit_str.parse(),
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!("Expected a type path or string literal for {arg}"),
+        )),
+    }
+}
+
+/// The options of `#[pool_item]` that take a value, as listed in its errors
+const POOL_ITEM_OPTIONS: &str =
+    "`Init`, `Key`, `Shutdown`, `OnShutdown`, `Batch`, `MaxBatchSize`, \
+`FairBudget`, `Added`, `Removed`, `MessageError`, `ThreadStartInfo`, `ThreadStart`, `ThreadStop`, \
+`PreProcess`, `PostProcess`, `IdNotFound`, `IdThreadRouter`, `MultiItemRejected`, `ErrorResponse`, \
+`Name`, `ApiResponse`, `Client` or `Implements`";
+
 #[derive(Default)]
 pub struct PoolItemArgs {
     pub init_type: Option<Type>,
     pub shutdown_method: Option<Ident>,
+    pub key_type: Option<Type>,
+    pub batch_method: Option<Ident>,
+    pub max_batch_size: Option<syn::LitInt>,
+    pub fair_budget: Option<syn::LitInt>,
+    pub on_shutdown_method: Option<Ident>,
+    pub added_method: Option<Ident>,
+    pub removed_method: Option<Ident>,
+    pub message_error_method: Option<Ident>,
+    pub thread_start_info_type: Option<Type>,
+    pub thread_start_method: Option<Ident>,
+    pub thread_stop_method: Option<Ident>,
+    pub error_response_method: Option<Ident>,
+    pub id_not_found_method: Option<Ident>,
+    pub pre_process_method: Option<Ident>,
+    pub post_process_method: Option<Ident>,
+    pub id_thread_router_method: Option<Ident>,
+    pub multi_item_rejected_method: Option<Ident>,
+    /// The name returned by `PoolItem::name`; the pool item's type unless one has been specified
+    pub name: Option<syn::LitStr>,
+    /// The name of the enum of the API's responses; `{Item}Response` unless one has been specified
+    pub api_response_name: Option<Ident>,
+    /// The name of the generated client; `{Item}Client` unless one has been specified
+    pub client_name: Option<Ident>,
+    /// True if no client is generated
+    pub no_client: bool,
+    /// The `#[pool_protocol]` traits whose messages the pool item accepts
+    pub implements: Vec<syn::Path>,
+    /// True if every request struct has named fields (see [`MessagingArgs::named_fields`])
+    pub named_fields: bool,
+}
+
+impl PoolItemArgs {
+    /// The type of the pool item's key; `u64` unless one has been specified
+    pub fn key_type(&self) -> Type {
+        self.key_type
+            .clone()
+            .unwrap_or_else(|| syn::parse_quote!(u64))
+    }
 }
 
 impl std::fmt::Debug for PoolItemArgs {
@@ -35,6 +273,41 @@ impl std::fmt::Debug for PoolItemArgs {
         f.debug_struct("PoolItemArgs")
             .field("init_type", &self.init_type.as_ref().map(|_| "Some(Type)"))
             .field("shutdown_method", &self.shutdown_method)
+            .field("key_type", &self.key_type.as_ref().map(|_| "Some(Type)"))
+            .field("batch_method", &self.batch_method)
+            .field(
+                "max_batch_size",
+                &self.max_batch_size.as_ref().map(syn::LitInt::to_string),
+            )
+            .field(
+                "fair_budget",
+                &self.fair_budget.as_ref().map(syn::LitInt::to_string),
+            )
+            .field("on_shutdown_method", &self.on_shutdown_method)
+            .field("added_method", &self.added_method)
+            .field("removed_method", &self.removed_method)
+            .field("message_error_method", &self.message_error_method)
+            .field(
+                "thread_start_info_type",
+                &self.thread_start_info_type.as_ref().map(|_| "Some(Type)"),
+            )
+            .field("thread_start_method", &self.thread_start_method)
+            .field("thread_stop_method", &self.thread_stop_method)
+            .field("error_response_method", &self.error_response_method)
+            .field("id_not_found_method", &self.id_not_found_method)
+            .field("pre_process_method", &self.pre_process_method)
+            .field("post_process_method", &self.post_process_method)
+            .field("id_thread_router_method", &self.id_thread_router_method)
+            .field(
+                "multi_item_rejected_method",
+                &self.multi_item_rejected_method,
+            )
+            .field("name", &self.name.as_ref().map(syn::LitStr::value))
+            .field("api_response_name", &self.api_response_name)
+            .field("client_name", &self.client_name)
+            .field("no_client", &self.no_client)
+            .field("implements", &self.implements.len())
+            .field("named_fields", &self.named_fields)
             .finish()
     }
 }
@@ -48,54 +321,109 @@ impl Parse for PoolItemArgs {
 
         let vars = Punctuated::<syn::Meta, Token![,]>::parse_terminated(input)?;
         for meta in vars {
-            if let syn::Meta::NameValue(nv) = meta {
+            if matches!(&meta, syn::Meta::Path(path) if is_named_fields(path)) {
+                args.named_fields = true;
+            } else if matches!(&meta, syn::Meta::Path(path) if path.is_ident("NoClient")) {
+                if args.client_name.is_some() {
+                    return Err(client_conflict(&meta));
+                }
+                args.no_client = true;
+            } else if let syn::Meta::NameValue(nv) = meta {
                 if nv.path.is_ident("Init") {
-                    if let syn::Expr::Path(path) = nv.value {
-                        args.init_type = Some(Type::Path(syn::TypePath {
-                            qself: None,
-                            path: path.path,
-                        }));
-                    } else if let syn::Expr::Lit(lit) = nv.value {
-                        if let syn::Lit::Str(lit_str) = lit.lit {
-                            let path: syn::Path = lit_str.parse()?;
-                            args.init_type = Some(Type::Path(syn::TypePath { qself: None, path }));
-                        } else {
-                            return Err(syn::Error::new_spanned(
-                                lit,
-                                "Expected string literal for Init",
-                            ));
-                        }
-                    } else {
-                        return Err(syn::Error::new_spanned(
-                            nv.value,
-                            "Expected a type path or string literal for Init",
-                        ));
+                    args.init_type = Some(parse_type(nv.value, "Init")?);
+                } else if nv.path.is_ident("Key") {
+                    args.key_type = Some(parse_type(nv.value, "Key")?);
+                } else if nv.path.is_ident("Batch") {
+                    args.batch_method = Some(parse_method_name(nv.value, "Batch")?);
+                } else if nv.path.is_ident("MaxBatchSize") {
+                    args.max_batch_size = Some(parse_usize(nv.value, "MaxBatchSize")?);
+                } else if nv.path.is_ident("FairBudget") {
+                    args.fair_budget = Some(parse_usize(nv.value, "FairBudget")?);
+                } else if nv.path.is_ident("OnShutdown") {
+                    args.on_shutdown_method = Some(parse_method_name(nv.value, "OnShutdown")?);
+                } else if nv.path.is_ident("Added") {
+                    args.added_method = Some(parse_method_name(nv.value, "Added")?);
+                } else if nv.path.is_ident("Removed") {
+                    args.removed_method = Some(parse_method_name(nv.value, "Removed")?);
+                } else if nv.path.is_ident("MessageError") {
+                    args.message_error_method = Some(parse_method_name(nv.value, "MessageError")?);
+                } else if nv.path.is_ident("ThreadStartInfo") {
+                    args.thread_start_info_type = Some(parse_type(nv.value, "ThreadStartInfo")?);
+                } else if nv.path.is_ident("ThreadStart") {
+                    args.thread_start_method = Some(parse_method_name(nv.value, "ThreadStart")?);
+                } else if nv.path.is_ident("ThreadStop") {
+                    args.thread_stop_method = Some(parse_method_name(nv.value, "ThreadStop")?);
+                } else if nv.path.is_ident("Implements") {
+                    args.implements.push(parse_path(nv.value, "Implements")?);
+                } else if nv.path.is_ident("ErrorResponse") {
+                    args.error_response_method =
+                        Some(parse_method_name(nv.value, "ErrorResponse")?);
+                } else if nv.path.is_ident("IdNotFound") {
+                    args.id_not_found_method = Some(parse_method_name(nv.value, "IdNotFound")?);
+                } else if nv.path.is_ident("PreProcess") {
+                    args.pre_process_method = Some(parse_method_name(nv.value, "PreProcess")?);
+                } else if nv.path.is_ident("PostProcess") {
+                    args.post_process_method = Some(parse_method_name(nv.value, "PostProcess")?);
+                } else if nv.path.is_ident("IdThreadRouter") {
+                    args.id_thread_router_method =
+                        Some(parse_method_name(nv.value, "IdThreadRouter")?);
+                } else if nv.path.is_ident("MultiItemRejected") {
+                    args.multi_item_rejected_method =
+                        Some(parse_method_name(nv.value, "MultiItemRejected")?);
+                } else if nv.path.is_ident("Name") {
+                    args.name = Some(parse_str(nv.value, "Name")?);
+                } else if nv.path.is_ident("ApiResponse") {
+                    args.api_response_name = Some(parse_method_name(nv.value, "ApiResponse")?);
+                } else if nv.path.is_ident("Client") {
+                    if args.no_client {
+                        return Err(client_conflict(&nv));
                     }
+                    args.client_name = Some(parse_method_name(nv.value, "Client")?);
                 } else if nv.path.is_ident("Shutdown") {
-                    if let syn::Expr::Path(path) = nv.value {
-                        if let Some(ident) = path.path.get_ident() {
-                            args.shutdown_method = Some(ident.clone());
-                        } else {
-                            return Err(syn::Error::new_spanned(
-                                path,
-                                "Expected an identifier for Shutdown",
-                            ));
-                        }
-                    } else if let syn::Expr::Lit(lit) = nv.value {
-                        if let syn::Lit::Str(lit_str) = lit.lit {
-                            args.shutdown_method = Some(lit_str.parse()?);
-                        } else {
-                            return Err(syn::Error::new_spanned(
-                                lit,
-                                "Expected string literal for Shutdown",
-                            ));
-                        }
-                    } else {
-                        return Err(syn::Error::new_spanned(
-                            nv.value,
-                            "Expected an identifier or string literal for Shutdown",
-                        ));
-                    }
+                    args.shutdown_method = Some(parse_method_name(nv.value, "Shutdown")?);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        &nv.path,
+                        format!("Unknown pool_item option; expected one of {POOL_ITEM_OPTIONS}"),
+                    ));
+                }
+            } else {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    format!("Expected `NamedFields`, `NoClient` or `Option = value` where the option is one of {POOL_ITEM_OPTIONS}"),
+                ));
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// The error for a pool item that both names its client and asks for no client
+fn client_conflict(option: impl quote::ToTokens) -> syn::Error {
+    syn::Error::new_spanned(option, "`Client` and `NoClient` cannot both be specified")
+}
+
+/// The arguments of `#[pool_protocol]`
+#[derive(Debug, Default)]
+pub struct ProtocolArgs {
+    /// True if every request struct has named fields (see [`MessagingArgs::named_fields`])
+    pub named_fields: bool,
+}
+
+impl Parse for ProtocolArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = ProtocolArgs::default();
+        let vars = Punctuated::<syn::Meta, Token![,]>::parse_terminated(input)?;
+        for meta in vars {
+            match meta {
+                syn::Meta::Path(path) if is_named_fields(&path) => {
+                    args.named_fields = true;
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "Unknown pool_protocol option; expected `NamedFields`",
+                    ));
                 }
             }
         }
@@ -118,6 +446,297 @@ mod tests {
         assert_eq!(args.response_type.to_string(), "Response");
     }
 
+    #[test]
+    fn test_parse_priority() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, priority = high };
+        let args = parser.parse2(tokens).expect("Failed to parse priority");
+        assert_eq!(args.request_type.to_string(), "Request");
+        assert_eq!(args.response_type.to_string(), "Response");
+        assert_eq!(args.priority.unwrap().to_string(), "High");
+    }
+
+    #[test]
+    fn test_parse_no_priority_is_none() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response };
+        let args = parser.parse2(tokens).expect("Failed to parse valid args");
+        assert!(args.priority.is_none());
+    }
+
+    #[test]
+    fn test_parse_stream() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, stream };
+        let args = parser.parse2(tokens).expect("Failed to parse stream");
+        assert_eq!(args.response_type.to_string(), "Response");
+        assert!(args.stream);
+
+        let tokens = quote! { Request, Response };
+        let args = parser.parse2(tokens).expect("Failed to parse valid args");
+        assert!(!args.stream);
+    }
+
+    #[test]
+    fn test_parse_multi() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, multi };
+        let args = parser.parse2(tokens).expect("Failed to parse multi");
+        assert!(args.multi);
+
+        let tokens = quote! { Request, Response, multi, stream };
+        let err = parser
+            .parse2(tokens)
+            .expect_err("Should fail with multi and stream");
+        assert!(err
+            .to_string()
+            .contains("`stream` and `multi` cannot be combined"));
+    }
+
+    #[test]
+    fn test_parse_routing_key() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, routing_key = user_id };
+        let args = parser.parse2(tokens).expect("Failed to parse routing_key");
+        assert_eq!(args.routing_key.unwrap().to_string(), "user_id");
+
+        let tokens = quote! { Request, Response, routing_key = "user_id" };
+        let err = parser
+            .parse2(tokens)
+            .expect_err("Should fail with a literal routing_key");
+        assert!(err
+            .to_string()
+            .contains("Expected the name of a method parameter for routing_key"));
+    }
+
+    #[test]
+    fn test_parse_no_error_report() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let args = parser
+            .parse2(quote! { Request, Response, no_error_report })
+            .expect("Failed to parse no_error_report");
+        assert!(args.no_error_report);
+
+        let args = parser
+            .parse2(quote! { Request, Response })
+            .expect("Failed to parse the types");
+        assert!(!args.no_error_report);
+    }
+
+    #[test]
+    fn test_parse_named_fields() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, named_fields };
+        let args = parser.parse2(tokens).expect("Failed to parse named_fields");
+        assert!(args.named_fields);
+
+        let args: PoolItemArgs = syn::parse2(quote! { NamedFields, Key = "String" })
+            .expect("Failed to parse NamedFields");
+        assert!(args.named_fields);
+        assert!(args.key_type.is_some());
+
+        let args: PoolItemArgs =
+            syn::parse2(quote! { named_fields }).expect("Failed to parse named_fields");
+        assert!(args.named_fields);
+        let args: ProtocolArgs =
+            syn::parse2(quote! { named_fields }).expect("Failed to parse named_fields");
+        assert!(args.named_fields);
+        assert!(!PoolItemArgs::default().named_fields);
+    }
+
+    #[test]
+    fn test_parse_invalid_priority() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, priority = urgent };
+        let err = parser
+            .parse2(tokens)
+            .expect_err("Should fail with unknown priority");
+        assert!(err
+            .to_string()
+            .contains("Expected one of `high`, `normal` or `low` for priority"));
+    }
+
+    #[test]
+    fn test_parse_pool_item_lifecycle_methods() {
+        let args: PoolItemArgs =
+            syn::parse2(quote! { Added = "added", Removed = removed, MessageError = "failed" })
+                .expect("Failed to parse lifecycle methods");
+        assert_eq!(args.added_method.unwrap().to_string(), "added");
+        assert_eq!(args.removed_method.unwrap().to_string(), "removed");
+        assert_eq!(args.message_error_method.unwrap().to_string(), "failed");
+
+        let err = syn::parse2::<PoolItemArgs>(quote! { Removed = 1 })
+            .expect_err("Should fail with a non method name");
+        assert!(err
+            .to_string()
+            .contains("Expected an identifier or string literal for Removed"));
+    }
+
+    #[test]
+    fn test_parse_pool_item_types_methods_and_sizes() {
+        let args: PoolItemArgs = syn::parse2(quote! {
+            Init = "MyInit", Key = String, Shutdown = stop, OnShutdown = "drain",
+            Batch = apply, MaxBatchSize = 16, FairBudget = 4
+        })
+        .expect("Failed to parse options");
+        let init_type = args.init_type.unwrap();
+        let key_type = args.key_type.unwrap();
+        assert_eq!(quote!(#init_type).to_string(), "MyInit");
+        assert_eq!(quote!(#key_type).to_string(), "String");
+        assert_eq!(args.shutdown_method.unwrap().to_string(), "stop");
+        assert_eq!(args.on_shutdown_method.unwrap().to_string(), "drain");
+        assert_eq!(args.batch_method.unwrap().to_string(), "apply");
+        assert_eq!(args.max_batch_size.unwrap().to_string(), "16");
+        assert_eq!(args.fair_budget.unwrap().to_string(), "4");
+
+        let err = syn::parse2::<PoolItemArgs>(quote! { OnShutdown = 1 })
+            .expect_err("Should fail with a non method name");
+        assert!(err
+            .to_string()
+            .contains("Expected an identifier or string literal for OnShutdown"));
+
+        let err = syn::parse2::<PoolItemArgs>(quote! { MaxBatchSize = "16" })
+            .expect_err("Should fail with a non integer size");
+        assert!(err
+            .to_string()
+            .contains("Expected an integer literal for MaxBatchSize"));
+    }
+
+    #[test]
+    fn test_parse_pool_item_thread_lifecycle() {
+        let args: PoolItemArgs = syn::parse2(quote! {
+            ThreadStartInfo = "RefCell<Stats>", ThreadStart = start, ThreadStop = "stop"
+        })
+        .expect("Failed to parse thread lifecycle");
+        let thread_start_info_type = args.thread_start_info_type.unwrap();
+        assert_eq!(
+            quote!(#thread_start_info_type).to_string(),
+            "RefCell < Stats >"
+        );
+        assert_eq!(args.thread_start_method.unwrap().to_string(), "start");
+        assert_eq!(args.thread_stop_method.unwrap().to_string(), "stop");
+    }
+
+    #[test]
+    fn test_parse_pool_item_implements() {
+        let args: PoolItemArgs =
+            syn::parse2(quote! { Implements = Health, Implements = "protocols::Snapshot" })
+                .expect("Failed to parse implemented protocols");
+        let implements: Vec<String> = args
+            .implements
+            .iter()
+            .map(|path| quote!(#path).to_string())
+            .collect();
+        assert_eq!(vec!["Health", "protocols :: Snapshot"], implements);
+    }
+
+    #[test]
+    fn test_parse_protocol_args() {
+        let args: ProtocolArgs =
+            syn::parse2(quote! { NamedFields }).expect("Failed to parse protocol args");
+        assert!(args.named_fields);
+
+        let err = syn::parse2::<ProtocolArgs>(quote! { Key = String })
+            .expect_err("Should fail with an unknown option");
+        assert!(err
+            .to_string()
+            .contains("Unknown pool_protocol option; expected `NamedFields`"));
+    }
+
+    #[test]
+    fn test_parse_pool_item_routing_and_processing_hooks() {
+        let args: PoolItemArgs = syn::parse2(quote! {
+            IdNotFound = missing, PreProcess = "enter", PostProcess = exit, IdThreadRouter = "route"
+        })
+        .expect("Failed to parse hooks");
+        assert_eq!(args.id_not_found_method.unwrap().to_string(), "missing");
+        assert_eq!(args.pre_process_method.unwrap().to_string(), "enter");
+        assert_eq!(args.post_process_method.unwrap().to_string(), "exit");
+        assert_eq!(args.id_thread_router_method.unwrap().to_string(), "route");
+    }
+
+    #[test]
+    fn test_parse_pool_item_unknown_option() {
+        let err = syn::parse2::<PoolItemArgs>(quote! { Init = "MyInit", Shutdwon = "cleanup" })
+            .expect_err("Should fail with an unknown option");
+        assert!(err
+            .to_string()
+            .starts_with("Unknown pool_item option; expected one of `Init`"));
+
+        let err = syn::parse2::<PoolItemArgs>(quote! { Batch(apply) })
+            .expect_err("Should fail with an option that is not a name value");
+        assert!(err
+            .to_string()
+            .starts_with("Expected `NamedFields`, `NoClient` or `Option = value`"));
+    }
+
+    #[test]
+    fn test_parse_pool_item_error_response() {
+        let args: PoolItemArgs = syn::parse2(quote! { ErrorResponse = "failed" })
+            .expect("Failed to parse error response method");
+        assert_eq!(args.error_response_method.unwrap().to_string(), "failed");
+    }
+
+    #[test]
+    fn test_parse_pool_item_name_and_multi_item_rejected() {
+        let args: PoolItemArgs =
+            syn::parse2(quote! { Name = "sessions", MultiItemRejected = "rejected" }).unwrap();
+        assert_eq!(args.name.unwrap().value(), "sessions");
+        assert_eq!(
+            args.multi_item_rejected_method.unwrap().to_string(),
+            "rejected"
+        );
+
+        let err = syn::parse2::<PoolItemArgs>(quote! { Name = sessions })
+            .expect_err("Should fail with a name that is not a string literal");
+        assert!(err
+            .to_string()
+            .contains("Expected a string literal for Name"));
+    }
+
+    #[test]
+    fn test_parse_pool_item_client() {
+        let args: PoolItemArgs = syn::parse2(quote! { Client = "Tally" }).unwrap();
+        assert_eq!(args.client_name.unwrap().to_string(), "Tally");
+        assert!(!args.no_client);
+
+        let args: PoolItemArgs = syn::parse2(quote! { NoClient }).unwrap();
+        assert!(args.client_name.is_none());
+        assert!(args.no_client);
+    }
+
+    #[test]
+    fn test_parse_pool_item_client_and_no_client() {
+        for options in [
+            quote! { Client = "Tally", NoClient },
+            quote! { NoClient, Client = "Tally" },
+        ] {
+            let err = syn::parse2::<PoolItemArgs>(options)
+                .expect_err("Should fail with both Client and NoClient");
+            assert_eq!(
+                "`Client` and `NoClient` cannot both be specified",
+                err.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_pool_item_api_response() {
+        let args: PoolItemArgs = syn::parse2(quote! { ApiResponse = RelayMessage })
+            .expect("Failed to parse api response name");
+        assert_eq!(args.api_response_name.unwrap().to_string(), "RelayMessage");
+    }
+
+    #[test]
+    fn test_parse_unknown_option() {
+        let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
+        let tokens = quote! { Request, Response, speed = high };
+        let err = parser
+            .parse2(tokens)
+            .expect_err("Should fail with unknown option");
+        assert!(err.to_string().contains("Unknown messaging option"));
+    }
+
     #[test]
     fn test_parse_too_few_args() {
         let parser = |input: syn::parse::ParseStream| MessagingArgs::parse(input);
@@ -0,0 +1,535 @@
+use crate::generation::{owned_field_type, returns_result};
+use crate::parsing::{MessagingArgs, ProtocolArgs};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{FnArg, Ident, ItemTrait, ReturnType, TraitItem, Type};
+
+/// A `#[messaging]` method of a protocol trait
+struct ProtocolMethod {
+    args: MessagingArgs,
+    method_name: Ident,
+    /// The request's fields; the id (of type `K`) followed by the method's parameters
+    request_fields: Vec<(Ident, Type)>,
+    /// The arguments that the method is called with, taken from the request
+    call_args: Vec<TokenStream>,
+    routing_key_field: Option<syn::Member>,
+    result_type: Type,
+    /// The method returns a `Result` whose errors are reported through `response_error`
+    fallible: bool,
+}
+
+pub fn generate_pool_protocol(mut input: ItemTrait, protocol_args: ProtocolArgs) -> TokenStream {
+    if !input.generics.params.is_empty() {
+        return syn::Error::new_spanned(
+            &input.generics,
+            "#[pool_protocol] traits cannot be generic",
+        )
+        .to_compile_error();
+    }
+
+    let trait_name = &input.ident;
+    let protocol_name = format_ident!("{}Protocol", trait_name);
+    let api_name = format_ident!("{}Api", trait_name);
+
+    let mut methods = Vec::new();
+    for item in &mut input.items {
+        if let TraitItem::Fn(method) = item {
+            let Some(index) = method
+                .attrs
+                .iter()
+                .position(|attr| attr.path().is_ident("messaging"))
+            else {
+                continue;
+            };
+            let attr = method.attrs.remove(index);
+            let mut args: MessagingArgs = match attr.parse_args() {
+                Ok(args) => args,
+                Err(e) => return e.to_compile_error(),
+            };
+            args.named_fields |= protocol_args.named_fields;
+            match protocol_method(args, &method.sig) {
+                Ok(method) => methods.push(method),
+                Err(e) => return e.to_compile_error(),
+            }
+        }
+    }
+
+    let vis = &input.vis;
+    let messages: Vec<TokenStream> = methods
+        .iter()
+        .map(|method| generate_protocol_messages(method, vis, &protocol_name, &api_name))
+        .collect();
+    let api = generate_protocol_api(&methods, vis, trait_name, &protocol_name, &api_name);
+
+    quote! {
+        #input
+
+        /// Identifies the protocol in `Implements`
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #protocol_name;
+
+        impl messaging_thread_pool::Protocol for #protocol_name {
+            type Api<K: messaging_thread_pool::PoolKey> = #api_name<K>;
+        }
+
+        #(#messages)*
+
+        #api
+    }
+}
+
+/// Reads the request's fields and the arguments of the call from a protocol method's signature
+fn protocol_method(args: MessagingArgs, sig: &syn::Signature) -> syn::Result<ProtocolMethod> {
+    if args.stream || args.multi {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "stream and multi methods are not supported by #[pool_protocol]",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "#[pool_protocol] methods cannot be generic",
+        ));
+    }
+    if !matches!(sig.inputs.first(), Some(FnArg::Receiver(receiver)) if receiver.reference.is_some())
+    {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "#[pool_protocol] methods must take `&self` or `&mut self`",
+        ));
+    }
+
+    let member = |position: usize, name: &Ident| {
+        if args.named_fields {
+            syn::Member::Named(name.clone())
+        } else {
+            syn::Member::Unnamed(syn::Index::from(position))
+        }
+    };
+    let typed_inputs: Vec<&syn::PatType> = sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(pat_type) => Some(pat_type),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    // the id is renamed if one of the method's parameters is already called `id`
+    let id_name = if typed_inputs.iter().any(|pat_type| {
+        matches!(pat_type.pat.as_ref(), syn::Pat::Ident(pat_ident) if pat_ident.ident == "id")
+    }) {
+        format_ident!("pool_item_id")
+    } else {
+        format_ident!("id")
+    };
+
+    let mut request_fields = vec![(id_name, syn::parse_quote!(K))];
+    let mut call_args = Vec::new();
+    let mut routing_key_field = None;
+    for pat_type in typed_inputs {
+        let name = if let syn::Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+            pat_ident.ident.clone()
+        } else {
+            format_ident!("arg{}", request_fields.len())
+        };
+        // the request owns its fields; a borrowed parameter is passed a reference to one
+        let (field_type, borrowed) = owned_field_type(&pat_type.ty)?;
+        let reference = if borrowed {
+            quote! { & }
+        } else {
+            quote! {}
+        };
+        let field = member(request_fields.len(), &name);
+        call_args.push(quote! { #reference request.#field });
+        if args.routing_key.as_ref() == Some(&name) {
+            routing_key_field = Some(field);
+        }
+        request_fields.push((name, field_type));
+    }
+    if let (Some(routing_key), None) = (&args.routing_key, &routing_key_field) {
+        return Err(syn::Error::new_spanned(
+            routing_key,
+            "routing_key must name one of the method's parameters",
+        ));
+    }
+
+    let result_type = match &sig.output {
+        ReturnType::Default => syn::parse_quote!(()),
+        ReturnType::Type(_, ty) => ty.as_ref().clone(),
+    };
+
+    Ok(ProtocolMethod {
+        method_name: sig.ident.clone(),
+        fallible: !args.no_error_report && returns_result(&sig.output),
+        args,
+        request_fields,
+        call_args,
+        routing_key_field,
+        result_type,
+    })
+}
+
+/// The request and response of a protocol method; generic over the key of the pool item and
+/// usable by any pool item that implements the protocol
+fn generate_protocol_messages(
+    method: &ProtocolMethod,
+    vis: &syn::Visibility,
+    protocol_name: &Ident,
+    api_name: &Ident,
+) -> TokenStream {
+    let request_name = &method.args.request_type;
+    let response_name = &method.args.response_type;
+    let result_type = &method.result_type;
+    let field_types: Vec<&Type> = method.request_fields.iter().map(|(_, ty)| ty).collect();
+
+    let (request_struct, id_field) = if method.args.named_fields {
+        let field_names: Vec<&Ident> = method.request_fields.iter().map(|(name, _)| name).collect();
+        let id_field = syn::Member::Named(field_names[0].clone());
+        (
+            quote! {
+                #[derive(Debug, Clone, PartialEq)]
+                #vis struct #request_name<K = u64> {
+                    #(pub #field_names: #field_types,)*
+                }
+
+                impl<K> #request_name<K> {
+                    #[allow(clippy::too_many_arguments)]
+                    pub fn new(#(#field_names: #field_types),*) -> Self {
+                        Self { #(#field_names,)* }
+                    }
+                }
+            },
+            id_field,
+        )
+    } else {
+        (
+            quote! {
+                #[derive(Debug, Clone, PartialEq)]
+                #vis struct #request_name<K = u64>( #(pub #field_types),* );
+            },
+            syn::Member::Unnamed(syn::Index::from(0)),
+        )
+    };
+
+    // only override the trait's default priority and routing when they have been specified
+    let priority_const = if let Some(priority) = &method.args.priority {
+        quote! { const PRIORITY: messaging_thread_pool::Priority = messaging_thread_pool::Priority::#priority; }
+    } else {
+        quote! {}
+    };
+    let routing_key_fn = if let Some(field) = &method.routing_key_field {
+        quote! {
+            fn routing_key(&self) -> u64 {
+                messaging_thread_pool::PoolKey::routing_hash(&self.#field)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        #request_struct
+
+        impl<K> messaging_thread_pool::IdTargeted<K> for #request_name<K>
+        where
+            K: messaging_thread_pool::PoolKey,
+        {
+            fn id(&self) -> K {
+                self.#id_field.clone()
+            }
+            #routing_key_fn
+        }
+
+        impl<P> messaging_thread_pool::RequestWithResponse<P> for #request_name<P::Key>
+        where
+            P: messaging_thread_pool::Implements<#protocol_name>,
+        {
+            type Response = #response_name<P::Key>;
+            #priority_const
+        }
+
+        impl<P> From<#request_name<P::Key>> for messaging_thread_pool::ThreadRequestResponse<P>
+        where
+            P: messaging_thread_pool::Implements<#protocol_name>,
+        {
+            fn from(request: #request_name<P::Key>) -> Self {
+                messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                    <P as messaging_thread_pool::Implements<#protocol_name>>::into_api(
+                        #api_name::#request_name(request)
+                    )
+                )
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        #vis struct #response_name<K = u64> {
+            pub id: K,
+            pub result: #result_type,
+        }
+
+        impl<P> From<messaging_thread_pool::ThreadRequestResponse<P>> for #response_name<P::Key>
+        where
+            P: messaging_thread_pool::Implements<#protocol_name>,
+        {
+            fn from(response: messaging_thread_pool::ThreadRequestResponse<P>) -> Self {
+                let messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(api) = response else {
+                    panic!("Unexpected response type")
+                };
+                match <P as messaging_thread_pool::Implements<#protocol_name>>::from_api(api) {
+                    Some(#api_name::#response_name(response)) => response,
+                    _ => panic!("Unexpected response type"),
+                }
+            }
+        }
+
+        impl<P> From<#response_name<P::Key>> for messaging_thread_pool::ThreadRequestResponse<P>
+        where
+            P: messaging_thread_pool::Implements<#protocol_name>,
+        {
+            fn from(response: #response_name<P::Key>) -> Self {
+                messaging_thread_pool::ThreadRequestResponse::MessagePoolItem(
+                    <P as messaging_thread_pool::Implements<#protocol_name>>::into_api(
+                        #api_name::#response_name(response)
+                    )
+                )
+            }
+        }
+    }
+}
+
+/// The enum of the protocol's requests and responses, which the pool items that implement the
+/// protocol hold in their own API, and the function that has a pool item process its requests
+fn generate_protocol_api(
+    methods: &[ProtocolMethod],
+    vis: &syn::Visibility,
+    trait_name: &Ident,
+    protocol_name: &Ident,
+    api_name: &Ident,
+) -> TokenStream {
+    let request_names: Vec<&Ident> = methods
+        .iter()
+        .map(|method| &method.args.request_type)
+        .collect();
+    let response_names: Vec<&Ident> = methods
+        .iter()
+        .map(|method| &method.args.response_type)
+        .collect();
+    let process_arms = methods.iter().map(|method| {
+        let request_name = &method.args.request_type;
+        let response_name = &method.args.response_type;
+        let method_name = &method.method_name;
+        let call_args = &method.call_args;
+        quote! {
+            #api_name::#request_name(request) => {
+                let id = messaging_thread_pool::IdTargeted::id(&request);
+                let result = pool_item.#method_name(#(#call_args),*);
+                #api_name::#response_name(#response_name { id, result })
+            }
+        }
+    });
+    let fallible_response_names: Vec<&Ident> = methods
+        .iter()
+        .filter(|method| method.fallible)
+        .map(|method| &method.args.response_type)
+        .collect();
+    let response_error = if fallible_response_names.is_empty() {
+        quote! { None }
+    } else {
+        quote! {
+            match self {
+                #(
+                    #api_name::#fallible_response_names(response) => {
+                        response.result.as_ref().err().map(|error| error as &dyn std::fmt::Debug)
+                    }
+                )*
+                _ => None,
+            }
+        }
+    };
+    let doc = format!("The requests and responses of [`{trait_name}`], as held by the API of each pool item that implements [`{protocol_name}`]");
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, PartialEq)]
+        #vis enum #api_name<K = u64> {
+            #(
+                #request_names(#request_names<K>),
+                #response_names(#response_names<K>),
+            )*
+        }
+
+        impl<K> messaging_thread_pool::IdTargeted<K> for #api_name<K>
+        where
+            K: messaging_thread_pool::PoolKey,
+        {
+            fn id(&self) -> K {
+                match self {
+                    #(
+                        #api_name::#request_names(request) => messaging_thread_pool::IdTargeted::id(request),
+                        #api_name::#response_names(response) => response.id.clone(),
+                    )*
+                }
+            }
+
+            fn routing_key(&self) -> u64 {
+                match self {
+                    #(
+                        #api_name::#request_names(request) => messaging_thread_pool::IdTargeted::routing_key(request),
+                        #api_name::#response_names(response) => messaging_thread_pool::PoolKey::routing_hash(&response.id),
+                    )*
+                }
+            }
+        }
+
+        impl<K> #api_name<K>
+        where
+            K: messaging_thread_pool::PoolKey,
+        {
+            /// The name of the message's request, for diagnostics
+            pub fn message_type(&self) -> &'static str {
+                match self {
+                    #(
+                        #api_name::#request_names(_) | #api_name::#response_names(_) => stringify!(#request_names),
+                    )*
+                }
+            }
+
+            /// Has the pool item process the request, returning its response
+            pub fn process<T>(self, pool_item: &mut T) -> Self
+            where
+                T: #trait_name + ?Sized,
+            {
+                match self {
+                    #(#process_arms)*
+                    _ => panic!("Unexpected message in process (expected Request)"),
+                }
+            }
+
+            /// The error held by a response whose method returned an `Err`, for
+            /// `PoolItem::response_error`
+            pub fn response_error(&self) -> Option<&dyn std::fmt::Debug> {
+                #response_error
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_generate_pool_protocol() {
+        let input: ItemTrait = parse_quote! {
+            pub trait Health {
+                #[messaging(HealthRequest, HealthResponse)]
+                fn health(&self, verbose: bool, component: &str) -> String;
+
+                fn helper(&self) {}
+            }
+        };
+
+        let output = generate_pool_protocol(input, ProtocolArgs::default());
+        let output_str = output.to_string();
+
+        // the trait is kept without the messaging attributes
+        assert!(output_str.contains("pub trait Health"));
+        assert!(!output_str.contains("# [messaging"));
+        assert!(output_str.contains("pub struct HealthProtocol ;"));
+        assert!(output_str
+            .contains("type Api < K : messaging_thread_pool :: PoolKey > = HealthApi < K > ;"));
+        assert!(output_str
+            .contains("pub struct HealthRequest < K = u64 > (pub K , pub bool , pub String) ;"));
+        assert!(output_str.contains(
+            "impl < P > messaging_thread_pool :: RequestWithResponse < P > for HealthRequest < P :: Key > where P : messaging_thread_pool :: Implements < HealthProtocol >"
+        ));
+        assert!(output_str.contains(
+            "pub struct HealthResponse < K = u64 > { pub id : K , pub result : String , }"
+        ));
+        assert!(
+            output_str.contains("let result = pool_item . health (request . 1 , & request . 2) ;")
+        );
+        assert!(output_str.contains("T : Health + ? Sized"));
+    }
+
+    #[test]
+    fn test_generate_pool_protocol_response_error() {
+        let input: ItemTrait = parse_quote! {
+            pub trait Store {
+                #[messaging(SaveRequest, SaveResponse)]
+                fn save(&mut self, value: u64) -> Result<(), String>;
+
+                #[messaging(CheckRequest, CheckResponse, no_error_report)]
+                fn check(&self) -> validation::Result;
+
+                #[messaging(CountRequest, CountResponse)]
+                fn count(&self) -> usize;
+            }
+        };
+
+        let output_str = generate_pool_protocol(input, ProtocolArgs::default()).to_string();
+
+        let response_error = &output_str[output_str
+            .find("pub fn response_error")
+            .expect("response_error to be generated")..];
+        let response_error = &response_error[..response_error.find("_ => None").unwrap()];
+        assert!(response_error.contains("StoreApi :: SaveResponse (response)"));
+        assert!(!response_error.contains("CheckResponse"));
+        assert!(!response_error.contains("CountResponse"));
+    }
+
+    #[test]
+    fn test_generate_pool_protocol_named_fields() {
+        let input: ItemTrait = parse_quote! {
+            pub trait Snapshot {
+                #[messaging(SnapshotRequest, SnapshotResponse, routing_key = id)]
+                fn snapshot(&self, id: u64) -> Vec<u8>;
+            }
+        };
+
+        let output = generate_pool_protocol(input, ProtocolArgs { named_fields: true });
+        let output_str = output.to_string();
+
+        assert!(output_str.contains(
+            "pub struct SnapshotRequest < K = u64 > { pub pool_item_id : K , pub id : u64 , }"
+        ));
+        assert!(output_str.contains(
+            "fn routing_key (& self) -> u64 { messaging_thread_pool :: PoolKey :: routing_hash (& self . id) }"
+        ));
+        assert!(output_str.contains("let result = pool_item . snapshot (request . id) ;"));
+        // the protocol's messages are routed by the routing key of the request that they hold
+        assert!(output_str.contains(
+            "SnapshotApi :: SnapshotRequest (request) => messaging_thread_pool :: IdTargeted :: routing_key (request)"
+        ));
+    }
+
+    #[test]
+    fn test_generate_pool_protocol_rejects_unsupported_methods() {
+        let error =
+            |input: ItemTrait| generate_pool_protocol(input, ProtocolArgs::default()).to_string();
+
+        assert!(error(parse_quote! {
+            pub trait Health {
+                #[messaging(HealthRequest, HealthResponse, stream)]
+                fn health(&self) -> Vec<String>;
+            }
+        })
+        .contains("stream and multi methods are not supported by #[pool_protocol]"));
+        assert!(error(parse_quote! {
+            pub trait Health {
+                #[messaging(HealthRequest, HealthResponse)]
+                fn health() -> String;
+            }
+        })
+        .contains("#[pool_protocol] methods must take `&self` or `&mut self`"));
+        assert!(error(parse_quote! {
+            pub trait Health<T> {
+                #[messaging(HealthRequest, HealthResponse)]
+                fn health(&self) -> T;
+            }
+        })
+        .contains("#[pool_protocol] traits cannot be generic"));
+    }
+}
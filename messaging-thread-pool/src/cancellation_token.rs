@@ -0,0 +1,104 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// A token used to cancel a batch of requests that has been sent to a thread pool.
+///
+/// Every request sent by a single call to `send_and_receive` shares the same token.
+/// When a pool thread takes a request off of its queue it checks the token; if the token has been
+/// cancelled the request is discarded without being processed and no response is sent.
+///
+/// The iterator of responses returned by the thread pool cancels the token when it is dropped,
+/// so abandoning the responses part way through also abandons any requests that have not yet started.
+/// A request that is already being processed when the token is cancelled runs to completion.
+///
+/// Clones share the same cancellation state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a new token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all requests that share this token and have not yet been processed
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if [`cancel`](Self::cancel) has been called on this token (or any of its clones)
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// An iterator adapter that cancels a [`CancellationToken`] when it is dropped
+#[derive(Debug)]
+pub(crate) struct CancelOnDrop<I> {
+    responses: I,
+    cancellation_token: CancellationToken,
+}
+
+impl<I> CancelOnDrop<I> {
+    pub(crate) fn new(responses: I, cancellation_token: CancellationToken) -> Self {
+        Self {
+            responses,
+            cancellation_token,
+        }
+    }
+}
+
+impl<I> Iterator for CancelOnDrop<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.responses.next()
+    }
+}
+
+impl<I> Drop for CancelOnDrop<I> {
+    fn drop(&mut self) {
+        // once all responses have been received there is nothing left to cancel, so this is harmless
+        self.cancellation_token.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CancelOnDrop, CancellationToken};
+
+    #[test]
+    fn new_token_is_not_cancelled() {
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+
+    #[test]
+    fn cancel_is_seen_by_clones() {
+        let target = CancellationToken::new();
+        let clone = target.clone();
+
+        clone.cancel();
+
+        assert!(target.is_cancelled());
+    }
+
+    #[test]
+    fn dropping_cancel_on_drop_cancels_token() {
+        let token = CancellationToken::new();
+        let mut target = CancelOnDrop::new(0..3, token.clone());
+
+        assert_eq!(Some(0), target.next());
+        assert!(!token.is_cancelled());
+
+        drop(target);
+
+        assert!(token.is_cancelled());
+    }
+}
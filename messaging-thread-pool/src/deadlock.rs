@@ -0,0 +1,292 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::{LazyLock, Mutex},
+    thread::{self, Thread, ThreadId},
+};
+
+thread_local! {
+    /// Set while the current thread is running the message loop of a pool thread
+    static IS_POOL_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The pool threads that each pool thread is currently waiting on for responses
+static WAITS_FOR: LazyLock<Mutex<WaitsFor>> = LazyLock::new(|| Mutex::new(WaitsFor::default()));
+
+/// The reason a blocking call made from a pool thread was refused
+///
+/// A pool thread processes one message at a time, so a pool item that waits for a response that can
+/// only be produced by its own thread (directly, or via other pool threads that are themselves waiting)
+/// would wait forever.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeadlockError {
+    /// The pool thread sent a request to itself
+    SameThread {
+        /// The name of the pool thread
+        thread: String,
+    },
+    /// Waiting would complete a cycle of pool threads that are each waiting on the next
+    Cycle {
+        /// The names of the pool threads in the cycle, starting with the thread that made the call;
+        /// the last thread is waiting on the first
+        threads: Vec<String>,
+    },
+}
+
+impl Display for DeadlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadlockError::SameThread { thread } => {
+                write!(
+                    f,
+                    "pool thread {thread} cannot wait on a response from itself"
+                )
+            }
+            DeadlockError::Cycle { threads } => {
+                write!(
+                    f,
+                    "waiting would deadlock pool threads {} -> {}",
+                    threads.join(" -> "),
+                    threads[0]
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DeadlockError {}
+
+/// Marks the current thread as a pool thread until the returned guard is dropped
+pub(crate) fn enter_pool_thread() -> PoolThreadGuard {
+    PoolThreadGuard {
+        was_pool_thread: IS_POOL_THREAD.replace(true),
+    }
+}
+
+/// Restores the previous "is a pool thread" state of the current thread when dropped
+pub(crate) struct PoolThreadGuard {
+    was_pool_thread: bool,
+}
+
+impl Drop for PoolThreadGuard {
+    fn drop(&mut self) {
+        IS_POOL_THREAD.set(self.was_pool_thread);
+    }
+}
+
+/// The wait-for graph between pool threads
+#[derive(Debug, Default)]
+struct WaitsFor {
+    /// The threads that each waiting thread is waiting on; a thread appears once per batch waiting on it
+    edges: HashMap<ThreadId, Vec<Thread>>,
+}
+
+impl WaitsFor {
+    fn add(&mut self, waiter: ThreadId, target: Thread) {
+        self.edges.entry(waiter).or_default().push(target);
+    }
+
+    fn remove(&mut self, waiter: ThreadId, target: ThreadId) {
+        if let Some(targets) = self.edges.get_mut(&waiter) {
+            if let Some(index) = targets.iter().position(|thread| thread.id() == target) {
+                targets.swap_remove(index);
+            }
+            if targets.is_empty() {
+                self.edges.remove(&waiter);
+            }
+        }
+    }
+
+    /// Returns the threads on a path of waits from `from` to `to`, starting with `from` and ending
+    /// with a thread that is waiting on `to`
+    fn path(&self, from: &Thread, to: ThreadId) -> Option<Vec<Thread>> {
+        let mut visited = HashSet::new();
+        self.path_from(from, to, &mut visited)
+    }
+
+    fn path_from(
+        &self,
+        from: &Thread,
+        to: ThreadId,
+        visited: &mut HashSet<ThreadId>,
+    ) -> Option<Vec<Thread>> {
+        if !visited.insert(from.id()) {
+            return None;
+        }
+        let targets = self.edges.get(&from.id())?;
+        let rest = if targets.iter().any(|target| target.id() == to) {
+            Vec::new()
+        } else {
+            targets
+                .iter()
+                .find_map(|target| self.path_from(target, to, visited))?
+        };
+        let mut path = vec![from.clone()];
+        path.extend(rest);
+        Some(path)
+    }
+}
+
+/// Records the pool threads that a batch sent from a pool thread is waiting on
+///
+/// The waits are removed from the wait-for graph when this is dropped. Nothing is recorded if the
+/// batch was not sent from a pool thread, as no pool thread can be waiting on such a thread.
+#[derive(Debug)]
+pub(crate) struct WaitingOn {
+    waiter: Option<Thread>,
+    targets: Vec<Thread>,
+}
+
+impl WaitingOn {
+    pub(crate) fn new() -> Self {
+        Self {
+            waiter: IS_POOL_THREAD.get().then(thread::current),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Records that the batch will wait on a response from the target pool thread
+    ///
+    /// Returns an error if the target is the current thread or if the target is already
+    /// (indirectly) waiting on the current thread.
+    pub(crate) fn wait_on(&mut self, target: &Thread) -> Result<(), DeadlockError> {
+        let Some(waiter) = &self.waiter else {
+            return Ok(());
+        };
+        if self.targets.iter().any(|thread| thread.id() == target.id()) {
+            return Ok(());
+        }
+        if target.id() == waiter.id() {
+            return Err(DeadlockError::SameThread {
+                thread: thread_name(waiter),
+            });
+        }
+
+        let mut waits_for = WAITS_FOR.lock().expect("no poisoned locks");
+        if let Some(path) = waits_for.path(target, waiter.id()) {
+            return Err(DeadlockError::Cycle {
+                threads: std::iter::once(waiter)
+                    .chain(path.iter())
+                    .map(thread_name)
+                    .collect(),
+            });
+        }
+        waits_for.add(waiter.id(), target.clone());
+        self.targets.push(target.clone());
+        Ok(())
+    }
+
+    /// Keeps the waits recorded until the returned iterator of responses is dropped
+    pub(crate) fn hold<I>(self, responses: I) -> WaitingFor<I> {
+        WaitingFor {
+            responses,
+            _waiting_on: self,
+        }
+    }
+}
+
+impl Drop for WaitingOn {
+    fn drop(&mut self) {
+        let Some(waiter) = &self.waiter else {
+            return;
+        };
+        if self.targets.is_empty() {
+            return;
+        }
+        let mut waits_for = WAITS_FOR.lock().expect("no poisoned locks");
+        for target in &self.targets {
+            waits_for.remove(waiter.id(), target.id());
+        }
+    }
+}
+
+/// An iterator of responses that holds the waits of the batch that they are for
+#[derive(Debug)]
+pub(crate) struct WaitingFor<I> {
+    responses: I,
+    _waiting_on: WaitingOn,
+}
+
+impl<I> Iterator for WaitingFor<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.responses.next()
+    }
+}
+
+pub(crate) fn thread_name(thread: &Thread) -> String {
+    thread
+        .name()
+        .map_or_else(|| format!("{:?}", thread.id()), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::{self, Thread};
+
+    use super::{DeadlockError, WaitingOn, WaitsFor, enter_pool_thread, thread_name};
+
+    fn named_thread(name: &str) -> Thread {
+        thread::Builder::new()
+            .name(name.to_string())
+            .spawn(|| {})
+            .unwrap()
+            .thread()
+            .clone()
+    }
+
+    #[test]
+    fn path_follows_waits_to_the_target() {
+        let (a, b, c) = (named_thread("a"), named_thread("b"), named_thread("c"));
+        let mut target = WaitsFor::default();
+        target.add(a.id(), b.clone());
+        target.add(b.id(), c.clone());
+
+        let names = |path: Vec<Thread>| path.iter().map(thread_name).collect::<Vec<_>>();
+        assert_eq!(
+            Some(vec!["a".to_string(), "b".to_string()]),
+            target.path(&a, c.id()).map(names)
+        );
+        assert!(target.path(&c, a.id()).is_none());
+
+        target.remove(b.id(), c.id());
+        assert!(target.path(&a, c.id()).is_none());
+        assert!(!target.edges.contains_key(&b.id()));
+    }
+
+    #[test]
+    fn threads_that_are_not_pool_threads_are_not_tracked() {
+        let mut target = WaitingOn::new();
+
+        assert_eq!(Ok(()), target.wait_on(&thread::current()));
+        assert!(target.targets.is_empty());
+    }
+
+    #[test]
+    fn pool_thread_cannot_wait_on_itself() {
+        let _pool_thread = enter_pool_thread();
+        let mut target = WaitingOn::new();
+
+        assert!(matches!(
+            target.wait_on(&thread::current()),
+            Err(DeadlockError::SameThread { .. })
+        ));
+    }
+
+    #[test]
+    fn cycle_error_names_each_thread_in_the_cycle() {
+        let target = DeadlockError::Cycle {
+            threads: vec!["a".to_string(), "b".to_string()],
+        };
+
+        assert_eq!(
+            "waiting would deadlock pool threads a -> b -> a",
+            target.to_string()
+        );
+    }
+}
@@ -1,4 +1,59 @@
-use crate::{ThreadPool, pool_item::PoolItem};
+use crossbeam_channel::unbounded;
+
+use crate::{ThreadPool, pool_item::PoolItem, priority::Priority, thread_request_response::*};
+
+/// What a [`ThreadPool`] does with its threads when it is dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropMode {
+    /// Shut down every thread and block until it has ended (see [`ThreadPool::shutdown`])
+    #[default]
+    Shutdown,
+    /// Ask every thread to shut down once it has processed the messages already queued, but do not
+    /// wait for them; the threads are detached
+    Detach,
+}
+
+impl<P> ThreadPool<P>
+where
+    P: PoolItem,
+{
+    /// Sets what happens to the pool's threads when the pool is dropped
+    ///
+    /// ```rust
+    /// use messaging_thread_pool::{DropMode, ThreadPool, samples::Randoms};
+    ///
+    /// let pool = ThreadPool::<Randoms>::new(2).with_drop_mode(DropMode::Detach);
+    /// // returns without waiting for the threads to end
+    /// drop(pool);
+    /// ```
+    pub fn with_drop_mode(mut self, drop_mode: DropMode) -> Self {
+        self.drop_mode = drop_mode;
+        self
+    }
+
+    /// Sends a shutdown request to every thread without waiting for the responses and lets the
+    /// threads end on their own
+    fn detach(&self) {
+        // nobody listens for the shutdown responses; the threads ignore the failed send
+        let (send_to_pool, _) = unbounded::<ThreadRequestResponse<P>>();
+        for (id, endpoint) in self
+            .thread_endpoints
+            .write()
+            .expect("no poisoned locks")
+            .drain(..)
+            .enumerate()
+        {
+            // as with shutdown the request is sent on the high priority lane and everything already
+            // queued is processed first; dropping the endpoint detaches the thread
+            let _ = endpoint.send(
+                &send_to_pool,
+                ThreadShutdownRequest(id as u64),
+                Priority::High,
+                None,
+            );
+        }
+    }
+}
 
 impl<P> Drop for ThreadPool<P>
 where
@@ -6,13 +61,20 @@ where
 {
     /// implement drop to shutdown all of the thread pools threads
     fn drop(&mut self) {
-        self.shutdown();
+        match self.drop_mode {
+            DropMode::Shutdown => {
+                self.shutdown();
+            }
+            DropMode::Detach => self.detach(),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ThreadPool, samples::*};
+    use crossbeam_channel::unbounded;
+
+    use crate::{DropMode, ThreadPool, samples::*, thread_request_response::*};
 
     #[test]
     fn one_thread_drop_clean_shutdown_as_expected() {
@@ -20,4 +82,23 @@ mod tests {
 
         drop(target);
     }
+
+    #[test]
+    fn detached_pool_threads_process_queued_messages_after_drop() {
+        let target = ThreadPool::<Randoms>::new(2).with_drop_mode(DropMode::Detach);
+        target
+            .send_and_receive((0..2u64).map(RandomsAddRequest))
+            .unwrap()
+            .for_each(|response| assert!(response.result().is_ok()));
+
+        let (send_back_to, receive_from_thread) = unbounded::<ThreadRequestResponse<Randoms>>();
+        target
+            .send(send_back_to, (0..2u64).map(SumRequest), None, None, None)
+            .unwrap();
+        drop(target);
+
+        // the requests queued before the drop are still processed
+        let responses: Vec<SumResponse> = receive_from_thread.iter().map(Into::into).collect();
+        assert_eq!(2, responses.len());
+    }
 }
@@ -1,7 +1,94 @@
-use crate::ID_BEING_PROCESSED;
+use std::{any::Any, marker::PhantomData};
+
+use crate::{ID_BEING_PROCESSED, KEY_BEING_PROCESSED, pool_key::PoolKey};
 
 /// This function can be used externally to access the id of the pool item that is currently
 /// being processed
+///
+/// Returns `None` for pool items whose key is not a `u64`; use [`key_being_processed`] instead.
 pub fn id_being_processed() -> Option<u64> {
     ID_BEING_PROCESSED.with(|id_being_processed| *id_being_processed.borrow())
 }
+
+/// This function can be used externally to access the key of the pool item that is currently
+/// being processed
+///
+/// Returns `None` if no pool item is being processed or if the pool item's key is not a `K`.
+pub fn key_being_processed<K>() -> Option<K>
+where
+    K: PoolKey,
+{
+    KEY_BEING_PROCESSED.with(|key_being_processed| {
+        // SAFETY: the key is only recorded while the `BeingProcessed` guard that borrows it is alive;
+        // the guard removes it again when it is dropped
+        let key = unsafe { &*key_being_processed.get()? };
+        key.downcast_ref::<K>().cloned()
+    })
+}
+
+/// Records the key of the pool item (or the id of the thread) that is about to be processed,
+/// until the returned guard is dropped
+///
+/// Only a reference to the key is recorded, so processing a message does not allocate; the key is
+/// cloned when [`key_being_processed`] asks for it. The guard must be dropped (not leaked) before
+/// the key is.
+pub(crate) fn set_being_processed<K>(key: Option<&K>, thread_id: Option<u64>) -> BeingProcessed<'_>
+where
+    K: PoolKey,
+{
+    let id = key
+        .and_then(|key| (key as &dyn Any).downcast_ref::<u64>().copied())
+        .or(thread_id);
+    BeingProcessed {
+        previous_id: ID_BEING_PROCESSED.replace(id),
+        previous_key: KEY_BEING_PROCESSED.replace(key.map(|key| key as &dyn Any as *const dyn Any)),
+        _key: PhantomData,
+    }
+}
+
+/// Restores the previously recorded key (if any) when dropped, so that a message processed within
+/// the processing of another (for example by an [`InlinePool`](crate::InlinePool)) does not clear
+/// the outer message's key
+pub(crate) struct BeingProcessed<'a> {
+    previous_id: Option<u64>,
+    previous_key: Option<*const dyn Any>,
+    _key: PhantomData<&'a dyn Any>,
+}
+
+impl Drop for BeingProcessed<'_> {
+    fn drop(&mut self) {
+        ID_BEING_PROCESSED.replace(self.previous_id);
+        KEY_BEING_PROCESSED.set(self.previous_key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{id_being_processed, key_being_processed, set_being_processed};
+
+    #[test]
+    fn key_is_recorded_until_the_guard_is_dropped() {
+        let key = "docs/readme.md".to_string();
+        {
+            let _being_processed = set_being_processed(Some(&key), None);
+            assert_eq!(Some(key.clone()), key_being_processed::<String>());
+            assert_eq!(None, key_being_processed::<u64>());
+            assert_eq!(None, id_being_processed());
+        }
+        assert_eq!(None, key_being_processed::<String>());
+    }
+
+    #[test]
+    fn nested_keys_restore_the_outer_key() {
+        let outer = 1u64;
+        let inner = 2u64;
+        let _outer = set_being_processed(Some(&outer), None);
+        {
+            let _inner = set_being_processed(Some(&inner), None);
+            assert_eq!(Some(2), key_being_processed::<u64>());
+            assert_eq!(Some(2), id_being_processed());
+        }
+        assert_eq!(Some(1), key_being_processed::<u64>());
+        assert_eq!(Some(1), id_being_processed());
+    }
+}
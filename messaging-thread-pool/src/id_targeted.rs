@@ -1,9 +1,12 @@
 use std::fmt::Debug;
 
+use crate::pool_key::PoolKey;
+
 /// A trait for types that have an ID used for routing within the thread pool.
 ///
 /// This trait is fundamental to how `messaging_thread_pool` works. The ID is used to:
-/// - Route requests to the correct thread (`id % thread_count`)
+/// - Route requests to the correct thread (`id % thread_count`), unless a separate
+///   [`routing_key`](IdTargeted::routing_key) is provided
 /// - Identify which pool item should process a message
 /// - Associate responses with their original requests
 ///
@@ -41,6 +44,11 @@ use std::fmt::Debug;
 /// }
 /// ```
 ///
+/// # Key Type
+///
+/// IDs are `u64` by default. Pool items with another [`PoolItem::Key`](crate::PoolItem::Key)
+/// (such as a `String`) use `IdTargeted<Key>` for their requests instead.
+///
 /// # Note on Generated Types
 ///
 /// When using the `#[pool_item]` macro, `IdTargeted` is automatically implemented
@@ -48,9 +56,25 @@ use std::fmt::Debug;
 /// manually for:
 /// - Your pool item struct
 /// - Custom initialization request types (when using `Init = "..."`)
-pub trait IdTargeted: Debug {
+pub trait IdTargeted<K = u64>: Debug
+where
+    K: PoolKey,
+{
     /// Returns the ID used for routing this type within the thread pool.
-    fn id(&self) -> u64;
+    fn id(&self) -> K;
+
+    /// Returns the key used to choose the thread that a request is sent to.
+    ///
+    /// Defaults to the [`routing_hash`](PoolKey::routing_hash) of the [`id`](IdTargeted::id), which
+    /// for `u64` ids is the id itself. Pool items that must live on the same thread (for example
+    /// a user and their sessions) can be co-located by sending all of their requests with a shared
+    /// routing key. The pool item is still identified by its `id` within that thread, so every request
+    /// for a given pool item, including the one that creates it, must use the same routing key.
+    ///
+    /// See [`Routed`](crate::Routed) for attaching a routing key to an existing request.
+    fn routing_key(&self) -> u64 {
+        self.id().routing_hash()
+    }
 }
 
 impl IdTargeted for u64 {
@@ -77,8 +77,9 @@
 //! ### Pool Items
 //!
 //! A **pool item** is any struct that implements the [`PoolItem`] trait. Each pool item:
-//! - Has a unique `u64` ID within the pool
-//! - Lives on a single thread (determined by `id % thread_count`)
+//! - Has a unique ID within the pool; a `u64` by default, or any [`PoolKey`] chosen with
+//!   `#[pool_item(Key = "String")]`
+//! - Lives on a single thread (determined by `id % thread_count`, or by the hash of a non-integer key)
 //! - Receives messages sequentially via its `process_message` method
 //!
 //! The `#[pool_item]` macro generates the [`PoolItem`] implementation for you.
@@ -88,6 +89,7 @@
 //! Communication with pool items happens through **request/response messages**:
 //! - Each method marked with `#[messaging(RequestType, ResponseType)]` becomes a message endpoint
 //! - The macro generates the request struct (with ID + method parameters) and response struct
+//!   (`#[messaging(Req, Resp, named_fields)]` names the request's fields after the parameters)
 //! - Messages are routed to the correct thread based on the target ID
 //!
 //! ### Thread Affinity
@@ -98,6 +100,12 @@
 //! - Consistent ordering of message processing
 //! - Warm CPU caches for frequently accessed items
 //!
+//! Related pool items can be placed on the same thread by routing them with a shared
+//! [`routing_key`](IdTargeted::routing_key) instead of their id; see [`Routed`] and
+//! `#[messaging(Req, Resp, routing_key = param)]`.
+//! A method marked `#[messaging(Req, Resp, multi)]` can then act on several co-located pool items in
+//! one step; the request is rejected with a [`MultiItemError`] if they are not all on the same thread.
+//!
 //! ## Using Non-Send/Sync Types
 //!
 //! The main advantage of this library is supporting thread-bound types. Here's an example
@@ -163,6 +171,74 @@
 //!     .collect();
 //! ```
 //!
+//! Each call to `send_and_receive` takes requests of one type. To mix request types in one round trip
+//! send the pool item's API with [`ThreadPool::send_and_receive_api`]; `#[pool_item]` generates an
+//! `{Item}Response` enum for the responses, with a variant per response type:
+//!
+//! ```rust
+//! # use messaging_thread_pool::{ThreadPool, samples::*};
+//! # let pool = ThreadPool::<Randoms>::new(4);
+//! # pool.send_and_receive_once(RandomsAddRequest(1)).expect("pool available");
+//! let requests: [RandomsApi; 2] = [SumRequest(1).into(), MeanRequest(1).into()];
+//! for response in pool.send_and_receive_api(requests.into_iter()).expect("pool available") {
+//!     match response {
+//!         RandomsResponse::SumResponse(sum) => println!("sum {}", sum.sum()),
+//!         RandomsResponse::MeanResponse(mean) => println!("mean {}", mean.mean()),
+//!         response => panic!("unexpected response {response:?}"),
+//!     }
+//! }
+//! ```
+//!
+//! ## Typed Clients
+//!
+//! `#[pool_item]` also generates a client for each pool item, named after it (`CounterClient` for
+//! `Counter`), that wraps any [`SenderAndReceiver`] and has a method for each `#[messaging]` method.
+//! Each method takes the pool item's id and the method's parameters and returns its result, and each
+//! has a `_batch` variant that sends many requests at once:
+//!
+//! ```rust
+//! use messaging_thread_pool::{ThreadPool, pool_item};
+//!
+//! #[derive(Debug)]
+//! pub struct Counter {
+//!     value: i64,
+//! }
+//!
+//! #[pool_item]
+//! impl Counter {
+//!     pub fn new(_id: u64) -> Self {
+//!         Self { value: 0 }
+//!     }
+//!
+//!     #[messaging(IncrementRequest, IncrementResponse)]
+//!     pub fn increment(&mut self, amount: i64) -> i64 {
+//!         self.value += amount;
+//!         self.value
+//!     }
+//! }
+//!
+//! let pool = ThreadPool::<Counter>::new(2);
+//! pool.send_and_receive((1..3u64).map(CounterInit))
+//!     .expect("pool available")
+//!     .for_each(drop);
+//!
+//! let client = CounterClient::new(&pool);
+//! assert_eq!(10, client.increment(1, 10).expect("pool available"));
+//!
+//! let responses = client
+//!     .increment_batch([(1, 5), (2, 7)])
+//!     .expect("pool available");
+//! assert_eq!(2, responses.len());
+//! ```
+//!
+//! ## Protocols
+//!
+//! A [`Protocol`] is a set of requests that more than one type of pool item can process, such as a
+//! health check. `#[pool_protocol]` on a trait generates the protocol's requests and responses once,
+//! generic over the pool item's key, and each pool item that implements the trait and lists it with
+//! `#[pool_item(Implements = Trait)]` accepts them. Generic code targets any pool item `P` where
+//! `P: Implements<TraitProtocol>`; see [`Protocol`] for an example.
+//!
 //! ## Testing with Mocks
 //!
 //! The [`SenderAndReceiver`] trait allows mocking the thread pool in tests:
@@ -193,6 +269,14 @@
 //! See [`samples`] for more comprehensive examples, and [`SenderAndReceiverMock`] for
 //! mock configuration options.
 //!
+//! When a test needs the real pool item logic rather than canned responses, use an
+//! [`InlinePool`]. It implements [`SenderAndReceiver`] but processes every request
+//! synchronously on the calling thread, so no threads are spawned.
+//!
+//! To reproduce ordering-dependent bugs between nested pools, a
+//! [`SimulatedPool`](simulation::SimulatedPool) runs every thread of a pool on the calling thread
+//! and picks the interleaving from a seed; see [`simulation`].
+//!
 //! ## The `#[pool_item]` Macro
 //!
 //! The macro accepts optional parameters:
@@ -210,6 +294,108 @@
 //!
 //! For details, see the macro documentation in [`macro@pool_item`].
 //!
+//! ## Priority Lanes
+//!
+//! Each thread has a high, normal and low [`Priority`] lane and always drains its higher lanes first.
+//! A method can be given a default lane with `#[messaging(Req, Resp, priority = high)]`, and any call can
+//! override the lane with [`ThreadPool::send_and_receive_with_priority`].
+//! Requests to the same pool item are only processed in order if they are sent on the same lane.
+//!
+//! ## Cancellation
+//!
+//! Every batch of requests is tied to a [`CancellationToken`]. Dropping the iterator returned by
+//! [`ThreadPool::send_and_receive`] cancels the batch, and the threads discard any of its requests that
+//! have not yet been started. Use [`ThreadPool::send_and_receive_with_token`] to cancel a batch explicitly.
+//!
+//! ## Streaming
+//!
+//! A method marked `#[messaging(Req, Resp, stream)]` sends back one response per item it produces,
+//! followed by an end-of-stream marker (see [`StreamChunk`]). [`ThreadPool::send_and_receive_stream`]
+//! returns an iterator over the items that yields them as they arrive and ends at the marker.
+//!
+//! ## Batching
+//!
+//! A pool item can process consecutive queued messages for the same ID together by overriding
+//! [`PoolItem::process_batch`] and [`PoolItem::max_batch_size`]
+//! (`#[pool_item(Batch = "method", MaxBatchSize = 32)]`), for example to flush to a database once per
+//! batch rather than once per message.
+//!
+//! ## Fair Scheduling
+//!
+//! By default each thread processes its messages in the order they arrive, so one busy pool item can
+//! keep the others on its thread waiting. A pool item whose [`PoolItem::scheduling`] is
+//! [`Scheduling::Fair`] (`#[pool_item(FairBudget = 8)]`) is instead given a mailbox per pool item, and
+//! the thread takes turns between the mailboxes.
+//!
+//! ## Watchdog
+//!
+//! [`ThreadPool::new_with_watchdog`] starts a [`Watchdog`] thread alongside the pool. When a thread
+//! spends longer than the watchdog's threshold on one message it logs a structured warning with the
+//! pool item, the message type and the thread's queue depth, and calls an optional callback with the
+//! same [`SlowMessage`] details.
+//!
+//! ## Deadlock Detection
+//!
+//! A pool item may call [`ThreadPool::send_and_receive`] on another pool (see [`samples::RandomsBatch`]),
+//! but a pool thread can only process one message at a time. The library keeps track of which pool
+//! threads are waiting on which, and a call from a pool item that targets its own thread, or a thread
+//! that is (directly or indirectly) waiting on it, fails with [`PoolError::Deadlock`] instead of hanging.
+//!
+//! ## Thread Resources
+//!
+//! Pool items on the same thread can share resources that are not `Send`, such as an `Rc` interner,
+//! a cache or a database connection, without locks. [`with_thread_resource`] borrows the current
+//! pool thread's resource of a given type, creating it on first use; every pool thread has its own
+//! resources, which are dropped with the thread. With the `#[pool_item]` macro a `#[messaging]` method
+//! can simply take a `#[thread_resource]` parameter:
+//!
+//! ```rust
+//! use messaging_thread_pool::{ThreadPool, pool_item};
+//!
+//! #[derive(Debug)]
+//! pub struct Page {
+//!     id: u64,
+//! }
+//!
+//! /// The number of views of every page on a thread
+//! #[derive(Debug, Default)]
+//! pub struct ThreadViews(u64);
+//!
+//! #[pool_item]
+//! impl Page {
+//!     pub fn new(id: u64) -> Self {
+//!         Self { id }
+//!     }
+//!
+//!     #[messaging(ViewRequest, ViewResponse)]
+//!     pub fn view(&self, #[thread_resource] views: &mut ThreadViews) -> u64 {
+//!         views.0 += 1;
+//!         views.0
+//!     }
+//! }
+//!
+//! let pool = ThreadPool::<Page>::new(1);
+//! pool.send_and_receive((0..2u64).map(PageInit)).unwrap().for_each(drop);
+//! assert_eq!(1, pool.send_and_receive_once(ViewRequest(0)).unwrap().result);
+//! assert_eq!(2, pool.send_and_receive_once(ViewRequest(1)).unwrap().result);
+//! ```
+//!
+//! ## Lifecycle Hooks
+//!
+//! [`PoolItem::on_added`] is called once a new pool item has been added to its thread and
+//! [`PoolItem::on_removed`] before it is removed, with a [`RemovalReason`]. A panic while a pool item
+//! processes a message is passed to [`PoolItem::on_message_error`] as a [`MessageError`]; by default
+//! the panic continues and ends the thread, but a pool item can choose to carry on instead.
+//!
+//! Methods that fail can return a `Result`. The error is sent back in the response and, for pool
+//! items that report it through [`PoolItem::response_error`] (as the `#[pool_item]` macro does),
+//! passed to [`PoolItem::pool_item_error_response`] first. A pool item whose `new` fails answers the
+//! add request with an [`AddResponse`] holding the [`NewPoolItemError`], which can keep the typed error.
+//!
+//! Each thread's state is created by [`PoolItem::thread_start`] and handed to [`PoolItem::thread_stop`]
+//! when the thread stops; any [`ThreadStopData`] it returns is forwarded in the thread's
+//! [`ThreadShutdownResponse`].
+//!
 //! ## Legacy API
 //!
 //! The [`api_specification!`] macro is the older way to define pool items. New code should
@@ -228,7 +414,11 @@
 //! - [`ThreadPool`] - The main entry point for creating and managing pools
 //! - [`PoolItem`] - Trait implemented by types managed in the pool
 //! - [`IdTargeted`] - Trait for types that have an ID for routing
+//! - [`PoolKey`] - Trait for types that can be used as pool item IDs
+//! - [`Protocol`] / [`Implements`] - Requests shared by several types of pool item
 //! - [`SenderAndReceiver`] - Trait for abstracting pool communication (enables mocking)
+//! - [`InlinePool`] - Runs real pool items synchronously on the calling thread (for tests)
+//! - [`simulation`] - Deterministic, seeded replay of pool thread interleavings
 //! - [`samples`] - Example implementations to learn from
 //! - [`id_provider`] - Utilities for generating unique IDs
 
@@ -244,37 +434,64 @@ pub mod id_being_processed;
 pub mod id_provider;
 pub mod samples;
 pub mod sender_couplet;
+pub mod simulation;
 
-pub use messaging_thread_pool_macros::pool_item;
+pub use messaging_thread_pool_macros::{pool_item, pool_protocol};
 
+mod cancellation_token;
+mod deadlock;
 mod drop;
 mod id_based_blocking;
 mod id_targeted;
 mod new;
+mod pool_error;
 pub mod pool_item;
+mod pool_key;
 mod pool_thread;
+mod priority;
+mod protocol;
 mod receive;
 pub mod request_response;
 mod request_with_response;
+mod routed;
+mod scheduling;
 mod send;
 mod send_and_receive;
 mod sender_and_receiver;
 mod shutdown;
+mod shutdown_graceful;
+mod stream_response;
 mod thread_endpoint;
 pub mod thread_request_response;
+mod thread_resources;
+mod watchdog;
 
+pub use cancellation_token::CancellationToken;
+pub use deadlock::DeadlockError;
+pub use drop::DropMode;
 pub use id_based_blocking::*;
 pub use id_being_processed::*;
 pub use id_targeted::IdTargeted;
+pub use pool_error::PoolError;
 pub use pool_item::*;
+pub use pool_key::PoolKey;
+pub use priority::Priority;
+pub use protocol::{Implements, Protocol};
 pub use request_response::RequestResponse;
 pub use request_with_response::RequestWithResponse;
+pub use routed::Routed;
+pub use scheduling::Scheduling;
 pub use sender_and_receiver::*;
 pub use sender_couplet::*;
+pub use shutdown_graceful::ShutdownReport;
+pub use stream_response::{StreamChunk, StreamResponse, StreamSink};
 pub use thread_request_response::*;
+pub use thread_resources::{with_thread_resource, with_thread_resource_or_insert_with};
+pub use watchdog::{SlowMessage, Watchdog};
 
 thread_local! {
     pub static ID_BEING_PROCESSED: RefCell<Option<u64>> = const { RefCell::new(None) };
+    static KEY_BEING_PROCESSED: std::cell::Cell<Option<*const dyn std::any::Any>> = const { std::cell::Cell::new(None) };
 }
 
 /// A pool of threads for managing stateful [`PoolItem`] instances.
@@ -374,6 +591,21 @@ thread_local! {
 /// // drop(pool);
 /// ```
 ///
+/// [`shutdown_graceful`](Self::shutdown_graceful) stops the pool accepting requests and gives its
+/// threads a deadline to process what is already queued, returning a [`ShutdownReport`]:
+///
+/// ```rust
+/// use std::time::Duration;
+/// use messaging_thread_pool::{ThreadPool, samples::Randoms};
+///
+/// let pool = ThreadPool::<Randoms>::new(4);
+/// let report = pool.shutdown_graceful(Duration::from_secs(5));
+/// assert!(report.missed_deadline.is_empty());
+/// ```
+///
+/// By default dropping the pool blocks until its threads have ended; use
+/// [`with_drop_mode`](Self::with_drop_mode) with [`DropMode::Detach`] to return immediately instead.
+///
 /// # Thread Distribution
 ///
 /// Items are assigned to threads using `id % thread_count`. All messages for the same
@@ -395,6 +627,14 @@ where
     P: PoolItem,
 {
     thread_endpoints: RwLock<Vec<ThreadEndpoint<P>>>,
+    /// The watchdog observing the pool threads, if one was requested; it is held so that it stops
+    /// when the pool is dropped
+    _watchdog: Option<watchdog::WatchdogThread>,
+    /// Cancelled when a graceful shutdown misses its deadline so that the threads discard the
+    /// messages still queued for pool items
+    drain_deadline: CancellationToken,
+    /// What happens to the threads when the pool is dropped
+    drop_mode: DropMode,
 }
 
 impl<P> ThreadPool<P>
@@ -1,11 +1,21 @@
-use std::{sync::RwLock, thread::Builder};
+use std::{
+    sync::{Arc, RwLock, atomic::AtomicUsize},
+    thread::Builder,
+};
 
 use crossbeam_channel::unbounded;
 use tracing::{Level, event};
 
 use crate::{
-    ThreadPool, pool_item::PoolItem, pool_thread::PoolThread, sender_couplet::SenderCouplet,
+    ThreadPool,
+    cancellation_token::CancellationToken,
+    drop::DropMode,
+    pool_item::PoolItem,
+    pool_thread::PoolThread,
+    priority::Priority,
+    sender_couplet::SenderCouplet,
     thread_endpoint::ThreadEndpoint,
+    watchdog::{ThreadActivity, Watchdog, WatchdogThread, WatchedThread},
 };
 
 impl<P> ThreadPool<P>
@@ -18,19 +28,54 @@ where
     ///
     /// Internally it creates a collection of threads.
     /// It has the ability to communicate with the threads via a vec of channels
-    /// (there is one channel for each spawned thread)
+    /// (there is one channel for each priority lane of each spawned thread)
     ///
     /// The number of threads is determined by the passed in thread_pool_size
     pub fn new(thread_pool_size: u64) -> Self {
+        Self::build(thread_pool_size, None)
+    }
+
+    /// This function creates a new [`ThreadPool`] whose threads are observed by a [`Watchdog`]
+    ///
+    /// The watchdog reports any message that a thread spends longer than the watchdog's threshold
+    /// processing; see [`Watchdog`] for details.
+    pub fn new_with_watchdog(thread_pool_size: u64, watchdog: Watchdog<P::Key>) -> Self {
+        Self::build(thread_pool_size, Some(watchdog))
+    }
+
+    fn build(thread_pool_size: u64, watchdog: Option<Watchdog<P::Key>>) -> Self {
         assert!(
             thread_pool_size > 0,
             "thread pool must have at least one thread"
         );
 
         let mut building = Vec::<ThreadEndpoint<P>>::new();
+        let mut watched = Vec::new();
+        let drain_deadline = CancellationToken::new();
 
         for i in 0..thread_pool_size {
-            let (send_to_thread, receive_from_pool) = unbounded::<SenderCouplet<P>>();
+            let (send_to_thread, receive_from_pool): (Vec<_>, Vec<_>) = (0..Priority::COUNT)
+                .map(|_| unbounded::<SenderCouplet<P>>())
+                .unzip();
+            let send_to_thread: [_; Priority::COUNT] =
+                send_to_thread.try_into().expect("one sender per lane");
+            let receive_from_pool: [_; Priority::COUNT] =
+                receive_from_pool.try_into().expect("one receiver per lane");
+
+            // the watchdog shares the thread's "currently processing" state and measures its lanes
+            let activity = watchdog.as_ref().map(|_| {
+                let activity = Arc::new(ThreadActivity::new());
+                watched.push(WatchedThread {
+                    thread_id: i,
+                    activity: Arc::clone(&activity),
+                    lanes: send_to_thread.clone(),
+                });
+                activity
+            });
+
+            let thread_drain_deadline = drain_deadline.clone();
+            let queued_pool_item_messages = Arc::new(AtomicUsize::new(0));
+            let thread_queued_pool_item_messages = Arc::clone(&queued_pool_item_messages);
 
             event!(Level::INFO, "Creating thread {}-{}", P::name(), i);
 
@@ -41,7 +86,12 @@ where
                     // NOTE: this will be over-ridden if the PoolItem sets the tracing
 
                     // start a new thread with id i
-                    let mut pool_thread = PoolThread::<P>::new(i, receive_from_pool);
+                    let mut pool_thread = PoolThread::<P>::new_with_lanes(i, receive_from_pool);
+                    if let Some(activity) = activity {
+                        pool_thread.set_activity(activity);
+                    }
+                    pool_thread.set_drain_deadline(thread_drain_deadline);
+                    pool_thread.set_queued_pool_item_messages(thread_queued_pool_item_messages);
 
                     event!(Level::INFO, "starting message loop");
 
@@ -53,11 +103,18 @@ where
                 })
                 .expect("thread to spawn");
 
-            building.push(ThreadEndpoint::new(send_to_thread, join_handle));
+            building.push(ThreadEndpoint::new(
+                send_to_thread,
+                join_handle,
+                queued_pool_item_messages,
+            ));
         }
 
         ThreadPool {
             thread_endpoints: RwLock::new(building),
+            _watchdog: watchdog.map(|watchdog| WatchdogThread::spawn(P::name(), watchdog, watched)),
+            drain_deadline,
+            drop_mode: DropMode::default(),
         }
     }
 }
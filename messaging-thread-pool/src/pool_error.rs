@@ -0,0 +1,59 @@
+use std::fmt::Display;
+
+use crossbeam_channel::SendError;
+
+use crate::{deadlock::DeadlockError, pool_item::PoolItem, sender_couplet::SenderCouplet};
+
+/// The reason a request could not be sent to a pool
+#[derive(Debug)]
+pub enum PoolError<P>
+where
+    P: PoolItem,
+{
+    /// The pool's threads have ended (usually because the pool has been shut down); holds the
+    /// request that could not be sent
+    Disconnected(SendError<SenderCouplet<P>>),
+    /// The request was sent from a pool thread and waiting for its response would deadlock
+    Deadlock(DeadlockError),
+    /// The request was sent but its response channel closed without a response; the targeted pool
+    /// item panicked processing it and [`PoolItem::on_message_error`] handled the panic (or the
+    /// pool thread ended)
+    NoResponse(P::Key),
+}
+
+impl<P> From<SendError<SenderCouplet<P>>> for PoolError<P>
+where
+    P: PoolItem,
+{
+    fn from(error: SendError<SenderCouplet<P>>) -> Self {
+        PoolError::Disconnected(error)
+    }
+}
+
+impl<P> From<DeadlockError> for PoolError<P>
+where
+    P: PoolItem,
+{
+    fn from(error: DeadlockError) -> Self {
+        PoolError::Deadlock(error)
+    }
+}
+
+impl<P> Display for PoolError<P>
+where
+    P: PoolItem,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolError::Disconnected(_) => {
+                write!(f, "the threads of the {} pool have ended", P::name())
+            }
+            PoolError::Deadlock(error) => write!(f, "{error}"),
+            PoolError::NoResponse(id) => {
+                write!(f, "no response was received for {} id {id:?}", P::name())
+            }
+        }
+    }
+}
+
+impl<P> std::error::Error for PoolError<P> where P: PoolItem {}
@@ -0,0 +1,161 @@
+use std::{
+    any::Any,
+    fmt::{Debug, Display},
+    panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+};
+
+use super::PoolItem;
+use crate::thread_request_response::ThreadRequestResponse;
+
+/// The reason that a pool item is being removed from its pool thread; passed to
+/// [`PoolItem::on_removed`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RemovalReason {
+    /// The pool item was removed by a [`RemovePoolItemRequest`](crate::RemovePoolItemRequest)
+    Removed,
+    /// The pool item's thread is shutting down
+    Shutdown,
+}
+
+/// A panic raised while a pool item was processing a message; passed to
+/// [`PoolItem::on_message_error`]
+pub struct MessageError<K> {
+    pool_item_id: K,
+    message_type: &'static str,
+    payload: Box<dyn Any + Send>,
+}
+
+impl<K> MessageError<K> {
+    pub(crate) fn new(
+        pool_item_id: K,
+        message_type: &'static str,
+        payload: Box<dyn Any + Send>,
+    ) -> Self {
+        Self {
+            pool_item_id,
+            message_type,
+            payload,
+        }
+    }
+
+    /// The key of the pool item that panicked
+    pub fn pool_item_id(&self) -> &K {
+        &self.pool_item_id
+    }
+
+    /// The type of the message being processed (see [`PoolItem::message_type`]); for a batch this
+    /// is the type of its first message
+    pub fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    /// The panic message, if the panic was raised with a string
+    pub fn message(&self) -> Option<&str> {
+        self.payload
+            .downcast_ref::<&'static str>()
+            .copied()
+            .or_else(|| self.payload.downcast_ref::<String>().map(String::as_str))
+    }
+
+    /// Takes the payload that the panic was raised with
+    pub fn into_payload(self) -> Box<dyn Any + Send> {
+        self.payload
+    }
+
+    /// Continues the panic, ending the pool thread
+    pub fn resume_unwind(self) -> ! {
+        resume_unwind(self.payload)
+    }
+}
+
+impl<K> Debug for MessageError<K>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MessageError")
+            .field("pool_item_id", &self.pool_item_id)
+            .field("message_type", &self.message_type)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+impl<K> Display for MessageError<K>
+where
+    K: Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pool item {:?} panicked processing {}: {}",
+            self.pool_item_id,
+            self.message_type,
+            self.message().unwrap_or("<non-string panic payload>")
+        )
+    }
+}
+
+/// Calls `process` for the pool item, passing any panic that it raises to the pool item's
+/// [`on_message_error`](PoolItem::on_message_error)
+///
+/// Returns `None` if `process` panicked and `on_message_error` handled the panic.
+pub(crate) fn catch_message_error<P, R>(
+    pool_item: &mut P,
+    pool_item_id: &P::Key,
+    message_type: &'static str,
+    process: impl FnOnce(&mut P) -> R,
+) -> Option<R>
+where
+    P: PoolItem,
+{
+    // the pool item is handed back to on_message_error, which decides whether it is still usable
+    match catch_unwind(AssertUnwindSafe(|| process(pool_item))) {
+        Ok(result) => Some(result),
+        Err(payload) => {
+            pool_item.on_message_error(MessageError::new(
+                pool_item_id.clone(),
+                message_type,
+                payload,
+            ));
+            None
+        }
+    }
+}
+
+/// Passes the error held by a pool item's response (if any) to [`PoolItem::pool_item_error_response`]
+pub(crate) fn report_error_response<P>(
+    response: &ThreadRequestResponse<P>,
+    pool_item_id: &P::Key,
+    message_type: &'static str,
+    thread_start_info: Option<&mut P::ThreadStartInfo>,
+) where
+    P: PoolItem,
+{
+    if let Some(error) = P::response_error(response) {
+        P::pool_item_error_response(pool_item_id, message_type, error, thread_start_info);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageError;
+
+    #[test]
+    fn message_error_exposes_string_panic_messages() {
+        let target = MessageError::new(1u64, "PanicRequest", Box::new("static"));
+        assert_eq!(Some("static"), target.message());
+
+        let target = MessageError::new(1u64, "PanicRequest", Box::new(format!("formatted {}", 2)));
+        assert_eq!(Some("formatted 2"), target.message());
+        assert_eq!(
+            "pool item 1 panicked processing PanicRequest: formatted 2",
+            target.to_string()
+        );
+
+        let target = MessageError::new(1u64, "PanicRequest", Box::new(3));
+        assert_eq!(None, target.message());
+        assert_eq!(Some(&3), target.into_payload().downcast_ref::<i32>());
+    }
+}
@@ -1,10 +1,19 @@
+pub mod lifecycle;
+pub mod multi_item_error;
 pub mod new_pool_item_error;
+pub mod shutdown_context;
 
-pub use self::new_pool_item_error::NewPoolItemError;
+pub use self::{
+    lifecycle::{MessageError, RemovalReason},
+    multi_item_error::MultiItemError,
+    new_pool_item_error::NewPoolItemError,
+    shutdown_context::{ChildPool, ShutdownContext},
+};
 use crate::{
-    id_targeted::IdTargeted, request_with_response::RequestWithResponse, thread_request_response::*,
+    id_targeted::IdTargeted, pool_key::PoolKey, request_with_response::RequestWithResponse,
+    scheduling::Scheduling, thread_request_response::*,
 };
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::ControlFlow};
 use tracing::{Level, event};
 
 /// The core trait for types managed by a [`ThreadPool`](crate::ThreadPool).
@@ -49,15 +58,20 @@ use tracing::{Level, event};
 ///
 /// # Associated Types
 ///
+/// - **`Key`**: The type of the ID that identifies each instance (`u64` unless specified otherwise)
 /// - **`Init`**: The request type used to create new instances (e.g., `CounterInit(u64)`)
 /// - **`Api`**: An enum of all message types the pool item can handle
 /// - **`ThreadStartInfo`**: Optional per-thread state (e.g., for tracing configuration)
 ///
 /// # Lifecycle
 ///
-/// 1. **Creation**: When an `Init` request is received, `new_pool_item` is called
-/// 2. **Processing**: Messages are delivered to `process_message` sequentially
-/// 3. **Shutdown**: `shutdown_pool` is called when the pool is dropped
+/// 1. **Creation**: When an `Init` request is received, `new_pool_item` is called and, once the
+///    pool item has been added to its thread, `on_added`
+/// 2. **Processing**: Messages are delivered to `process_message` sequentially; a panic while
+///    processing one is passed to `on_message_error`
+/// 3. **Removal**: `on_removed` is called before a pool item is removed by a `RemovePoolItemRequest`
+/// 4. **Shutdown**: `on_shutdown` and then `on_removed` are called for every pool item when the
+///    pool is shut down or dropped, followed by `thread_stop` for each thread
 ///
 /// # Thread Affinity
 ///
@@ -72,9 +86,16 @@ use tracing::{Level, event};
 pub trait PoolItem: Debug
 where
     Self: Sized,
-    Self::Init: Send + IdTargeted + RequestWithResponse<Self, Response = AddResponse>,
-    Self::Api: Debug + Send + IdTargeted,
+    Self::Init:
+        Send + IdTargeted<Self::Key> + RequestWithResponse<Self, Response = AddResponse<Self::Key>>,
+    Self::Api: Debug + Send + IdTargeted<Self::Key>,
 {
+    /// The type of the ID that identifies each pool item.
+    ///
+    /// The `#[pool_item]` macro uses `u64` unless `#[pool_item(Key = "...")]` is specified.
+    /// Keys other than integers are routed to a thread by their hash; see [`PoolKey`].
+    type Key: PoolKey;
+
     /// The request type for creating new instances of this pool item.
     ///
     /// When the pool receives an `Init` message, it calls `new_pool_item` to
@@ -105,14 +126,136 @@ where
     /// The `#[pool_item]` macro generates this implementation automatically.
     fn process_message(&mut self, request: Self::Api) -> ThreadRequestResponse<Self>;
 
+    /// Process an incoming message, sending any number of responses back to the caller.
+    ///
+    /// This is what the pool thread actually calls. Each response passed to `send` is returned
+    /// to the caller immediately; `send` returns `Break` once the caller is no longer listening
+    /// (or has cancelled the request), at which point no more responses should be produced.
+    ///
+    /// The default implementation sends the single response returned by `process_message`.
+    /// The `#[pool_item]` macro overrides it when the pool item has streaming methods
+    /// (`#[messaging(Req, Resp, stream)]`).
+    fn process_message_stream(
+        &mut self,
+        request: Self::Api,
+        send: &mut dyn FnMut(ThreadRequestResponse<Self>) -> ControlFlow<()>,
+    ) {
+        let _ = send(self.process_message(request));
+    }
+
+    /// Process several messages for this pool item at once, returning one response per message.
+    ///
+    /// When a pool thread finds more than one consecutive queued message for the same pool item it
+    /// hands them over together (up to [`max_batch_size`](PoolItem::max_batch_size)) so that work
+    /// such as flushing to a database can be done once per batch rather than once per message.
+    /// The responses must be returned in the same order as the messages.
+    ///
+    /// Streaming requests (see [`is_stream`](PoolItem::is_stream)) are never batched.
+    ///
+    /// The default implementation calls `process_message` for each message in turn.
+    fn process_batch(&mut self, requests: Vec<Self::Api>) -> Vec<ThreadRequestResponse<Self>> {
+        requests
+            .into_iter()
+            .map(|request| self.process_message(request))
+            .collect()
+    }
+
+    /// The maximum number of consecutive queued messages for the same pool item that are passed to
+    /// [`process_batch`](PoolItem::process_batch) together.
+    ///
+    /// The default of 1 disables batching.
+    fn max_batch_size() -> usize {
+        1
+    }
+
+    /// Returns true if the request is for a streaming messaging method.
+    ///
+    /// Streaming requests are never passed to [`process_batch`](PoolItem::process_batch); they are
+    /// always processed by [`process_message_stream`](PoolItem::process_message_stream).
+    /// The `#[pool_item]` macro overrides this for pool items with streaming methods.
+    #[allow(unused_variables)]
+    fn is_stream(request: &Self::Api) -> bool {
+        false
+    }
+
+    /// How the pool threads choose which queued message to process next.
+    ///
+    /// The default of [`Scheduling::Fifo`] processes messages in the order they arrive;
+    /// [`Scheduling::Fair`] takes turns between the pool items that have messages waiting so that one
+    /// busy pool item cannot monopolize its thread.
+    fn scheduling() -> Scheduling {
+        Scheduling::Fifo
+    }
+
+    /// Returns the name of the type of a message, for diagnostics such as the pool's [`Watchdog`].
+    ///
+    /// The `#[pool_item]` macro returns the name of the request struct; the default is the name of
+    /// the `Api` type.
+    ///
+    /// [`Watchdog`]: crate::Watchdog
+    #[allow(unused_variables)]
+    fn message_type(request: &Self::Api) -> &'static str {
+        std::any::type_name::<Self::Api>()
+    }
+
     /// Called when a message targets an ID that doesn't exist in the pool.
     ///
     /// The default behavior is to panic. Override this to handle missing IDs gracefully
     /// (e.g., by creating the item on-demand or returning an error response).
     fn id_not_found(request: &Self::Api) -> ThreadRequestResponse<Self> {
         // default behaviour is to panic
-        event!(Level::ERROR, "pool item with id {} not found", request.id());
-        panic!("pool item with id {} not found", request.id());
+        event!(
+            Level::ERROR,
+            "pool item with id {:?} not found",
+            request.id()
+        );
+        panic!("pool item with id {:?} not found", request.id());
+    }
+
+    /// Returns the ids of all of the pool items targeted by a multi item request.
+    ///
+    /// Multi item requests (`#[messaging(Req, Resp, multi)]`) are routed to a single thread and
+    /// act on several pool items at once. All of the targeted pool items must be on that thread.
+    ///
+    /// The `#[pool_item]` macro generates this implementation automatically.
+    fn multi_item_ids(request: &Self::Api) -> Vec<Self::Key> {
+        vec![request.id()]
+    }
+
+    /// Process a message that targets several co-located pool items at once.
+    ///
+    /// `items` holds a mutable reference to each pool item returned by
+    /// [`multi_item_ids`](PoolItem::multi_item_ids), in the same order.
+    ///
+    /// The `#[pool_item]` macro generates this implementation automatically.
+    fn process_multi_item_message(
+        items: &mut [&mut Self],
+        request: Self::Api,
+    ) -> ThreadRequestResponse<Self> {
+        let _ = items;
+        panic!(
+            "pool item {} does not support multi item request {:?}",
+            Self::name(),
+            request
+        );
+    }
+
+    /// Called instead of [`process_multi_item_message`](PoolItem::process_multi_item_message)
+    /// when the targeted pool items are not all present on the thread the request was routed to.
+    ///
+    /// The `#[pool_item]` macro generates this implementation automatically, returning the
+    /// error in the response.
+    fn multi_item_rejected(
+        request: Self::Api,
+        error: MultiItemError<Self::Key>,
+    ) -> ThreadRequestResponse<Self> {
+        event!(
+            Level::ERROR,
+            "multi item request {:?} rejected; {}",
+            request,
+            error
+        );
+        panic!("multi item request {request:?} rejected; {error}");
     }
 
     /// Returns the type name for logging purposes.
@@ -136,10 +279,11 @@ where
     /// ```
     fn new_pool_item(request: Self::Init) -> Result<Self, NewPoolItemError>;
 
-    /// Called for each pool item when the pool is shutting down.
+    /// Returns shutdown responses to include in the thread's [`ThreadShutdownResponse`].
     ///
-    /// Override this to perform cleanup operations. The returned responses are
-    /// collected and can be inspected by the caller.
+    /// This is called by the default [`on_shutdown`](PoolItem::on_shutdown); override that instead
+    /// if the pool item needs access to the thread or holds a shared child pool.
+    /// The returned responses are collected and can be inspected by the caller.
     ///
     /// # Example
     ///
@@ -153,34 +297,149 @@ where
         Vec::<ThreadShutdownResponse>::default()
     }
 
+    /// Called for each pool item when its thread shuts down.
+    ///
+    /// The pool items on a thread are shut down in ascending order of the
+    /// [`routing_hash`](PoolKey::routing_hash) of their keys (ascending id for integer keys) and are
+    /// dropped once all of them have been shut down. The `context` gives access to the thread and
+    /// collects the responses that become the children of the thread's [`ThreadShutdownResponse`].
+    ///
+    /// A child pool that is shared between pool items should be handed to
+    /// [`ShutdownContext::release_child_pool`] rather than shut down directly; it is then shut down
+    /// exactly once, when the last reference to it is released.
+    ///
+    /// The default implementation adds the responses returned by
+    /// [`shutdown_pool`](PoolItem::shutdown_pool).
+    fn on_shutdown(&mut self, context: &mut ShutdownContext<'_, Self>) {
+        context.add_shutdown_responses(self.shutdown_pool());
+    }
+
+    /// Called once the pool item has been added to its pool thread.
+    ///
+    /// A pool item that is rejected because a pool item with the same id already exists is dropped
+    /// without this being called.
+    fn on_added(&mut self) {
+        // do nothing by default
+    }
+
+    /// Called before the pool item is removed from its pool thread and dropped.
+    ///
+    /// When the thread shuts down this is called (with [`RemovalReason::Shutdown`]) after
+    /// [`on_shutdown`](PoolItem::on_shutdown). It is not called when a thread is aborted, as the
+    /// pool items are left in place.
+    #[allow(unused_variables)]
+    fn on_removed(&mut self, reason: RemovalReason) {
+        // do nothing by default
+    }
+
+    /// Called when processing a message (or a batch of messages) for this pool item panics.
+    ///
+    /// The default implementation continues the panic, which ends the pool thread.
+    ///
+    /// If this returns instead, the pool item stays in place and the thread carries on with its next
+    /// message. The message that panicked is not answered; its sender's end of the response channel
+    /// is dropped, so [`send_and_receive_once`](crate::ThreadPool::send_and_receive_once) and
+    /// [`send_and_receive_one`](crate::SenderAndReceiver::send_and_receive_one) return
+    /// [`PoolError::NoResponse`](crate::PoolError::NoResponse) and `send_and_receive` yields one
+    /// response fewer. Panics raised by multi item messages are not passed to this hook.
+    fn on_message_error(&mut self, error: MessageError<Self::Key>) {
+        error.resume_unwind()
+    }
+
     /// Called once when a pool thread starts.
     ///
     /// Returns optional thread-local state that will be passed to
-    /// `pool_item_pre_process` and `pool_item_post_process`.
+    /// `pool_item_pre_process` and `pool_item_post_process`, and finally to `thread_stop`.
     ///
     /// Primary use case: Configuring tracing/logging for the thread.
     fn thread_start() -> Option<Self::ThreadStartInfo> {
         None
     }
 
-    /// Called before processing each message.
+    /// Called once when a pool thread stops, with the state created by
+    /// [`thread_start`](PoolItem::thread_start).
+    ///
+    /// This is called when the thread is shut down (after its pool items have been shut down), when it
+    /// is aborted and when every channel to the thread has been disconnected. It is not called if
+    /// `thread_start` returned `None`.
+    ///
+    /// Any data returned (such as statistics about buffers flushed on stop) is forwarded in the
+    /// thread's [`ThreadShutdownResponse`]; see [`ThreadShutdownResponse::stop_data`]. It is
+    /// discarded when the thread is aborted or disconnected.
+    #[allow(unused_variables)]
+    fn thread_stop(thread_start_info: Self::ThreadStartInfo) -> Option<ThreadStopData> {
+        None
+    }
+
+    /// Called before processing each message that targets a pool item.
+    ///
+    /// It is not called for messages that target the thread itself (such as
+    /// [`ThreadEchoRequest`] or a shutdown), which have no pool item id.
     ///
     /// Use this hook to enable per-item tracing or perform setup.
     #[allow(unused_variables)]
-    fn pool_item_pre_process(pool_item_id: u64, thread_start_info: &mut Self::ThreadStartInfo) {
+    fn pool_item_pre_process(
+        pool_item_id: Self::Key,
+        thread_start_info: &mut Self::ThreadStartInfo,
+    ) {
         // do nothing by default
     }
 
-    /// Called after processing each message.
+    /// Called after processing each message that targets a pool item.
+    ///
+    /// As with [`pool_item_pre_process`](PoolItem::pool_item_pre_process) it is not called for
+    /// messages that target the thread itself.
     ///
     /// Use this hook to disable per-item tracing or perform cleanup.
     #[allow(unused_variables)]
-    fn pool_item_post_process(pool_item_id: u64, thread_start_info: &mut Self::ThreadStartInfo) {
+    fn pool_item_post_process(
+        pool_item_id: Self::Key,
+        thread_start_info: &mut Self::ThreadStartInfo,
+    ) {
         // do nothing by default
     }
 
+    /// Returns the error held by a response, if the response is the result of a method that failed.
+    ///
+    /// Errors returned here are passed to
+    /// [`pool_item_error_response`](PoolItem::pool_item_error_response) before the response is sent.
+    /// The `#[pool_item]` macro implements this for every `#[messaging]` method that returns a
+    /// `Result`, including those of the `#[pool_protocol]` traits that the pool item implements. The
+    /// default implementation returns `None`.
+    #[allow(unused_variables)]
+    fn response_error(response: &ThreadRequestResponse<Self>) -> Option<&dyn Debug> {
+        None
+    }
+
+    /// Called with the error of each response that [`response_error`](PoolItem::response_error)
+    /// reports, before the response is sent.
+    ///
+    /// Use this hook to log, count or trace failures in one place. It is passed the thread's state
+    /// when [`thread_start`](PoolItem::thread_start) created some. The default implementation
+    /// records a debug event.
+    #[allow(unused_variables)]
+    fn pool_item_error_response(
+        pool_item_id: &Self::Key,
+        message_type: &'static str,
+        error: &dyn Debug,
+        thread_start_info: Option<&mut Self::ThreadStartInfo>,
+    ) {
+        event!(
+            Level::DEBUG,
+            "{} request for {:?}, id={:?} failed: {:?}",
+            message_type,
+            Self::name(),
+            pool_item_id,
+            error
+        );
+    }
+
     /// Determines which thread handles a given pool item ID.
     ///
+    /// The value passed in is the request's [`routing_key`](IdTargeted::routing_key), which is the
+    /// pool item ID (or the [`routing_hash`](PoolKey::routing_hash) of a non-`u64` key) unless the
+    /// request specifies otherwise.
+    ///
     /// The default implementation is `id % thread_count`, which distributes IDs
     /// evenly across threads assuming sequential ID assignment.
     ///
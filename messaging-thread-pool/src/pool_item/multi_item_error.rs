@@ -0,0 +1,32 @@
+use std::fmt::{Debug, Display};
+
+/// The reason a multi item request was rejected rather than executed
+///
+/// A multi item request is only executed if every pool item that it targets is found on the
+/// thread that the request was routed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultiItemError<K = u64> {
+    /// The request did not target any pool items
+    NoIds,
+    /// The same pool item was targeted more than once
+    DuplicateId(K),
+    /// The pool item was not found on the thread that the request was routed to; either it does not
+    /// exist or it is not co-located with the other targeted pool items
+    NotCoLocated(K),
+}
+
+impl<K: Debug> Display for MultiItemError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultiItemError::NoIds => write!(f, "multi item request does not target any pool items"),
+            MultiItemError::DuplicateId(id) => {
+                write!(f, "pool item with id {id:?} is targeted more than once")
+            }
+            MultiItemError::NotCoLocated(id) => {
+                write!(f, "pool item with id {id:?} not found on the routed thread")
+            }
+        }
+    }
+}
+
+impl<K: Debug> std::error::Error for MultiItemError<K> {}
@@ -1,6 +1,124 @@
-/// This is a 'better than nothing' error implementation
-/// It allows the initialisation code to return error information as a string
-#[derive(Debug)]
+use std::{
+    any::Any,
+    fmt::{Debug, Display},
+    sync::Arc,
+};
+
+/// The reason that a pool item could not be created; returned by
+/// [`PoolItem::new_pool_item`](super::PoolItem::new_pool_item)
+///
+/// It always has a message. An error created with [`from_error`](NewPoolItemError::from_error)
+/// (as the `#[pool_item]` macro does for a `new` that returns `Result<Self, E>`) also keeps the
+/// typed error, which the [`AddResponse`](crate::AddResponse) carries back to the caller.
+#[derive(Debug, Clone)]
 pub struct NewPoolItemError {
     pub error_message: String,
+    error: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl NewPoolItemError {
+    /// An error with a message only
+    pub fn new(error_message: impl Into<String>) -> Self {
+        Self {
+            error_message: error_message.into(),
+            error: None,
+        }
+    }
+
+    /// An error that keeps the typed error as well as its message
+    pub fn from_error<E>(error: E) -> Self
+    where
+        E: Display + Send + Sync + 'static,
+    {
+        Self {
+            error_message: error.to_string(),
+            error: Some(Arc::new(error)),
+        }
+    }
+
+    /// The error message
+    pub fn message(&self) -> &str {
+        &self.error_message
+    }
+
+    /// The typed error, if the error was created from one of type `E`
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: 'static,
+    {
+        self.error.as_deref().and_then(|error| error.downcast_ref())
+    }
+}
+
+impl From<String> for NewPoolItemError {
+    fn from(error_message: String) -> Self {
+        Self::new(error_message)
+    }
+}
+
+impl From<&str> for NewPoolItemError {
+    fn from(error_message: &str) -> Self {
+        Self::new(error_message)
+    }
+}
+
+/// Errors are equal if they have the same message; the typed errors (which need not be comparable)
+/// are not compared
+impl PartialEq for NewPoolItemError {
+    fn eq(&self, other: &Self) -> bool {
+        self.error_message == other.error_message
+    }
+}
+
+impl Eq for NewPoolItemError {}
+
+impl Display for NewPoolItemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error_message)
+    }
+}
+
+impl std::error::Error for NewPoolItemError {}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Display;
+
+    use super::NewPoolItemError;
+
+    #[derive(Debug, PartialEq)]
+    struct InvalidConfig(u32);
+
+    impl Display for InvalidConfig {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "invalid config {}", self.0)
+        }
+    }
+
+    #[test]
+    fn typed_errors_can_be_retrieved() {
+        let target = NewPoolItemError::from_error(InvalidConfig(3));
+
+        assert_eq!("invalid config 3", target.message());
+        assert_eq!(
+            Some(&InvalidConfig(3)),
+            target.downcast_ref::<InvalidConfig>()
+        );
+        assert_eq!(None, target.downcast_ref::<String>());
+        assert_eq!(target, target.clone());
+    }
+
+    #[test]
+    fn errors_are_equal_by_message() {
+        let target = NewPoolItemError::from("failed");
+
+        assert_eq!(NewPoolItemError::new("failed"), target);
+        assert_eq!(None, target.downcast_ref::<InvalidConfig>());
+        assert_eq!(NewPoolItemError::from_error("failed".to_string()), target);
+        assert_eq!(
+            NewPoolItemError::from_error(InvalidConfig(3)),
+            NewPoolItemError::from_error(InvalidConfig(3))
+        );
+        assert_ne!(NewPoolItemError::new("other"), target);
+    }
 }
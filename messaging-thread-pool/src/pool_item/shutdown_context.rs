@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use crate::{
+    InlinePool, SenderAndReceiverMock, ThreadPool, pool_item::PoolItem,
+    request_with_response::RequestWithResponse, simulation::SimulatedPool,
+    thread_request_response::ThreadShutdownResponse,
+};
+
+/// A pool that the pool items of another pool use, and that can be shut down by them
+///
+/// Pool items hand the child pools they hold to the [`ShutdownContext`] passed to
+/// [`PoolItem::on_shutdown`]; see [`ShutdownContext::release_child_pool`].
+pub trait ChildPool: Send + Sync {
+    /// Shuts the pool down, returning the shutdown responses of its threads
+    fn shutdown_child_pool(&self) -> Vec<ThreadShutdownResponse>;
+}
+
+impl<P> ChildPool for ThreadPool<P>
+where
+    P: PoolItem,
+    Self: Send + Sync,
+{
+    fn shutdown_child_pool(&self) -> Vec<ThreadShutdownResponse> {
+        self.shutdown()
+    }
+}
+
+impl<P> ChildPool for InlinePool<P>
+where
+    P: PoolItem,
+    Self: Send + Sync,
+{
+    fn shutdown_child_pool(&self) -> Vec<ThreadShutdownResponse> {
+        self.shutdown()
+    }
+}
+
+impl<P> ChildPool for SimulatedPool<P>
+where
+    P: PoolItem,
+    Self: Send + Sync,
+{
+    fn shutdown_child_pool(&self) -> Vec<ThreadShutdownResponse> {
+        self.shutdown()
+    }
+}
+
+/// A mock has no threads to shut down
+impl<P, T> ChildPool for SenderAndReceiverMock<P, T>
+where
+    P: PoolItem,
+    T: RequestWithResponse<P>,
+    Self: Send + Sync,
+{
+    fn shutdown_child_pool(&self) -> Vec<ThreadShutdownResponse> {
+        Vec::new()
+    }
+}
+
+/// Shuts down a child pool if the registry held the last reference to it
+type ReleaseChildPool = Box<dyn FnOnce() -> Option<Vec<ThreadShutdownResponse>>>;
+
+/// The child pools released by the pool items of one pool thread while it shuts down
+///
+/// Each child pool is held once, however many pool items released it. Once every pool item has been
+/// dropped the registry drops its references; the child pool is only shut down (here) if the registry
+/// held the last reference, so a child pool shared by several threads is shut down exactly once, by
+/// the last thread to release it. A child pool that is still referenced elsewhere is left running.
+#[derive(Default)]
+pub(crate) struct ChildPoolRegistry {
+    /// The child pools in the order that they were first released, keyed by their address
+    child_pools: Vec<(usize, ReleaseChildPool)>,
+}
+
+impl ChildPoolRegistry {
+    fn release<C>(&mut self, child_pool: Arc<C>)
+    where
+        C: ChildPool + 'static,
+    {
+        let address = Arc::as_ptr(&child_pool) as *const () as usize;
+        if self.child_pools.iter().all(|(held, _)| *held != address) {
+            self.child_pools.push((
+                address,
+                Box::new(move || {
+                    Arc::into_inner(child_pool).map(|child_pool| child_pool.shutdown_child_pool())
+                }),
+            ));
+        }
+    }
+
+    /// Drops the registry's references, shutting down the child pools that nothing else refers to
+    pub(crate) fn shutdown(self) -> Vec<ThreadShutdownResponse> {
+        self.child_pools
+            .into_iter()
+            .filter_map(|(_, release)| release())
+            .flatten()
+            .collect()
+    }
+}
+
+/// The context passed to [`PoolItem::on_shutdown`] when a pool thread shuts down
+pub struct ShutdownContext<'a, P>
+where
+    P: PoolItem,
+{
+    thread_id: u64,
+    thread_start_info: Option<&'a mut P::ThreadStartInfo>,
+    child_pools: &'a mut ChildPoolRegistry,
+    shutdown_responses: &'a mut Vec<ThreadShutdownResponse>,
+}
+
+impl<'a, P> ShutdownContext<'a, P>
+where
+    P: PoolItem,
+{
+    pub(crate) fn new(
+        thread_id: u64,
+        thread_start_info: Option<&'a mut P::ThreadStartInfo>,
+        child_pools: &'a mut ChildPoolRegistry,
+        shutdown_responses: &'a mut Vec<ThreadShutdownResponse>,
+    ) -> Self {
+        Self {
+            thread_id,
+            thread_start_info,
+            child_pools,
+            shutdown_responses,
+        }
+    }
+
+    /// The id of the pool thread that is shutting down
+    pub fn thread_id(&self) -> u64 {
+        self.thread_id
+    }
+
+    /// The thread's state created by [`PoolItem::thread_start`], if any
+    pub fn thread_start_info(&mut self) -> Option<&mut P::ThreadStartInfo> {
+        self.thread_start_info.as_deref_mut()
+    }
+
+    /// Hands over a reference to a child pool used by the pool item
+    ///
+    /// The child pool is shut down once the last reference to it has been released, and its
+    /// shutdown responses become children of this thread's [`ThreadShutdownResponse`]. Releasing
+    /// the same child pool from several pool items has no further effect.
+    pub fn release_child_pool<C>(&mut self, child_pool: Arc<C>)
+    where
+        C: ChildPool + 'static,
+    {
+        self.child_pools.release(child_pool);
+    }
+
+    /// Adds responses to the children of this thread's [`ThreadShutdownResponse`]
+    pub fn add_shutdown_responses(&mut self, responses: Vec<ThreadShutdownResponse>) {
+        self.shutdown_responses.extend(responses);
+    }
+}
+
+impl<P> std::fmt::Debug for ShutdownContext<'_, P>
+where
+    P: PoolItem,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownContext")
+            .field("thread_id", &self.thread_id)
+            .field("child_pools", &self.child_pools.child_pools.len())
+            .field("shutdown_responses", &self.shutdown_responses)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{ThreadPool, samples::*, thread_request_response::*};
+
+    use super::ChildPoolRegistry;
+
+    #[test]
+    fn child_pool_is_shut_down_once_when_the_registry_holds_the_last_reference() {
+        let child_pool = Arc::new(ThreadPool::<Randoms>::new(2));
+        let mut target = ChildPoolRegistry::default();
+        target.release(Arc::clone(&child_pool));
+        target.release(child_pool);
+
+        assert_eq!(
+            vec![
+                ThreadShutdownResponse::new(0, vec![]),
+                ThreadShutdownResponse::new(1, vec![])
+            ],
+            target.shutdown()
+        );
+    }
+
+    #[test]
+    fn child_pool_referenced_elsewhere_is_left_running() {
+        let child_pool = Arc::new(ThreadPool::<Randoms>::new(1));
+        let mut target = ChildPoolRegistry::default();
+        target.release(Arc::clone(&child_pool));
+
+        assert!(target.shutdown().is_empty());
+        assert!(
+            child_pool
+                .send_and_receive_once(RandomsAddRequest(1))
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fmt::Debug,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+/// A type that can be used to identify a pool item within a thread pool.
+///
+/// Pool items are keyed by `u64` unless their [`PoolItem::Key`](crate::PoolItem::Key) says otherwise.
+/// Any type that is `Hash + Eq + Clone + Send` can be used as a key by implementing this trait;
+/// for example strings or UUIDs.
+///
+/// ```rust
+/// use messaging_thread_pool::PoolKey;
+///
+/// #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// struct AccountNumber(String);
+///
+/// impl PoolKey for AccountNumber {}
+/// ```
+///
+/// # Routing
+///
+/// The thread that a pool item lives on is chosen from [`routing_hash`](PoolKey::routing_hash).
+/// For integer keys this is the value of the key itself, so consecutive ids are spread evenly
+/// across the threads. For all other keys it is a hash of the key.
+pub trait PoolKey: Debug + Hash + Eq + Clone + Send + 'static {
+    /// Returns the value that is used to choose the thread for this key
+    ///
+    /// The default implementation hashes the key. The hash only needs to be consistent within
+    /// a single process.
+    fn routing_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+macro_rules! integer_pool_key {
+    ($($t:ty),*) => {
+        $(
+            impl PoolKey for $t {
+                fn routing_hash(&self) -> u64 {
+                    *self as u64
+                }
+            }
+        )*
+    };
+}
+
+integer_pool_key!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl PoolKey for u128 {}
+impl PoolKey for i128 {}
+impl PoolKey for String {}
+impl PoolKey for &'static str {}
+
+#[cfg(test)]
+mod tests {
+    use super::PoolKey;
+
+    #[test]
+    fn integer_keys_route_by_value() {
+        assert_eq!(7, 7u64.routing_hash());
+        assert_eq!(7, 7u32.routing_hash());
+    }
+
+    #[test]
+    fn string_keys_route_by_consistent_hash() {
+        assert_eq!(
+            "account-1".to_string().routing_hash(),
+            "account-1".to_string().routing_hash()
+        );
+        assert_ne!(
+            "account-1".to_string().routing_hash(),
+            "account-2".to_string().routing_hash()
+        );
+    }
+}
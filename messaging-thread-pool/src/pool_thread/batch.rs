@@ -0,0 +1,358 @@
+use std::ops::ControlFlow;
+
+use tracing::{Level, event};
+
+use crate::{
+    id_being_processed::set_being_processed,
+    id_targeted::IdTargeted,
+    pool_item::{
+        PoolItem,
+        lifecycle::{catch_message_error, report_error_response},
+    },
+    sender_couplet::SenderCouplet,
+    thread_request_response::ThreadRequestResponse,
+};
+
+use super::PoolThread;
+
+impl<P> PoolThread<P>
+where
+    P: PoolItem,
+{
+    /// Takes any further queued messages for the same pool item as the given message off of the
+    /// lanes, up to the pool item's [`max_batch_size`](PoolItem::max_batch_size)
+    ///
+    /// Only messages for an existing pool item are batched. The first queued message that cannot
+    /// be part of the batch is deferred; it is put back at the front of its lane, so a higher
+    /// priority message that arrives in the meantime is still processed first.
+    pub(crate) fn gather_batch(&mut self, first: SenderCouplet<P>) -> Vec<SenderCouplet<P>> {
+        let max_batch_size = P::max_batch_size();
+        let Some(id) = Self::batch_id(&first) else {
+            return vec![first];
+        };
+        if max_batch_size <= 1 || !self.pool_item_map.contains_key(&id) {
+            return vec![first];
+        }
+
+        let mut batch = vec![first];
+        while batch.len() < max_batch_size {
+            let Some((lane, next)) = self.try_next_sender_couplet() else {
+                break;
+            };
+            if Self::batch_id(&next).as_ref() == Some(&id) {
+                batch.push(next);
+            } else {
+                self.deferred[lane] = Some(next);
+                break;
+            }
+        }
+        batch
+    }
+
+    /// Processes the messages of a batch gathered by [`gather_batch`](PoolThread::gather_batch)
+    ///
+    /// A batch of one message is processed as normal; larger batches are handed to the pool item's
+    /// [`process_batch`](PoolItem::process_batch).
+    /// While the batch is processed it is recorded as the thread's activity for the pool's watchdog.
+    ///
+    /// A shutdown request arrives on the high priority lane, ahead of messages that were queued
+    /// before it; those messages are processed before the thread is shut down.
+    pub(crate) fn process_sender_couplets(
+        &mut self,
+        mut batch: Vec<SenderCouplet<P>>,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        if matches!(batch[0].request(), ThreadRequestResponse::ThreadShutdown(_)) {
+            self.process_queued(thread_start_info)?;
+        }
+
+        self.count_off(&batch);
+        if let Some(activity) = &self.activity {
+            let request = batch[0].request();
+            activity.started(request.pool_item_id(), request.message_type());
+        }
+
+        let result = if batch.len() == 1 {
+            let sender_couplet = batch.pop().expect("batch has one message");
+            self.process_sender_couplet(sender_couplet, thread_start_info)
+        } else {
+            self.process_batch(batch, thread_start_info)
+        };
+
+        if let Some(activity) = &self.activity {
+            activity.finished();
+        }
+        result
+    }
+
+    /// Processes every message that is already queued (including any deferred message) in the order
+    /// that they are taken off of the lanes
+    fn process_queued(
+        &mut self,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        while let Some((_, sender_couplet)) = self.try_next_sender_couplet() {
+            let batch = self.gather_batch(sender_couplet);
+            self.process_sender_couplets(batch, thread_start_info)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Hands a batch of more than one message to the pool item's
+    /// [`process_batch`](PoolItem::process_batch)
+    ///
+    /// Each response is returned to the sender of the corresponding message. Cancelled messages (see
+    /// [`is_discarded`](PoolThread::is_discarded)) are discarded.
+    fn process_batch(
+        &mut self,
+        batch: Vec<SenderCouplet<P>>,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        let (return_to, requests): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .filter(|sender_couplet| {
+                let discarded = self.is_discarded(sender_couplet);
+                if discarded {
+                    event!(
+                        Level::DEBUG,
+                        "Discarding cancelled request for {:?}, id={:?}",
+                        P::name(),
+                        sender_couplet.request().pool_item_id()
+                    );
+                }
+                !discarded
+            })
+            .map(|sender_couplet| {
+                let SenderCouplet {
+                    return_to, request, ..
+                } = sender_couplet;
+                let ThreadRequestResponse::MessagePoolItem(request) = request else {
+                    panic!("only pool item messages are batched");
+                };
+                (return_to, request)
+            })
+            .unzip();
+
+        let Some(id) = requests.first().map(IdTargeted::id) else {
+            return ControlFlow::Continue(());
+        };
+
+        event!(
+            Level::DEBUG,
+            "Processing a batch of {} requests for {:?}, id={:?}",
+            requests.len(),
+            P::name(),
+            id
+        );
+
+        let _resources = self.resources.enter();
+        let being_processed = set_being_processed(Some(&id), None);
+        if let Some(thread_start_info) = thread_start_info.as_mut() {
+            P::pool_item_pre_process(id.clone(), thread_start_info);
+        }
+
+        let request_count = requests.len();
+        let message_type = P::message_type(&requests[0]);
+        let targeted = self
+            .pool_item_map
+            .get_mut(&id)
+            .expect("batches are only gathered for existing pool items");
+        // if the batch panics and the pool item handles it none of the messages are answered
+        if let Some(responses) = catch_message_error(targeted, &id, message_type, |targeted| {
+            targeted.process_batch(requests)
+        }) {
+            assert_eq!(
+                request_count,
+                responses.len(),
+                "process_batch must return one response per request"
+            );
+            for (return_to, response) in return_to.iter().zip(responses) {
+                report_error_response(&response, &id, message_type, thread_start_info.as_mut());
+                let _ = Self::send_response(return_to, response, None);
+            }
+        }
+
+        drop(being_processed);
+        if let Some(thread_start_info) = thread_start_info.as_mut() {
+            P::pool_item_post_process(id, thread_start_info);
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Returns the id of the targeted pool item if the message can be part of a batch
+    ///
+    /// Streaming requests are never batched.
+    pub(super) fn batch_id(sender_couplet: &SenderCouplet<P>) -> Option<P::Key> {
+        match sender_couplet.request() {
+            ThreadRequestResponse::MessagePoolItem(request) if !P::is_stream(request) => {
+                Some(request.id())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam_channel::unbounded;
+
+    use crate::{
+        PoolItem, Priority, pool_item, pool_thread::PoolThread, sender_couplet::SenderCouplet,
+        thread_request_response::*,
+    };
+
+    /// A pool item with a streaming method
+    #[derive(Debug)]
+    pub struct Feed {
+        id: u64,
+    }
+
+    #[pool_item]
+    impl Feed {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+
+        #[messaging(EntriesRequest, EntriesResponse, stream)]
+        pub fn entries(&self, count: u64) -> Vec<u64> {
+            (0..count).map(|n| self.id + n).collect()
+        }
+
+        #[messaging(LatestRequest, LatestResponse)]
+        pub fn latest(&self) -> u64 {
+            self.id
+        }
+    }
+
+    /// A pool item that records the size of each batch that it processes
+    #[derive(Debug)]
+    pub struct Ledger {
+        entries: Vec<i64>,
+        flushes: Vec<usize>,
+    }
+
+    impl Ledger {
+        pub fn new(_id: u64) -> Self {
+            Self {
+                entries: Vec::new(),
+                flushes: Vec::new(),
+            }
+        }
+
+        fn apply_batch(&mut self, requests: Vec<LedgerApi>) -> Vec<ThreadRequestResponse<Self>> {
+            self.flushes.push(requests.len());
+            requests
+                .into_iter()
+                .map(|request| self.process_message(request))
+                .collect()
+        }
+    }
+
+    #[pool_item(Batch = "apply_batch", MaxBatchSize = 3)]
+    impl Ledger {
+        #[messaging(PostRequest, PostResponse)]
+        pub fn post(&mut self, amount: i64) -> usize {
+            self.entries.push(amount);
+            self.entries.len()
+        }
+
+        #[messaging(FlushesRequest, FlushesResponse)]
+        pub fn flushes(&self) -> Vec<usize> {
+            self.flushes.clone()
+        }
+    }
+
+    #[test]
+    fn consecutive_requests_for_the_same_pool_item_are_batched_up_to_the_limit() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Ledger>>();
+        let (request_send, request_receive) = unbounded::<SenderCouplet<Ledger>>();
+        let mut target = PoolThread::new(1, request_receive);
+
+        let requests: Vec<ThreadRequestResponse<Ledger>> = vec![
+            LedgerInit(1).into(),
+            LedgerInit(2).into(),
+            PostRequest(1, 10).into(),
+            PostRequest(1, 20).into(),
+            PostRequest(1, 30).into(),
+            PostRequest(1, 40).into(),
+            PostRequest(2, 50).into(),
+            PostRequest(1, 60).into(),
+            FlushesRequest(1).into(),
+            ThreadAbortRequest(1).into(),
+        ];
+        for request in requests {
+            request_send
+                .send(SenderCouplet::new(response_send.clone(), request))
+                .unwrap();
+        }
+
+        target.message_loop();
+
+        let mut responses = response_receive.try_iter().skip(2);
+        let posted: Vec<usize> = responses
+            .by_ref()
+            .take(6)
+            .map(|response| PostResponse::from(response).result)
+            .collect();
+        assert_eq!(vec![1, 2, 3, 4, 1, 5], posted);
+
+        // 10, 20 and 30 fill a batch; 40 is followed by a request for pool item 2 so is processed
+        // on its own; 60 and the flushes request form the last batch
+        let flushes = FlushesResponse::from(responses.next().unwrap()).result;
+        assert_eq!(vec![3, 2], flushes);
+    }
+
+    #[test]
+    fn a_deferred_message_does_not_hold_up_a_higher_priority_message() {
+        let (response_send, _response_receive) = unbounded::<ThreadRequestResponse<Ledger>>();
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Ledger>>();
+        let (normal_send, normal_receive) = unbounded::<SenderCouplet<Ledger>>();
+        let (_low_send, low_receive) = unbounded::<SenderCouplet<Ledger>>();
+        let mut target = PoolThread::new_with_lanes(1, [high_receive, normal_receive, low_receive]);
+        let mut thread_start_info = None;
+        for id in [1, 2] {
+            let _ = target.process_sender_couplet(
+                SenderCouplet::new(response_send.clone(), LedgerInit(id)),
+                &mut thread_start_info,
+            );
+            normal_send
+                .send(SenderCouplet::new(
+                    response_send.clone(),
+                    PostRequest(id, 10),
+                ))
+                .unwrap();
+        }
+
+        // the request for pool item 2 cannot join pool item 1's batch so it is deferred
+        let (_, first) = target.next_sender_couplet().unwrap();
+        assert_eq!(1, target.gather_batch(first).len());
+
+        high_send
+            .send(SenderCouplet::new(
+                response_send,
+                ThreadEchoRequest::new(1, "high".to_string()),
+            ))
+            .unwrap();
+
+        let (_, next) = target.next_sender_couplet().unwrap();
+        assert!(matches!(
+            next.request(),
+            ThreadRequestResponse::ThreadEcho(_)
+        ));
+        let (lane, deferred) = target.next_sender_couplet().unwrap();
+        assert_eq!(Priority::Normal.lane(), lane);
+        assert_eq!(Some(2), deferred.request().pool_item_id());
+    }
+
+    #[test]
+    fn streaming_requests_are_not_batched() {
+        let (response_send, _response_receive) = unbounded::<ThreadRequestResponse<Feed>>();
+
+        let stream = SenderCouplet::new(response_send.clone(), EntriesRequest(1, 3));
+        let latest = SenderCouplet::new(response_send, LatestRequest(1));
+
+        assert_eq!(None, PoolThread::batch_id(&stream));
+        assert_eq!(Some(1), PoolThread::batch_id(&latest));
+    }
+}
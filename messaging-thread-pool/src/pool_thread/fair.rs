@@ -0,0 +1,455 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::ControlFlow,
+};
+
+use crate::{
+    pool_item::PoolItem, priority::Priority, sender_couplet::SenderCouplet,
+    thread_request_response::ThreadRequestResponse,
+};
+
+use super::PoolThread;
+
+/// The messages received by a pool thread that is using fair scheduling, split by the pool item
+/// that they target
+struct Mailboxes<P>
+where
+    P: PoolItem,
+{
+    /// The queued messages of each pool item, in the order they were received
+    mailboxes: HashMap<P::Key, VecDeque<SenderCouplet<P>>>,
+    /// The pool items with queued messages, in the order that they will take their turns
+    turns: VecDeque<P::Key>,
+}
+
+impl<P> Mailboxes<P>
+where
+    P: PoolItem,
+{
+    fn new() -> Self {
+        Self {
+            mailboxes: HashMap::new(),
+            turns: VecDeque::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Adds a message to the end of the mailbox of the pool item that it targets
+    fn push(&mut self, id: P::Key, sender_couplet: SenderCouplet<P>) {
+        let mailbox = self.mailboxes.entry(id.clone()).or_default();
+        if mailbox.is_empty() {
+            self.turns.push_back(id);
+        }
+        mailbox.push_back(sender_couplet);
+    }
+
+    /// Takes up to `budget` messages from the mailbox of the pool item whose turn it is
+    ///
+    /// A pool item that still has messages queued goes to the back of the queue of turns.
+    fn next_turn(&mut self, budget: usize) -> Option<VecDeque<SenderCouplet<P>>> {
+        let id = self.turns.pop_front()?;
+        let mailbox = self
+            .mailboxes
+            .get_mut(&id)
+            .expect("every turn has a mailbox");
+        let turn: VecDeque<_> = mailbox.drain(..budget.min(mailbox.len())).collect();
+        if mailbox.is_empty() {
+            self.mailboxes.remove(&id);
+        } else {
+            self.turns.push_back(id);
+        }
+        Some(turn)
+    }
+}
+
+impl<P> PoolThread<P>
+where
+    P: PoolItem,
+{
+    /// Processes messages by taking turns between the pool items that have messages waiting
+    ///
+    /// Each turn processes up to `budget` messages for one pool item (batching them if the pool item
+    /// supports it). Each priority lane has its own mailboxes; a turn is only taken from a lane's
+    /// mailboxes once the mailboxes of every higher priority lane are empty.
+    /// A message that targets the thread rather than a pool item is processed once the messages received
+    /// before it on its own lane, and those in the mailboxes of every higher priority lane, have been
+    /// processed; it is processed ahead of the mailboxes of lower priority lanes, as it would be by the
+    /// fifo message loop. A shutdown request also waits for every lane's mailboxes, as the thread
+    /// processes everything that it has received before it shuts down.
+    ///
+    /// Returns [`ControlFlow::Break`] once a request to shutdown or abort the thread has been processed
+    /// and [`ControlFlow::Continue`] if every lane has been disconnected.
+    pub(super) fn fair_message_loop(
+        &mut self,
+        budget: usize,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        let budget = budget.max(1);
+        // the mailboxes of each lane, highest priority first
+        let mut mailboxes: [Mailboxes<P>; Priority::COUNT] =
+            std::array::from_fn(|_| Mailboxes::new());
+        // the thread message of each lane that is waiting for the messages received before it
+        let mut thread_messages: [Option<SenderCouplet<P>>; Priority::COUNT] = Default::default();
+
+        'turns: loop {
+            // only wait for a message if there is nothing else to do
+            if thread_messages.iter().all(Option::is_none)
+                && mailboxes.iter().all(Mailboxes::is_empty)
+            {
+                let Some((lane, sender_couplet)) = self.next_sender_couplet() else {
+                    return ControlFlow::Continue(());
+                };
+                thread_messages[lane] = Self::receive_into(&mut mailboxes[lane], sender_couplet);
+            }
+            // take everything that is already queued on the lanes above the highest priority waiting
+            // thread message, so that nothing received after it on its own lane overtakes it
+            loop {
+                let lanes = thread_messages
+                    .iter()
+                    .position(Option::is_some)
+                    .unwrap_or(Priority::COUNT);
+                let Some((lane, sender_couplet)) = self.try_next_sender_couplet_within(lanes)
+                else {
+                    break;
+                };
+                thread_messages[lane] = Self::receive_into(&mut mailboxes[lane], sender_couplet);
+            }
+
+            for lane in 0..Priority::COUNT {
+                if let Some(turn) = mailboxes[lane].next_turn(budget) {
+                    self.process_turn(turn, thread_start_info)?;
+                    continue 'turns;
+                }
+                let ready = thread_messages[lane]
+                    .as_ref()
+                    .is_some_and(|thread_message| {
+                        !matches!(
+                            thread_message.request(),
+                            ThreadRequestResponse::ThreadShutdown(_)
+                        ) || mailboxes.iter().all(Mailboxes::is_empty)
+                    });
+                if ready {
+                    let thread_message = thread_messages[lane].take().expect("checked above");
+                    self.process_sender_couplets(vec![thread_message], thread_start_info)?;
+                    continue 'turns;
+                }
+            }
+        }
+    }
+
+    /// Puts a message that targets a pool item into that pool item's mailbox
+    ///
+    /// A message that targets the thread is returned instead.
+    fn receive_into(
+        mailboxes: &mut Mailboxes<P>,
+        sender_couplet: SenderCouplet<P>,
+    ) -> Option<SenderCouplet<P>> {
+        match sender_couplet.request().pool_item_id() {
+            Some(id) => {
+                mailboxes.push(id, sender_couplet);
+                None
+            }
+            None => Some(sender_couplet),
+        }
+    }
+
+    /// Processes the messages of one pool item's turn in order
+    ///
+    /// Consecutive messages are batched up to the pool item's
+    /// [`max_batch_size`](PoolItem::max_batch_size).
+    fn process_turn(
+        &mut self,
+        mut turn: VecDeque<SenderCouplet<P>>,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        while let Some(first) = turn.pop_front() {
+            let mut batch = vec![first];
+            if let Some(id) = Self::batch_id(&batch[0])
+                && self.pool_item_map.contains_key(&id)
+            {
+                while batch.len() < P::max_batch_size()
+                    && turn
+                        .front()
+                        .is_some_and(|next| Self::batch_id(next).as_ref() == Some(&id))
+                {
+                    batch.extend(turn.pop_front());
+                }
+            }
+            self.process_sender_couplets(batch, thread_start_info)?;
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use crossbeam_channel::{Sender, unbounded};
+
+    use crate::{
+        pool_item, pool_thread::PoolThread, request_response::RequestResponse,
+        sender_couplet::SenderCouplet, thread_request_response::*,
+    };
+
+    use super::Mailboxes;
+
+    /// A pool item that is scheduled fairly, taking turns of at most 2 messages
+    #[derive(Debug)]
+    pub struct Chatty {
+        id: u64,
+    }
+
+    impl Chatty {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+    }
+
+    #[pool_item(FairBudget = 2)]
+    impl Chatty {
+        #[messaging(PingRequest, PingResponse)]
+        pub fn ping(&self, n: u32) -> (u64, u32) {
+            (self.id, n)
+        }
+    }
+
+    #[test]
+    fn pool_items_take_turns_of_at_most_the_budget() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Chatty>>();
+        let (request_send, request_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let mut target = PoolThread::new(1, request_receive);
+
+        let mut requests: Vec<ThreadRequestResponse<Chatty>> =
+            vec![ChattyInit(1).into(), ChattyInit(2).into()];
+        requests.extend((1..=5).map(|n| PingRequest(1, n).into()));
+        requests.extend((1..=2).map(|n| PingRequest(2, n).into()));
+        requests.push(ThreadAbortRequest(1).into());
+        for request in requests {
+            request_send
+                .send(SenderCouplet::new(response_send.clone(), request))
+                .unwrap();
+        }
+
+        target.message_loop();
+
+        let pings: Vec<(u64, u32)> = response_receive
+            .try_iter()
+            .filter_map(|response| match response {
+                ThreadRequestResponse::MessagePoolItem(_) => Some(PingResponse::from(response)),
+                _ => None,
+            })
+            .map(|response| response.result)
+            .collect();
+
+        // pool item 2 does not have to wait for all of pool item 1's messages to be processed,
+        // and each pool item's messages are processed in order
+        assert_eq!(
+            vec![(1, 1), (2, 1), (1, 2), (1, 3), (2, 2), (1, 4), (1, 5)],
+            pings
+        );
+    }
+
+    #[test]
+    fn higher_priority_mailboxes_are_served_before_lower_priority_turns() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Chatty>>();
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let (normal_send, normal_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let (low_send, low_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let mut target = PoolThread::new_with_lanes(1, [high_receive, normal_receive, low_receive]);
+
+        let mut high: Vec<ThreadRequestResponse<Chatty>> =
+            vec![ChattyInit(1).into(), ChattyInit(2).into()];
+        high.extend((1..=4).map(|n| PingRequest(2, n).into()));
+        for request in high {
+            high_send
+                .send(SenderCouplet::new(response_send.clone(), request))
+                .unwrap();
+        }
+        for n in 1..=3 {
+            normal_send
+                .send(SenderCouplet::new(response_send.clone(), PingRequest(1, n)))
+                .unwrap();
+        }
+        low_send
+            .send(SenderCouplet::new(response_send, ThreadAbortRequest(1)))
+            .unwrap();
+
+        target.message_loop();
+
+        let pings: Vec<(u64, u32)> = response_receive
+            .try_iter()
+            .filter_map(|response| match response {
+                ThreadRequestResponse::MessagePoolItem(_) => Some(PingResponse::from(response)),
+                _ => None,
+            })
+            .map(|response| response.result)
+            .collect();
+
+        // pool item 1's normal priority messages do not get a turn between pool item 2's high
+        // priority turns
+        assert_eq!(
+            vec![(2, 1), (2, 2), (2, 3), (2, 4), (1, 1), (1, 2), (1, 3)],
+            pings
+        );
+    }
+
+    /// The high priority lane of a thread, and where to send the response to a request sent on it
+    type Lane = (
+        Sender<SenderCouplet<Alarm>>,
+        Sender<ThreadRequestResponse<Alarm>>,
+    );
+
+    thread_local! {
+        /// The high priority lane of the thread under test, on which `Alarm` sends an abort
+        static HIGH_LANE: RefCell<Option<Lane>> = const { RefCell::new(None) };
+    }
+
+    /// A pool item that aborts its thread the first time that it rings, taking turns of one message
+    #[derive(Debug)]
+    pub struct Alarm {
+        id: u64,
+    }
+
+    impl Alarm {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+    }
+
+    #[pool_item(FairBudget = 1)]
+    impl Alarm {
+        #[messaging(RingRequest, RingResponse)]
+        pub fn ring(&self, n: u32) -> (u64, u32) {
+            if let Some((high_lane, return_to)) = HIGH_LANE.take() {
+                high_lane
+                    .send(SenderCouplet::new(return_to, ThreadAbortRequest(1)))
+                    .unwrap();
+            }
+            (self.id, n)
+        }
+    }
+
+    #[test]
+    fn abort_is_processed_ahead_of_lower_priority_mailboxes() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Alarm>>();
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Alarm>>();
+        let (_normal_send, normal_receive) = unbounded::<SenderCouplet<Alarm>>();
+        let (low_send, low_receive) = unbounded::<SenderCouplet<Alarm>>();
+        let mut target = PoolThread::new_with_lanes(1, [high_receive, normal_receive, low_receive]);
+
+        // every ring is taken into the low priority mailbox before the first of them sends the abort
+        let mut requests: Vec<ThreadRequestResponse<Alarm>> = vec![AlarmInit(1).into()];
+        requests.extend((1..=10).map(|n| RingRequest(1, n).into()));
+        for request in requests {
+            low_send
+                .send(SenderCouplet::new(response_send.clone(), request))
+                .unwrap();
+        }
+        HIGH_LANE.set(Some((high_send, response_send)));
+
+        target.message_loop();
+
+        let mut responses: Vec<ThreadRequestResponse<Alarm>> =
+            response_receive.try_iter().collect();
+        assert!(matches!(
+            responses.pop(),
+            Some(ThreadRequestResponse::ThreadAbort(
+                RequestResponse::Response(_)
+            ))
+        ));
+        let rings: Vec<(u64, u32)> = responses
+            .into_iter()
+            .filter_map(|response| match response {
+                ThreadRequestResponse::MessagePoolItem(_) => Some(RingResponse::from(response)),
+                _ => None,
+            })
+            .map(|response| response.result)
+            .collect();
+        // the abort does not wait for the rest of the low priority mailbox
+        assert_eq!(vec![(1, 1)], rings);
+    }
+
+    #[test]
+    fn shutdown_processes_every_lane_before_the_thread_ends() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Chatty>>();
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let (_normal_send, normal_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let (low_send, low_receive) = unbounded::<SenderCouplet<Chatty>>();
+        let mut target = PoolThread::new_with_lanes(1, [high_receive, normal_receive, low_receive]);
+
+        high_send
+            .send(SenderCouplet::new(response_send.clone(), ChattyInit(1)))
+            .unwrap();
+        for n in 1..=3 {
+            low_send
+                .send(SenderCouplet::new(response_send.clone(), PingRequest(1, n)))
+                .unwrap();
+        }
+        high_send
+            .send(SenderCouplet::new(response_send, ThreadShutdownRequest(1)))
+            .unwrap();
+
+        target.message_loop();
+
+        let pings: Vec<(u64, u32)> = response_receive
+            .try_iter()
+            .filter_map(|response| match response {
+                ThreadRequestResponse::MessagePoolItem(_) => Some(PingResponse::from(response)),
+                _ => None,
+            })
+            .map(|response| response.result)
+            .collect();
+        assert_eq!(vec![(1, 1), (1, 2), (1, 3)], pings);
+    }
+
+    #[test]
+    fn fairly_scheduled_pool_items_can_be_used_from_a_thread_pool() {
+        let target = crate::ThreadPool::<Chatty>::new(2);
+        target
+            .send_and_receive((0..4).map(ChattyInit))
+            .unwrap()
+            .for_each(|response| assert!(response.result().is_ok()));
+
+        let mut pings: Vec<(u64, u32)> = target
+            .send_and_receive((0..4).map(|id| PingRequest(id, 7)))
+            .unwrap()
+            .map(|response| response.result)
+            .collect();
+        pings.sort();
+
+        assert_eq!(vec![(0, 7), (1, 7), (2, 7), (3, 7)], pings);
+        assert_eq!(2, target.shutdown().len());
+    }
+
+    #[test]
+    fn mailboxes_keep_the_order_of_each_pool_item() {
+        let (response_send, _response_receive) = unbounded::<ThreadRequestResponse<Chatty>>();
+        let mut target = Mailboxes::<Chatty>::new();
+        for (id, n) in [(1, 1), (1, 2), (2, 1), (1, 3)] {
+            target.push(
+                id,
+                SenderCouplet::new(response_send.clone(), PingRequest(id, n)),
+            );
+        }
+
+        let turns: Vec<Vec<u32>> = std::iter::from_fn(|| target.next_turn(2))
+            .map(|turn| {
+                turn.into_iter()
+                    .map(|sender_couplet| match sender_couplet.request() {
+                        ThreadRequestResponse::MessagePoolItem(ChattyApi::PingRequest(
+                            crate::request_response::RequestResponse::Request(request),
+                        )) => request.1,
+                        _ => panic!("unexpected message"),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(vec![vec![1, 2], vec![1], vec![3]], turns);
+        assert!(target.is_empty());
+    }
+}
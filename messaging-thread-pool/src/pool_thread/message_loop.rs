@@ -1,10 +1,21 @@
-use std::collections::hash_map::Entry;
+use std::{collections::hash_map::Entry, ops::ControlFlow};
 
+use crossbeam_channel::{Select, Sender, TryRecvError};
 use tracing::{Level, event, instrument};
 
 use crate::{
-    ID_BEING_PROCESSED, pool_item::PoolItem, request_response::RequestResponse,
-    sender_couplet::SenderCouplet, thread_request_response::*,
+    cancellation_token::CancellationToken,
+    deadlock::enter_pool_thread,
+    id_being_processed::set_being_processed,
+    id_targeted::IdTargeted,
+    pool_item::{
+        PoolItem, RemovalReason,
+        lifecycle::{catch_message_error, report_error_response},
+    },
+    request_response::RequestResponse,
+    scheduling::Scheduling,
+    sender_couplet::SenderCouplet,
+    thread_request_response::*,
 };
 
 use super::PoolThread;
@@ -25,9 +36,14 @@ where
     ///
     /// Thread requests are handled within this loop and are used to control the thread pool
     ///
+    /// Higher priority lanes are always drained before lower priority lanes are looked at.
+    ///
     /// ThreadShutdown and ThreadAbort messages cause the message loop to exit and as a result end the thread.
     #[instrument(skip(self), fields(id=self.thread_id, name=P::name()))]
     pub fn message_loop(&mut self) {
+        // blocking calls made by pool items on this thread are checked for deadlocks
+        let _pool_thread = enter_pool_thread();
+
         // will loop until the queue is empty and the sender is dropped
 
         // the thread start info could be anything but it is expected to provide some
@@ -35,157 +51,386 @@ where
         // in simple cases it may just, for example hold the default guard for the subscriber
         let mut thread_start_info = P::thread_start();
 
-        while let Ok(sender_couplet) = self.pool_thread_receiver.recv() {
-            event!(
-                Level::TRACE,
-                "receiving request {:?}",
-                sender_couplet.request(),
-            );
+        let message_loop_result = match P::scheduling() {
+            Scheduling::Fifo => self.fifo_message_loop(&mut thread_start_info),
+            Scheduling::Fair { budget } => self.fair_message_loop(budget, &mut thread_start_info),
+        };
+        if message_loop_result.is_break() {
+            // return breaking out of the message loop and thus ending the thread.
+            return;
+        }
+        Self::stop_thread(&mut thread_start_info);
 
-            let SenderCouplet { return_to, request } = sender_couplet;
+        // to get here the "send end" of all of the channels must have been dropped which
+        // suggest that the main thread has ended.
+        panic!("message loop finished unexpectedly; thread shutting down");
+    }
 
-            let id = request.id();
-            // store the id being processed in thread local storage
-            ID_BEING_PROCESSED.replace(Some(id));
-            // provide hook to perform actions on pool item load (tracing for example)
-            if let Some(thread_start_info) = &mut thread_start_info {
-                P::pool_item_pre_process(id, thread_start_info);
-            }
+    /// Processes messages in the order that they are taken off of the lanes
+    ///
+    /// Returns [`ControlFlow::Break`] once a request to shutdown or abort the thread has been processed
+    /// and [`ControlFlow::Continue`] if every lane has been disconnected.
+    fn fifo_message_loop(
+        &mut self,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        while let Some((_, sender_couplet)) = self.next_sender_couplet() {
+            // consecutive queued messages for the same pool item may be processed together
+            let batch = self.gather_batch(sender_couplet);
+            self.process_sender_couplets(batch, thread_start_info)?;
+        }
+        // loop will only exit here if the "main" thread has exited; this is not expected
+        ControlFlow::Continue(())
+    }
 
-            let response = match request {
-                ThreadRequestResponse::MessagePoolItem(request) => {
-                    // find the pool item that needs to process the request
-                    if let Some(targeted) = self.pool_item_map.get_mut(&id) {
-                        targeted.process_message(request)
-                    } else {
-                        P::id_not_found(&request)
-                    }
+    /// This function takes the next message to process off of the highest priority lane that has one
+    ///
+    /// A message deferred while gathering the previous batch is taken before the rest of its lane.
+    /// It blocks until a message is available and returns it with the lane that it was taken from.
+    /// None is returned when every lane has been disconnected.
+    pub(super) fn next_sender_couplet(&mut self) -> Option<(usize, SenderCouplet<P>)> {
+        loop {
+            let mut open_lanes = Vec::with_capacity(self.pool_thread_receivers.len());
+            for (lane, receiver) in self.pool_thread_receivers.iter().enumerate() {
+                if let Some(sender_couplet) = self.deferred[lane].take() {
+                    return Some((lane, sender_couplet));
                 }
-                ThreadRequestResponse::AddPoolItem(RequestResponse::Request(request)) => {
-                    match P::new_pool_item(request) {
-                        Ok(new_pool_item) => {
-                            event!(
-                                Level::DEBUG,
-                                "Inserting a new {:?} into the threads map, id={:?}",
-                                P::name(),
-                                id
-                            );
+                match receiver.try_recv() {
+                    Ok(sender_couplet) => return Some((lane, sender_couplet)),
+                    Err(TryRecvError::Empty) => open_lanes.push(receiver),
+                    Err(TryRecvError::Disconnected) => (),
+                }
+            }
+
+            if open_lanes.is_empty() {
+                return None;
+            }
 
-                            // try and add the new item
-                            match self.pool_item_map.entry(id) {
-                                Entry::Vacant(v) => {
-                                    // give the pool item the opportunity to create its own tracing subscriber
-                                    // let tracing_subscriber = new_pool_item.pool_item_subscriber();
+            // wait for any of the open lanes to become ready and then re-check them in priority order
+            let mut select = Select::new();
+            for receiver in open_lanes {
+                select.recv(receiver);
+            }
+            select.ready();
+        }
+    }
 
-                                    v.insert(new_pool_item);
+    /// This function processes a single message that has been taken off of the [`PoolThread`]s queue
+    ///
+    /// The response is sent back on the channel contained in the sender couplet.
+    /// If the couplet's cancellation token has been cancelled (or the pool's graceful shutdown has
+    /// passed its deadline) the request is discarded without being processed and no response is sent.
+    /// The thread's resources can be borrowed while the request is processed.
+    ///
+    /// It returns [`ControlFlow::Break`] when the message was a request to shutdown or abort
+    /// the thread; in this case the caller is expected to stop processing messages.
+    pub(crate) fn process_sender_couplet(
+        &mut self,
+        sender_couplet: SenderCouplet<P>,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> ControlFlow<()> {
+        event!(
+            Level::TRACE,
+            "receiving request {:?}",
+            sender_couplet.request(),
+        );
 
-                                    AddResponse::new(id, Ok(id))
-                                }
-                                Entry::Occupied(_) => AddResponse::new(
-                                    id,
-                                    Err("failed to add; pool item already exists".to_string()),
-                                ),
+        if self.is_discarded(&sender_couplet) {
+            event!(
+                Level::DEBUG,
+                "Discarding cancelled request for {:?}, id={:?}",
+                P::name(),
+                sender_couplet.request().pool_item_id()
+            );
+            return ControlFlow::Continue(());
+        }
+
+        let SenderCouplet {
+            return_to,
+            request,
+            cancellation_token,
+        } = sender_couplet;
+
+        // the thread's resources are available to the pool items while the request is processed
+        let _resources = self.resources.enter();
+
+        // requests either target a pool item or the thread itself
+        let pool_item_id = request.pool_item_id();
+        let thread_id = request.thread_id();
+        // store the id being processed in thread local storage
+        let being_processed = set_being_processed(pool_item_id.as_ref(), thread_id);
+        // provide hook to perform actions on pool item load (tracing for example)
+        if let (Some(thread_start_info), Some(id)) = (thread_start_info.as_mut(), &pool_item_id) {
+            P::pool_item_pre_process(id.clone(), thread_start_info);
+        }
+
+        let response: Option<ThreadRequestResponse<P>> = match request {
+            ThreadRequestResponse::MessagePoolItem(request) => {
+                // find the pool item that needs to process the request
+                let id = pool_item_id
+                    .clone()
+                    .expect("pool item messages target a pool item");
+                if let Some(targeted) = self.pool_item_map.get_mut(&id) {
+                    let message_type = P::message_type(&request);
+                    // the pool item sends its own responses; a streaming request sends more than one
+                    catch_message_error(targeted, &id, message_type, |targeted| {
+                        targeted.process_message_stream(request, &mut |response| {
+                            report_error_response(
+                                &response,
+                                &id,
+                                message_type,
+                                thread_start_info.as_mut(),
+                            );
+                            Self::send_response(&return_to, response, cancellation_token.as_ref())
+                        })
+                    });
+                    None
+                } else {
+                    Some(P::id_not_found(&request))
+                }
+            }
+            ThreadRequestResponse::MessagePoolItems(request) => {
+                Some(self.process_multi_item(request))
+            }
+            ThreadRequestResponse::AddPoolItem(RequestResponse::Request(request)) => Some({
+                let id = pool_item_id
+                    .clone()
+                    .expect("add requests target a pool item");
+                match P::new_pool_item(request) {
+                    Ok(new_pool_item) => {
+                        event!(
+                            Level::DEBUG,
+                            "Inserting a new {:?} into the threads map, id={:?}",
+                            P::name(),
+                            id
+                        );
+
+                        // try and add the new item
+                        match self.pool_item_map.entry(id.clone()) {
+                            Entry::Vacant(v) => {
+                                // give the pool item the opportunity to create its own tracing subscriber
+                                // let tracing_subscriber = new_pool_item.pool_item_subscriber();
+
+                                v.insert(new_pool_item).on_added();
+
+                                AddResponse::new(id.clone(), Ok(id))
                             }
-                        }
-                        Err(new_pool_item_error) => {
-                            AddResponse::new(id, Err(new_pool_item_error.error_message))
+                            Entry::Occupied(_) => AddResponse::new(
+                                id,
+                                Err("failed to add; pool item already exists".to_string()),
+                            ),
                         }
                     }
-                    .into()
-                }
-                ThreadRequestResponse::RemovePoolItem(RequestResponse::Request(_request)) => {
-                    let success = self.pool_item_map.remove(&id).is_some();
-
-                    event!(
-                        Level::DEBUG,
-                        "Trying to remove a {:?} from the threads map, id={:?}, success={:?}",
-                        P::name(),
-                        id,
-                        success
-                    );
-
-                    RemovePoolItemResponse::new(id, success).into()
-                }
-                ThreadRequestResponse::ThreadShutdown(RequestResponse::Request(_request)) => {
-                    debug_assert_eq!(
-                        self.thread_id, id,
-                        "this messages should have targeted this thread"
-                    );
-                    // this call to shut down the child threads and consequently empty the internal map
-                    // is how thread shutdown differs from thread abort. Abort just exist the loop and leaves the
-                    // state in place
-                    let children = self.shutdown_child_pool();
-                    return_to
-                        .send(ThreadShutdownResponse::new(id, children).into())
-                        .expect("the send should always succeed");
-                    debug_assert!(
-                        self.pool_item_map.is_empty(),
-                        "ThreadShutdown should drain all elements"
-                    );
-                    // return breaking out of the message loop and thus ending the thread.
-                    return;
-                }
-                ThreadRequestResponse::ThreadEcho(RequestResponse::Request(request)) => {
-                    ThreadEchoResponse::new(id, request.message().to_string(), self.thread_id)
-                        .into()
-                }
-                ThreadRequestResponse::ThreadAbort(RequestResponse::Request(_request)) => {
-                    debug_assert_eq!(
-                        self.thread_id, id,
-                        "this messages should have targeted this thread"
-                    );
-
-                    return_to
-                        .send(ThreadAbortResponse(id).into())
-                        .expect("the send should always succeed");
-
-                    // return breaking out of the message loop and thus ending the thread.
-                    return;
+                    Err(new_pool_item_error) => AddResponse::failed(id, new_pool_item_error),
                 }
+                .into()
+            }),
+            ThreadRequestResponse::RemovePoolItem(RequestResponse::Request(_request)) => {
+                let id = pool_item_id
+                    .clone()
+                    .expect("remove requests target a pool item");
+                let success = match self.pool_item_map.entry(id.clone()) {
+                    Entry::Occupied(mut o) => {
+                        o.get_mut().on_removed(RemovalReason::Removed);
+                        o.remove();
+                        true
+                    }
+                    Entry::Vacant(_) => false,
+                };
+
+                event!(
+                    Level::DEBUG,
+                    "Trying to remove a {:?} from the threads map, id={:?}, success={:?}",
+                    P::name(),
+                    id,
+                    success
+                );
+
+                Some(RemovePoolItemResponse::new(id, success).into())
+            }
+            ThreadRequestResponse::ThreadShutdown(RequestResponse::Request(_request)) => {
+                let id = thread_id.expect("shutdown requests target a thread");
+                debug_assert_eq!(
+                    self.thread_id, id,
+                    "this messages should have targeted this thread"
+                );
+                // this call to shut down the pool items (and their child pools) and consequently empty the internal map
+                // is how thread shutdown differs from thread abort. Abort just exist the loop and leaves the
+                // state in place
+                let children = self.shutdown_pool_items(thread_start_info);
+                let stop_data = Self::stop_thread(thread_start_info);
+                // the pool does not wait for the response if it has detached its threads or if a
+                // graceful shutdown has passed its deadline
+                let _ = return_to.send(
+                    ThreadShutdownResponse::new(id, children)
+                        .with_stop_data(stop_data)
+                        .into(),
+                );
+                debug_assert!(
+                    self.pool_item_map.is_empty(),
+                    "ThreadShutdown should drain all elements"
+                );
+                // break out of the message loop and thus end the thread.
+                return ControlFlow::Break(());
+            }
+            ThreadRequestResponse::ThreadEcho(RequestResponse::Request(request)) => Some(
+                ThreadEchoResponse::new(
+                    request.id(),
+                    request.message().to_string(),
+                    self.thread_id,
+                )
+                .into(),
+            ),
+            ThreadRequestResponse::ThreadAbort(RequestResponse::Request(_request)) => {
+                let id = thread_id.expect("abort requests target a thread");
+                debug_assert_eq!(
+                    self.thread_id, id,
+                    "this messages should have targeted this thread"
+                );
+                // the pool items are left in place but the thread's state is torn down
+                Self::stop_thread(thread_start_info);
+
+                // the caller may no longer be waiting for the response
+                let _ = return_to.send(ThreadAbortResponse(id).into());
+
+                // break out of the message loop and thus end the thread.
+                return ControlFlow::Break(());
+            }
 
-                _ => panic!("unrecognised thread thread request"),
-            };
-            event!(Level::TRACE, ?response);
-
-            match return_to.send(response) {
-                Ok(_) => (),
-                Err(err) => {
-                    // The channel that is supposed to be receiving the response cannot receive it,
-                    // it has probably been dropped,
-                    // discard the response message and continue
-                    event!(
-                        Level::WARN,
-                        "Cannot return results, other end of channel has most likely been dropped. Err = {}",
-                        &err
-                    );
-                }
-            };
+            _ => panic!("unrecognised thread thread request"),
+        };
+        if let Some(response) = response {
+            let _ = Self::send_response(&return_to, response, None);
+        }
+
+        // reset the thread local storage to indicate that no id is currently being processed
+        drop(being_processed);
+        // provide hook for post item processing (removing tracing for example)
+        if let (Some(thread_start_info), Some(id)) = (thread_start_info.as_mut(), pool_item_id) {
+            P::pool_item_post_process(id, thread_start_info);
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Passes the thread's state (if any) to [`PoolItem::thread_stop`], returning the data to forward
+    /// in the thread's [`ThreadShutdownResponse`]
+    fn stop_thread(thread_start_info: &mut Option<P::ThreadStartInfo>) -> Option<ThreadStopData> {
+        thread_start_info.take().and_then(P::thread_stop)
+    }
 
-            // reset the thread local storage to indicate that no id is currently being processed
-            ID_BEING_PROCESSED.replace(None);
-            // provide hook for post item processing (removing tracing for example)
-            if let Some(thread_start_info) = &mut thread_start_info {
-                P::pool_item_post_process(id, thread_start_info);
+    /// This function takes the next message to process off of the highest priority lane that has one
+    /// without blocking, returning it with the lane that it was taken from
+    ///
+    /// A message deferred while gathering the previous batch is taken before the rest of its lane.
+    pub(super) fn try_next_sender_couplet(&mut self) -> Option<(usize, SenderCouplet<P>)> {
+        self.try_next_sender_couplet_within(self.pool_thread_receivers.len())
+    }
+
+    /// The same as [`try_next_sender_couplet`](Self::try_next_sender_couplet) except that only the
+    /// first `lanes` lanes (the highest priority ones) are looked at
+    pub(super) fn try_next_sender_couplet_within(
+        &mut self,
+        lanes: usize,
+    ) -> Option<(usize, SenderCouplet<P>)> {
+        self.pool_thread_receivers
+            .iter()
+            .zip(&mut self.deferred)
+            .take(lanes)
+            .enumerate()
+            .find_map(|(lane, (receiver, deferred))| {
+                deferred
+                    .take()
+                    .or_else(|| receiver.try_recv().ok())
+                    .map(|sender_couplet| (lane, sender_couplet))
+            })
+    }
+
+    /// This function sends a response back to the caller
+    ///
+    /// It returns [`ControlFlow::Break`] if the caller is no longer listening for responses to the request,
+    /// either because it has dropped its end of the channel or because it has cancelled the request
+    pub(super) fn send_response(
+        return_to: &Sender<ThreadRequestResponse<P>>,
+        response: ThreadRequestResponse<P>,
+        cancellation_token: Option<&CancellationToken>,
+    ) -> ControlFlow<()> {
+        event!(Level::TRACE, ?response);
+
+        match return_to.send(response) {
+            Ok(_) => (),
+            Err(err) => {
+                // The channel that is supposed to be receiving the response cannot receive it,
+                // it has probably been dropped,
+                // discard the response message and continue
+                event!(
+                    Level::WARN,
+                    "Cannot return results, other end of channel has most likely been dropped. Err = {}",
+                    &err
+                );
+                return ControlFlow::Break(());
             }
+        };
 
-            // loop will only exit here if the "main" thread has exited; this is not expected
+        if cancellation_token.is_some_and(CancellationToken::is_cancelled) {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
         }
-
-        // to get here the "send end" of the channel must have been dropped which
-        // suggest that the main thread has ended.
-        panic!("message loop finished unexpectedly; thread shutting down");
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        sync::Mutex,
+        thread::{self, ThreadId},
+    };
+
     use crossbeam_channel::unbounded;
 
     use crate::{
-        pool_thread::PoolThread, samples::*, sender_couplet::SenderCouplet,
-        thread_request_response::*,
+        CancellationToken, pool_item, pool_thread::PoolThread, samples::*,
+        sender_couplet::SenderCouplet, thread_request_response::*,
     };
 
+    /// The threads on which a [`Flusher`] thread has stopped
+    static STOPPED_ON: Mutex<Vec<ThreadId>> = Mutex::new(Vec::new());
+
+    /// A pool item whose threads buffer writes that are flushed when the thread stops
+    #[derive(Debug)]
+    pub struct Flusher {
+        id: u64,
+    }
+
+    #[pool_item(
+        ThreadStartInfo = "Vec<u64>",
+        ThreadStart = "start",
+        ThreadStop = "stop"
+    )]
+    impl Flusher {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+
+        pub fn start() -> Option<Vec<u64>> {
+            Some(vec![1, 2, 3])
+        }
+
+        pub fn stop(buffer: Vec<u64>) -> Option<ThreadStopData> {
+            STOPPED_ON
+                .lock()
+                .expect("no poisoned locks")
+                .push(thread::current().id());
+            Some(ThreadStopData::new(buffer.len()))
+        }
+
+        #[messaging(FlusherIdRequest, FlusherIdResponse)]
+        pub fn flusher_id(&self) -> u64 {
+            self.id
+        }
+    }
+
     #[test]
     fn send_init_id_2_twice_returns_response_indicating_second_request_was_ignored() {
         let id = 2;
@@ -351,16 +596,17 @@ mod tests {
             response_receive.recv().unwrap().into();
 
         // there should be one thread shutdown
-        // Randoms pool item "pretends" that it has shutdown a thread pool and returns its id
-        // as there are 2 pool items is is non-deterministic which one will get called
-        assert!(
+        // Randoms pool item "pretends" that it has shutdown a thread pool and returns its id;
+        // every pool item is shut down, in order of id
+        assert_eq!(
+            ThreadShutdownResponse::new(
+                15,
+                vec![
+                    ThreadShutdownResponse::new(1, vec![]),
+                    ThreadShutdownResponse::new(2, vec![])
+                ]
+            ),
             thread_shutdown_payload
-                == ThreadShutdownResponse::new(15, vec![ThreadShutdownResponse::new(1, vec![])])
-                || thread_shutdown_payload
-                    == ThreadShutdownResponse::new(
-                        15,
-                        vec![ThreadShutdownResponse::new(2, vec![])]
-                    )
         );
         assert!(target.pool_item_map.is_empty());
     }
@@ -397,16 +643,17 @@ mod tests {
         // there should be one thread shutdown
         let thread_shutdown_response: ThreadShutdownResponse =
             response_receive.recv().unwrap().into();
-        // Randoms pool item "pretends" that it has shutdown a thread pool with an id equal to its id
-        // as there are 2 pool items it is not deterministic which one will have shutdown called
-        assert!(
+        // Randoms pool item "pretends" that it has shutdown a thread pool with an id equal to its id;
+        // every pool item is shut down, in order of id
+        assert_eq!(
+            ThreadShutdownResponse::new(
+                5,
+                vec![
+                    ThreadShutdownResponse::new(101, vec![]),
+                    ThreadShutdownResponse::new(102, vec![])
+                ]
+            ),
             thread_shutdown_response
-                == ThreadShutdownResponse::new(5, vec![ThreadShutdownResponse::new(101, vec![])])
-                || thread_shutdown_response
-                    == ThreadShutdownResponse::new(
-                        5,
-                        vec![ThreadShutdownResponse::new(102, vec![])]
-                    )
         );
         assert!(target.pool_item_map.is_empty());
     }
@@ -579,6 +826,70 @@ mod tests {
         assert_eq!(1, thread_abort_response.thread_id());
     }
 
+    #[test]
+    fn abort_exits_loop_when_the_caller_has_stopped_waiting() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Randoms>>();
+        let (request_send, request_receive) = unbounded::<SenderCouplet<Randoms>>();
+
+        let mut target = PoolThread::new(1, request_receive);
+
+        request_send
+            .send(SenderCouplet::<Randoms>::new(
+                response_send,
+                ThreadAbortRequest(1),
+            ))
+            .unwrap();
+        drop(response_receive);
+
+        // the loop ends without panicking even though the response cannot be sent
+        target.message_loop();
+    }
+
+    #[test]
+    fn thread_stop_data_is_forwarded_in_the_shutdown_response() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Flusher>>();
+        let (request_send, request_receive) = unbounded::<SenderCouplet<Flusher>>();
+
+        let mut target = PoolThread::new(1, request_receive);
+
+        request_send
+            .send(SenderCouplet::new(response_send, ThreadShutdownRequest(1)))
+            .unwrap();
+
+        target.message_loop();
+
+        let thread_shutdown_payload: ThreadShutdownResponse =
+            response_receive.recv().unwrap().into();
+        assert_eq!(
+            Some(&3),
+            thread_shutdown_payload
+                .stop_data()
+                .and_then(ThreadStopData::downcast_ref::<usize>)
+        );
+    }
+
+    #[test]
+    fn thread_stop_is_called_when_the_thread_is_aborted() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Flusher>>();
+        let (request_send, request_receive) = unbounded::<SenderCouplet<Flusher>>();
+
+        let mut target = PoolThread::new(1, request_receive);
+
+        request_send
+            .send(SenderCouplet::new(response_send, ThreadAbortRequest(1)))
+            .unwrap();
+
+        target.message_loop();
+
+        let _: ThreadAbortResponse = response_receive.recv().unwrap().into();
+        assert!(
+            STOPPED_ON
+                .lock()
+                .expect("no poisoned locks")
+                .contains(&thread::current().id())
+        );
+    }
+
     #[test]
     fn id_2_receives_shutdown_message_exits_loop() {
         let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Randoms>>();
@@ -636,4 +947,145 @@ mod tests {
             thread_shutdown_payload.children()
         )
     }
+
+    #[test]
+    fn messages_queued_on_all_lanes_higher_lanes_processed_first() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Randoms>>();
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Randoms>>();
+        let (normal_send, normal_receive) = unbounded::<SenderCouplet<Randoms>>();
+        let (low_send, low_receive) = unbounded::<SenderCouplet<Randoms>>();
+
+        let mut target = PoolThread::new_with_lanes(0, [high_receive, normal_receive, low_receive]);
+
+        // queue in reverse priority order; the shutdown goes last on the lowest lane
+        low_send
+            .send(SenderCouplet::new(
+                response_send.clone(),
+                ThreadEchoRequest::new(0, "low".to_string()),
+            ))
+            .unwrap();
+        low_send
+            .send(SenderCouplet::new(
+                response_send.clone(),
+                ThreadShutdownRequest(0),
+            ))
+            .unwrap();
+        for message in ["normal 1", "normal 2"] {
+            normal_send
+                .send(SenderCouplet::new(
+                    response_send.clone(),
+                    ThreadEchoRequest::new(0, message.to_string()),
+                ))
+                .unwrap();
+        }
+        high_send
+            .send(SenderCouplet::new(
+                response_send,
+                ThreadEchoRequest::new(0, "high".to_string()),
+            ))
+            .unwrap();
+
+        target.message_loop();
+
+        let echoed: Vec<String> = (0..4)
+            .map(|_| {
+                let response: ThreadEchoResponse = response_receive.recv().unwrap().into();
+                response.message().to_string()
+            })
+            .collect();
+
+        assert_eq!(vec!["high", "normal 1", "normal 2", "low"], echoed);
+        let _shutdown: ThreadShutdownResponse = response_receive.recv().unwrap().into();
+    }
+
+    #[test]
+    fn shutdown_on_the_high_lane_processes_everything_queued_first() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Randoms>>();
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Randoms>>();
+        let (normal_send, normal_receive) = unbounded::<SenderCouplet<Randoms>>();
+        let (low_send, low_receive) = unbounded::<SenderCouplet<Randoms>>();
+
+        let mut target = PoolThread::new_with_lanes(0, [high_receive, normal_receive, low_receive]);
+
+        for (lane, message) in [(&normal_send, "normal"), (&low_send, "low")] {
+            lane.send(SenderCouplet::new(
+                response_send.clone(),
+                ThreadEchoRequest::new(0, message.to_string()),
+            ))
+            .unwrap();
+        }
+        high_send
+            .send(SenderCouplet::new(response_send, ThreadShutdownRequest(0)))
+            .unwrap();
+
+        target.message_loop();
+
+        let echoed: Vec<String> = (0..2)
+            .map(|_| {
+                let response: ThreadEchoResponse = response_receive.recv().unwrap().into();
+                response.message().to_string()
+            })
+            .collect();
+
+        assert_eq!(vec!["normal", "low"], echoed);
+        let _shutdown: ThreadShutdownResponse = response_receive.recv().unwrap().into();
+        // the normal and low lanes have been drained
+        assert!(normal_send.is_empty());
+        assert!(low_send.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "message loop finished unexpectedly")]
+    fn all_lanes_disconnected_message_loop_panics() {
+        let (high_send, high_receive) = unbounded::<SenderCouplet<Randoms>>();
+        let (normal_send, normal_receive) = unbounded::<SenderCouplet<Randoms>>();
+        let (low_send, low_receive) = unbounded::<SenderCouplet<Randoms>>();
+
+        let mut target = PoolThread::new_with_lanes(0, [high_receive, normal_receive, low_receive]);
+
+        drop((high_send, normal_send, low_send));
+
+        target.message_loop();
+    }
+
+    #[test]
+    fn cancelled_request_is_discarded_without_response() {
+        let (response_send, response_receive) = unbounded::<ThreadRequestResponse<Randoms>>();
+        let (request_send, request_receive) = unbounded::<SenderCouplet<Randoms>>();
+
+        let mut target = PoolThread::new(1, request_receive);
+
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        let live = CancellationToken::new();
+
+        request_send
+            .send(SenderCouplet::new_with_cancellation_token(
+                response_send.clone(),
+                RandomsAddRequest(1),
+                cancelled,
+            ))
+            .unwrap();
+        request_send
+            .send(SenderCouplet::new_with_cancellation_token(
+                response_send.clone(),
+                RandomsAddRequest(2),
+                live,
+            ))
+            .unwrap();
+        request_send
+            .send(SenderCouplet::new(response_send, ThreadAbortRequest(1)))
+            .unwrap();
+
+        target.message_loop();
+
+        // only the request with the live token is processed
+        let add_response: AddResponse = response_receive.recv().unwrap().into();
+        assert_eq!(2, add_response.id());
+        let _abort: ThreadAbortResponse = response_receive.recv().unwrap().into();
+        assert!(response_receive.is_empty());
+
+        assert_eq!(1, target.pool_item_map.len());
+        assert!(target.pool_item_map.contains_key(&2));
+    }
 }
@@ -1,12 +1,24 @@
+mod batch;
+mod fair;
 mod message_loop;
+mod multi_item;
 pub mod new;
-pub mod shutdown_child_pool;
+pub mod shutdown_pool_items;
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 use crossbeam_channel::Receiver;
 
-use crate::{pool_item::PoolItem, sender_couplet::SenderCouplet};
+use crate::{
+    cancellation_token::CancellationToken, pool_item::PoolItem, priority::Priority,
+    sender_couplet::SenderCouplet, thread_resources::ResourceScope, watchdog::ThreadActivity,
+};
 
 /// This structure represents a thread within the thread pool
 pub struct PoolThread<P>
@@ -15,9 +27,83 @@ where
 {
     /// A unique id assigned to the pool thread
     thread_id: u64,
-    /// Stores the channel on which requests will be received
-    pool_thread_receiver: Receiver<SenderCouplet<P>>,
+    /// Stores the channels on which requests will be received; one per priority lane, highest priority first
+    pool_thread_receivers: [Receiver<SenderCouplet<P>>; Priority::COUNT],
     /// This is a hash map that will hold the ownership of all pool items created in this
     /// pool thread keyed by their ids
-    pool_item_map: HashMap<u64, P>,
+    pool_item_map: HashMap<P::Key, P>,
+    /// For each lane, a message that was taken off of it while gathering a batch but was not part
+    /// of the batch; it is processed before anything else still queued on its lane
+    deferred: [Option<SenderCouplet<P>>; Priority::COUNT],
+    /// The "currently processing" state shared with the pool's watchdog, if it has one
+    activity: Option<Arc<ThreadActivity<P::Key>>>,
+    /// Cancelled when the pool's graceful shutdown misses its deadline; from then on any queued
+    /// messages that target pool items are discarded
+    drain_deadline: Option<CancellationToken>,
+    /// The number of pool item messages sent to this thread that it has not yet started to process,
+    /// shared with the thread's endpoint
+    queued_pool_item_messages: Option<Arc<AtomicUsize>>,
+    /// The resources shared by the pool items on this thread (see [`with_thread_resource`](crate::with_thread_resource))
+    resources: ResourceScope,
+}
+
+impl<P> PoolThread<P>
+where
+    P: PoolItem,
+{
+    /// Returns the number of pool items currently owned by this thread
+    pub(crate) fn pool_item_count(&self) -> usize {
+        self.pool_item_map.len()
+    }
+
+    /// Shares the state of the message currently being processed with a watchdog
+    pub(crate) fn set_activity(&mut self, activity: Arc<ThreadActivity<P::Key>>) {
+        self.activity = Some(activity);
+    }
+
+    /// Shares the token that the pool cancels when a graceful shutdown misses its deadline
+    pub(crate) fn set_drain_deadline(&mut self, drain_deadline: CancellationToken) {
+        self.drain_deadline = Some(drain_deadline);
+    }
+
+    /// Shares the count of the pool item messages queued for this thread with its endpoint
+    pub(crate) fn set_queued_pool_item_messages(&mut self, queued: Arc<AtomicUsize>) {
+        self.queued_pool_item_messages = Some(queued);
+    }
+
+    /// Returns true if the message should be discarded without being processed
+    ///
+    /// This is the case if its cancellation token has been cancelled, or if it is a message for
+    /// pool items and the pool's graceful shutdown has passed its deadline.
+    pub(crate) fn is_discarded(&self, sender_couplet: &SenderCouplet<P>) -> bool {
+        sender_couplet.is_cancelled() || self.is_past_drain_deadline(sender_couplet)
+    }
+
+    /// Returns true if the message is for pool items and the pool's graceful shutdown has passed
+    /// its deadline
+    fn is_past_drain_deadline(&self, sender_couplet: &SenderCouplet<P>) -> bool {
+        sender_couplet.request().is_pool_item_message()
+            && self
+                .drain_deadline
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Counts the pool item messages of a batch off of the messages queued for this thread
+    ///
+    /// Messages discarded because the graceful shutdown has passed its deadline are left counted;
+    /// the pool reports them as discarded.
+    pub(crate) fn count_off(&self, batch: &[SenderCouplet<P>]) {
+        let Some(queued) = &self.queued_pool_item_messages else {
+            return;
+        };
+        let started = batch
+            .iter()
+            .filter(|sender_couplet| {
+                sender_couplet.request().is_pool_item_message()
+                    && !self.is_past_drain_deadline(sender_couplet)
+            })
+            .count();
+        queued.fetch_sub(started, Ordering::AcqRel);
+    }
 }
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::{
+    pool_item::{MultiItemError, PoolItem},
+    thread_request_response::ThreadRequestResponse,
+};
+
+use super::PoolThread;
+
+impl<P> PoolThread<P>
+where
+    P: PoolItem,
+{
+    /// Processes a request that targets several pool items at once.
+    ///
+    /// The request is only executed if every targeted pool item is owned by this thread,
+    /// otherwise it is rejected.
+    pub(crate) fn process_multi_item(&mut self, request: P::Api) -> ThreadRequestResponse<P> {
+        let ids = P::multi_item_ids(&request);
+        match self.targeted_pool_items(&ids) {
+            Ok(mut items) => P::process_multi_item_message(&mut items, request),
+            Err(error) => P::multi_item_rejected(request, error),
+        }
+    }
+
+    /// Borrows each of the pool items with the given ids, in the order of the ids
+    fn targeted_pool_items(
+        &mut self,
+        ids: &[P::Key],
+    ) -> Result<Vec<&mut P>, MultiItemError<P::Key>> {
+        if ids.is_empty() {
+            return Err(MultiItemError::NoIds);
+        }
+        for (i, id) in ids.iter().enumerate() {
+            if ids[..i].contains(id) {
+                return Err(MultiItemError::DuplicateId(id.clone()));
+            }
+            if !self.pool_item_map.contains_key(id) {
+                return Err(MultiItemError::NotCoLocated(id.clone()));
+            }
+        }
+
+        let mut targeted: HashMap<&P::Key, &mut P> = self
+            .pool_item_map
+            .iter_mut()
+            .filter(|(id, _)| ids.contains(id))
+            .collect();
+        Ok(ids
+            .iter()
+            .map(|id| targeted.remove(id).expect("presence checked above"))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam_channel::never;
+
+    use crate::{pool_item::MultiItemError, pool_thread::PoolThread, samples::*};
+
+    fn pool_thread_with_items(ids: &[u64]) -> PoolThread<Randoms> {
+        let mut target = PoolThread::<Randoms>::new(0, never());
+        for id in ids {
+            target.pool_item_map.insert(*id, Randoms::new(*id));
+        }
+        target
+    }
+
+    #[test]
+    fn targeted_pool_items_returned_in_order_of_ids() {
+        let mut target = pool_thread_with_items(&[1, 2, 3]);
+
+        let items = target.targeted_pool_items(&[3, 1]).unwrap();
+
+        assert_eq!(vec![3, 1], items.iter().map(|i| i.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn missing_pool_item_is_not_co_located() {
+        let mut target = pool_thread_with_items(&[1, 2]);
+
+        assert_eq!(
+            Some(MultiItemError::NotCoLocated(4)),
+            target.targeted_pool_items(&[1, 4]).err()
+        );
+    }
+
+    #[test]
+    fn duplicate_and_empty_ids_are_rejected() {
+        let mut target = pool_thread_with_items(&[1, 2]);
+
+        assert_eq!(
+            Some(MultiItemError::DuplicateId(1)),
+            target.targeted_pool_items(&[1, 2, 1]).err()
+        );
+        assert_eq!(
+            Some(MultiItemError::NoIds),
+            target.targeted_pool_items(&[]).err()
+        );
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, unbounded};
 
-use crate::{pool_item::PoolItem, sender_couplet::SenderCouplet};
+use crate::{
+    pool_item::PoolItem, priority::Priority, sender_couplet::SenderCouplet,
+    thread_resources::ResourceScope,
+};
 
 use super::PoolThread;
 
@@ -18,14 +21,41 @@ where
     /// The routing logic is such that the same pool item will always be handled by the same PoolThread.
     ///
     /// Messages are passed to the the PoolThread on the pool_thread_receiver channel.
+    /// This channel is treated as the normal priority lane; the PoolThread has no other lanes.
     ///
     /// The PoolThread spins around its message_loop function processing messages until a request is
     /// received to shutdown.
     pub(crate) fn new(id: u64, pool_thread_receiver: Receiver<SenderCouplet<P>>) -> Self {
+        // the senders for the unused lanes are dropped immediately so these lanes are seen as disconnected
+        let (_, high_priority_receiver) = unbounded();
+        let (_, low_priority_receiver) = unbounded();
+
+        Self::new_with_lanes(
+            id,
+            [
+                high_priority_receiver,
+                pool_thread_receiver,
+                low_priority_receiver,
+            ],
+        )
+    }
+
+    /// This function creates a new PoolThread that receives messages on a channel per priority lane
+    ///
+    /// The receivers are ordered highest priority first (see [`Priority`]).
+    pub(crate) fn new_with_lanes(
+        id: u64,
+        pool_thread_receivers: [Receiver<SenderCouplet<P>>; Priority::COUNT],
+    ) -> Self {
         Self {
             thread_id: id,
-            pool_thread_receiver,
+            pool_thread_receivers,
             pool_item_map: HashMap::default(),
+            deferred: Default::default(),
+            activity: None,
+            drain_deadline: None,
+            queued_pool_item_messages: None,
+            resources: ResourceScope::new(),
         }
     }
 }
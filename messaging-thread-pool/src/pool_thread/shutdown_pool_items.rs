@@ -0,0 +1,151 @@
+use crate::{
+    pool_item::{PoolItem, RemovalReason, ShutdownContext, shutdown_context::ChildPoolRegistry},
+    pool_key::PoolKey,
+    thread_request_response::*,
+};
+
+use super::PoolThread;
+
+impl<P> PoolThread<P>
+where
+    P: PoolItem,
+{
+    /// This function shuts down all of the pool items in this thread
+    ///
+    /// Each pool item's [`on_shutdown`](PoolItem::on_shutdown) is called in ascending order of the
+    /// routing hash of its key, followed by its [`on_removed`](PoolItem::on_removed). The pool items are then dropped, after which any child pools that they
+    /// released and that are no longer referenced elsewhere are shut down.
+    ///
+    /// The responses returned are the children of this thread's [`ThreadShutdownResponse`].
+    pub fn shutdown_pool_items(
+        &mut self,
+        thread_start_info: &mut Option<P::ThreadStartInfo>,
+    ) -> Vec<ThreadShutdownResponse> {
+        let mut pool_items: Vec<_> = self.pool_item_map.drain().collect();
+        pool_items.sort_by_key(|(key, _)| key.routing_hash());
+
+        let mut child_pools = ChildPoolRegistry::default();
+        let mut shutdown_responses = Vec::new();
+        for (_, pool_item) in &mut pool_items {
+            let mut context = ShutdownContext::new(
+                self.thread_id,
+                thread_start_info.as_mut(),
+                &mut child_pools,
+                &mut shutdown_responses,
+            );
+            pool_item.on_shutdown(&mut context);
+            pool_item.on_removed(RemovalReason::Shutdown);
+        }
+        drop(pool_items);
+
+        shutdown_responses.extend(child_pools.shutdown());
+        shutdown_responses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crossbeam_channel::{bounded, unbounded};
+
+    use crate::{
+        ThreadPool, id_provider::id_provider_mutex::IdProviderMutex, pool_thread::PoolThread,
+        samples::*, sender_couplet::SenderCouplet, thread_request_response::*,
+    };
+
+    #[test]
+    fn thread_pool_contains_single_element_2() {
+        let (_send_to_thread, receive_from_caller) = unbounded::<SenderCouplet<Randoms>>();
+
+        // request/response channel
+        let (_send_back, _receive_back_from) = bounded::<ThreadRequestResponse<Randoms>>(0);
+
+        let mut target = PoolThread::<Randoms>::new(1, receive_from_caller);
+
+        let sample_pool_item = Randoms {
+            id: 2,
+            numbers: vec![1, 2],
+        };
+
+        target.pool_item_map.insert(2, sample_pool_item);
+
+        let result = target.shutdown_pool_items(&mut None);
+
+        assert!(target.pool_item_map.is_empty());
+        assert_eq!(1, result.len());
+        assert_eq!(ThreadShutdownResponse::new(2, vec![]), result[0]);
+    }
+
+    #[test]
+    fn thread_pool_contains_single_element_1() {
+        let (_send_to_thread, receive_from_caller) = unbounded::<SenderCouplet<Randoms>>();
+
+        // request/response channel
+        let (_send_back, _receive_back_from) = bounded::<ThreadRequestResponse<Randoms>>(0);
+
+        let mut target = PoolThread::<Randoms>::new(1, receive_from_caller);
+
+        let sample_pool_item = Randoms {
+            id: 1,
+            numbers: vec![100, 200],
+        };
+
+        target.pool_item_map.insert(1, sample_pool_item);
+
+        let result = target.shutdown_pool_items(&mut None);
+
+        assert!(target.pool_item_map.is_empty());
+        assert_eq!(1, result.len());
+        assert_eq!(ThreadShutdownResponse::new(1, vec![]), result[0]);
+    }
+
+    #[test]
+    fn shared_child_pool_is_shut_down_once_under_the_thread_that_released_it_last() {
+        let randoms_thread_pool = Arc::new(ThreadPool::<Randoms>::new(3));
+        let id_provider = Arc::new(IdProviderMutex::new(0));
+        let target = ThreadPool::<RandomsBatch<RandomsThreadPool>>::new(2);
+        target
+            .send_and_receive((0..4).map(|id| RandomsBatchAddRequest {
+                id,
+                number_of_contained_randoms: 2,
+                id_provider: id_provider.clone(),
+                randoms_thread_pool: randoms_thread_pool.clone(),
+            }))
+            .unwrap()
+            .for_each(|response| assert!(response.result().is_ok()));
+        // only the batches now hold the child pool
+        drop(randoms_thread_pool);
+
+        let shutdown_responses = target.shutdown();
+
+        let child_pool_shutdowns: Vec<_> = shutdown_responses
+            .iter()
+            .filter(|thread| !thread.children().is_empty())
+            .collect();
+        assert_eq!(1, child_pool_shutdowns.len());
+        assert_eq!(
+            vec![0, 1, 2],
+            child_pool_shutdowns[0]
+                .children()
+                .iter()
+                .map(ThreadShutdownResponse::thread_id)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn thread_pool_contains_no_elements_shutdown_returns_empty_vec() {
+        let (_send_to_thread, receive_from_caller) = unbounded::<SenderCouplet<Randoms>>();
+
+        // request/response channel
+        let (_send_back, _receive_back_from) = bounded::<ThreadRequestResponse<Randoms>>(0);
+
+        let mut target = PoolThread::<Randoms>::new(1, receive_from_caller);
+
+        let result = target.shutdown_pool_items(&mut None);
+
+        assert!(target.pool_item_map.is_empty());
+        assert!(result.is_empty());
+    }
+}
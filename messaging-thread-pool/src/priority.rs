@@ -0,0 +1,49 @@
+/// The priority lane that a request is sent on.
+///
+/// Each thread in a [`ThreadPool`](crate::ThreadPool) has one channel per priority.
+/// A pool thread always drains its higher priority lanes before looking at its lower priority lanes,
+/// so an urgent request does not have to wait behind a long batch of normal requests.
+///
+/// Requests that target the same pool item are processed in the order they were sent **within a lane**.
+/// There is no ordering guarantee between lanes; a high priority request can overtake a normal
+/// priority request (including the request that added the pool item) sent before it.
+///
+/// The priority of a request is taken from [`RequestWithResponse::PRIORITY`](crate::RequestWithResponse::PRIORITY)
+/// unless it is overridden for a call with
+/// [`send_and_receive_with_priority`](crate::ThreadPool::send_and_receive_with_priority).
+/// Requests that shutdown or abort a thread are always sent on the high priority lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// The number of priority lanes each pool thread has
+    pub(crate) const COUNT: usize = 3;
+
+    /// Returns the index of the lane for this priority; lane 0 is the highest priority
+    pub(crate) fn lane(self) -> usize {
+        self as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Priority;
+
+    #[test]
+    fn lanes_ordered_highest_priority_first() {
+        assert_eq!(0, Priority::High.lane());
+        assert_eq!(1, Priority::Normal.lane());
+        assert_eq!(2, Priority::Low.lane());
+        assert!(Priority::High < Priority::Low);
+    }
+
+    #[test]
+    fn default_priority_is_normal() {
+        assert_eq!(Priority::Normal, Priority::default());
+    }
+}
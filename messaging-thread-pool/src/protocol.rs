@@ -0,0 +1,73 @@
+use std::fmt::Debug;
+
+use crate::{id_targeted::IdTargeted, pool_item::PoolItem, pool_key::PoolKey};
+
+/// A set of requests that more than one type of pool item can process.
+///
+/// Protocols are generated by the `#[pool_protocol]` attribute on a trait. The requests and
+/// responses of the protocol are defined once, generic over the pool item's key, and any pool item
+/// that lists the protocol with `#[pool_item(Implements = Trait)]` accepts them.
+/// Generic code can then send the protocol's requests to any pool that [`Implements`] it.
+///
+/// # Example
+///
+/// ```rust
+/// use messaging_thread_pool::{Implements, SenderAndReceiver, ThreadPool, pool_item, pool_protocol};
+///
+/// #[pool_protocol]
+/// pub trait Health {
+///     #[messaging(HealthRequest, HealthResponse)]
+///     fn health(&self) -> String;
+/// }
+///
+/// #[derive(Debug)]
+/// pub struct Disk;
+///
+/// impl Health for Disk {
+///     fn health(&self) -> String {
+///         "ok".to_string()
+///     }
+/// }
+///
+/// #[pool_item(Implements = Health)]
+/// impl Disk {
+///     pub fn new(_id: u64) -> Self {
+///         Self
+///     }
+/// }
+///
+/// // works with the pool of any pool item that implements the protocol
+/// fn health<P, S>(pool: &S, id: P::Key) -> String
+/// where
+///     P: Implements<HealthProtocol>,
+///     S: SenderAndReceiver<P>,
+/// {
+///     pool.send_and_receive_one(HealthRequest(id))
+///         .expect("pool to be available")
+///         .result
+/// }
+///
+/// let pool = ThreadPool::<Disk>::new(1);
+/// pool.send_and_receive_once(DiskInit(1)).expect("pool to be available");
+/// assert_eq!("ok", health(&pool, 1));
+/// ```
+pub trait Protocol {
+    /// The messages of the protocol (each of its requests and responses) for pool items
+    /// identified by keys of type `K`
+    type Api<K: PoolKey>: Debug + Send + IdTargeted<K>;
+}
+
+/// Implemented by pool items whose [`Api`](PoolItem::Api) includes the messages of the protocol `T`
+///
+/// The `#[pool_item(Implements = Trait)]` attribute implements this; it adds a variant holding
+/// the protocol's messages to the pool item's API.
+pub trait Implements<T>: PoolItem
+where
+    T: Protocol,
+{
+    /// Wraps a message of the protocol in the pool item's API
+    fn into_api(message: T::Api<Self::Key>) -> Self::Api;
+
+    /// Returns the message of the protocol held by the pool item's API, if it holds one
+    fn from_api(api: Self::Api) -> Option<T::Api<Self::Key>>;
+}
@@ -38,7 +38,9 @@ mod tests {
 
         let requests = (0..3u64).map(|id| ThreadEchoRequest::new(id, format!("ping {id}")));
 
-        target.send(send_to_pool, requests).unwrap();
+        target
+            .send(send_to_pool, requests, None, None, None)
+            .unwrap();
 
         let results: Vec<ThreadEchoResponse> = target
             .receive::<ThreadEchoRequest>(receive_from_thread)
@@ -59,7 +61,9 @@ mod tests {
 
         let requests: Vec<_> = (0..1).map(RandomsAddRequest).collect();
 
-        target.send(send_to_pool, requests.into_iter()).unwrap();
+        target
+            .send(send_to_pool, requests.into_iter(), None, None, None)
+            .unwrap();
 
         let result: Vec<AddResponse> = target
             .receive::<RandomsAddRequest>(receive_from_thread)
@@ -1,20 +1,29 @@
 use crate::{
-    id_targeted::IdTargeted, pool_item::PoolItem, request_with_response::RequestWithResponse,
+    id_targeted::IdTargeted, pool_item::PoolItem, pool_key::PoolKey,
+    request_with_response::RequestWithResponse,
 };
 
 use super::RequestResponse;
 
-impl<P, T> IdTargeted for RequestResponse<P, T>
+impl<P, T, K> IdTargeted<K> for RequestResponse<P, T>
 where
-    T: RequestWithResponse<P> + IdTargeted,
+    T: RequestWithResponse<P> + IdTargeted<K>,
     P: PoolItem,
+    K: PoolKey,
 {
-    fn id(&self) -> u64 {
+    fn id(&self) -> K {
         let RequestResponse::Request(request) = self else {
             panic!("not expected; only requests are required to support IdTargeted");
         };
         request.id()
     }
+
+    fn routing_key(&self) -> u64 {
+        let RequestResponse::Request(request) = self else {
+            panic!("not expected; only requests are required to support IdTargeted");
+        };
+        request.routing_key()
+    }
 }
 
 #[cfg(test)]
@@ -22,6 +31,7 @@ mod tests {
     use crate::{
         id_targeted::IdTargeted,
         request_response::RequestResponse,
+        routed::Routed,
         samples::{Randoms, RandomsAddRequest},
         thread_request_response::AddResponse,
     };
@@ -48,4 +58,12 @@ mod tests {
 
         assert_eq!(0, target.id());
     }
+
+    #[test]
+    fn request_response_contains_routed_request_returns_its_routing_key() {
+        let target = RequestResponse::<Randoms, _>::Request(Routed::new(7, RandomsAddRequest(1)));
+
+        assert_eq!(1, target.id());
+        assert_eq!(7, target.routing_key());
+    }
 }
@@ -1,6 +1,8 @@
 use std::fmt::Debug;
 
-use crate::{pool_item::PoolItem, thread_request_response::ThreadRequestResponse};
+use crate::{
+    pool_item::PoolItem, priority::Priority, thread_request_response::ThreadRequestResponse,
+};
 
 /// This trait allows for the pairing of requests and responses.
 ///
@@ -13,4 +15,7 @@ where
     Self::Response: Debug + From<ThreadRequestResponse<P>> + Into<ThreadRequestResponse<P>>,
 {
     type Response;
+
+    /// The priority lane that requests of this type are sent on by default
+    const PRIORITY: Priority = Priority::Normal;
 }
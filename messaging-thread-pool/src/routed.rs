@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+
+use crate::{
+    id_targeted::IdTargeted, pool_item::PoolItem, pool_key::PoolKey, priority::Priority,
+    request_with_response::RequestWithResponse, thread_request_response::ThreadRequestResponse,
+};
+
+/// Wraps a request so that it is sent to the thread chosen by an explicit routing key rather than
+/// by the id of the pool item it targets.
+///
+/// This allows pool items that need to share a thread (for example a user and all of their sessions)
+/// to be co-located. The wrapped request still targets the pool item with its own id.
+/// Any request can be wrapped, including the request that creates the pool item and
+/// [`RemovePoolItemRequest`](crate::RemovePoolItemRequest); all of the requests for a given pool
+/// item must be sent with the same routing key.
+///
+/// The response is the response of the wrapped request.
+///
+/// # Example
+///
+/// ```rust
+/// use messaging_thread_pool::{Routed, ThreadPool, samples::*};
+///
+/// let pool = ThreadPool::<Randoms>::new(2);
+///
+/// // pool item 1 lives on the thread chosen by routing key 0 rather than on the thread chosen by its id
+/// pool.send_and_receive_once(Routed::new(0, RandomsAddRequest(1))).unwrap();
+/// let response = pool.send_and_receive_once(Routed::new(0, MeanRequest(1))).unwrap();
+/// assert!(response.mean() > 0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Routed<T> {
+    routing_key: u64,
+    request: T,
+}
+
+impl<T> Routed<T> {
+    /// Wraps the request so that it is routed by the given key
+    pub fn new(routing_key: u64, request: T) -> Self {
+        Self {
+            routing_key,
+            request,
+        }
+    }
+
+    /// Returns the wrapped request
+    pub fn into_inner(self) -> T {
+        self.request
+    }
+}
+
+impl<T, K> IdTargeted<K> for Routed<T>
+where
+    T: IdTargeted<K>,
+    K: PoolKey,
+{
+    fn id(&self) -> K {
+        self.request.id()
+    }
+
+    fn routing_key(&self) -> u64 {
+        self.routing_key
+    }
+}
+
+impl<P, T> RequestWithResponse<P> for Routed<T>
+where
+    P: PoolItem,
+    T: RequestWithResponse<P>,
+    T::Response: Debug + From<ThreadRequestResponse<P>> + Into<ThreadRequestResponse<P>>,
+{
+    type Response = T::Response;
+    const PRIORITY: Priority = T::PRIORITY;
+}
+
+impl<P, T> From<Routed<T>> for ThreadRequestResponse<P>
+where
+    P: PoolItem,
+    T: Into<ThreadRequestResponse<P>>,
+{
+    fn from(routed: Routed<T>) -> Self {
+        routed.request.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{IdTargeted, ThreadPool, samples::*, thread_request_response::*};
+
+    use super::Routed;
+
+    #[test]
+    fn routed_request_keeps_id_of_wrapped_request() {
+        let target = Routed::new(7, SumRequest(3));
+
+        assert_eq!(3, target.id());
+        assert_eq!(7, target.routing_key());
+        assert_eq!(SumRequest(3), target.into_inner());
+    }
+
+    #[test]
+    fn routed_pool_items_are_co_located_on_the_same_thread() {
+        let target = ThreadPool::<Randoms>::new(2);
+
+        // ids 1 and 3 would normally be on thread 1
+        target
+            .send_and_receive(
+                [
+                    Routed::new(0, RandomsAddRequest(1)),
+                    Routed::new(0, RandomsAddRequest(2)),
+                    Routed::new(0, RandomsAddRequest(3)),
+                ]
+                .into_iter(),
+            )
+            .unwrap()
+            .for_each(|response| assert!(response.result().is_ok()));
+
+        let response = target
+            .send_and_receive_once(Routed::new(0, SumRequest(3)))
+            .unwrap();
+        assert_eq!(3, response.id);
+
+        // item 3 is not on the thread that its id routes to
+        let removed = target
+            .send_and_receive_once(RemovePoolItemRequest(3))
+            .unwrap();
+        assert!(!removed.item_existed());
+        let removed = target
+            .send_and_receive_once(Routed::new(0, RemovePoolItemRequest(3)))
+            .unwrap();
+        assert!(removed.item_existed());
+    }
+}
@@ -7,7 +7,7 @@ use crate as messaging_thread_pool;
 ///
 /// This sample demonstrates the most basic `#[pool_item]` usage with:
 /// - Mutable state (the `history` vector)
-/// - Multiple message types (`post`, `get_history`)
+/// - Multiple message types (`post`, `get_history`, `stream_history`)
 /// - Minimal boilerplate
 ///
 /// # Generated Types
@@ -19,6 +19,8 @@ use crate as messaging_thread_pool;
 /// - `PostResponse { id, result }` - Response with message index
 /// - `GetHistoryRequest(u64)` - Request message history
 /// - `GetHistoryResponse { id, result }` - Response with history vector
+/// - `StreamHistoryRequest(u64)` - Request message history as a stream
+/// - `StreamHistoryResponse { id, result }` - One chunk of the streamed history
 ///
 /// # Example
 ///
@@ -81,4 +83,12 @@ impl ChatRoom {
     pub fn get_history(&self) -> Vec<String> {
         self.history.clone()
     }
+
+    /// Stream the message history one message at a time.
+    ///
+    /// Each message is sent back as a separate response, followed by an end-of-stream marker.
+    #[messaging(StreamHistoryRequest, StreamHistoryResponse, stream)]
+    pub fn stream_history(&self) -> impl Iterator<Item = String> + '_ {
+        self.history.iter().cloned()
+    }
 }
@@ -32,7 +32,7 @@ use super::{RandomsAddRequest, SumRequest, SumResponse};
 /// ```
 pub trait InnerThreadPool: Debug + Send {
     /// The concrete thread pool type that implements `SenderAndReceiver<Randoms>`
-    type ThreadPool: SenderAndReceiver<Randoms> + Send + Sync + Debug;
+    type ThreadPool: SenderAndReceiver<Randoms> + ChildPool + Send + Sync + Debug + 'static;
 }
 
 /// Marker type for using a real `ThreadPool<Randoms>` as the inner pool.
@@ -43,7 +43,7 @@ impl InnerThreadPool for RandomsThreadPool {
 }
 
 /// Implement `InnerThreadPool` for mock types to enable testing.
-impl<T: RequestWithResponse<Randoms> + Send + Sync> InnerThreadPool
+impl<T: RequestWithResponse<Randoms> + Send + Sync + 'static> InnerThreadPool
     for SenderAndReceiverMock<Randoms, T>
 where
     <T as request_with_response::RequestWithResponse<Randoms>>::Response: Send,
@@ -109,6 +109,8 @@ impl<P: InnerThreadPool> From<RandomsBatchAddRequest<P>>
 /// - **Custom Init type**: Uses `RandomsBatchAddRequest<P>` instead of generated init
 /// - **Nested pools**: Each batch references an inner pool of `Randoms`
 /// - **Shared resources**: Multiple batches share the same inner pool and ID provider
+/// - **Shutdown lifecycle**: Each batch releases the inner pool when the outer pool shuts down; it
+///   is shut down once the last reference to it has gone
 ///
 /// # Architecture
 ///
@@ -170,7 +172,10 @@ pub struct RandomsBatch<P: InnerThreadPool> {
     pub randoms_thread_pool: Arc<P::ThreadPool>,
 }
 
-#[pool_item(Init = "RandomsBatchAddRequest<P>")]
+#[pool_item(
+    Init = "RandomsBatchAddRequest<P>",
+    OnShutdown = "release_randoms_thread_pool"
+)]
 impl<P: InnerThreadPool> RandomsBatch<P> {
     pub fn new(add_request: RandomsBatchAddRequest<P>) -> Self {
         let mut new = Self {
@@ -200,6 +205,12 @@ impl<P: InnerThreadPool> RandomsBatch<P> {
         self.randoms_thread_pool.as_ref()
     }
 
+    /// Hands the shared inner pool to the shutdown context; whichever batch's thread releases the
+    /// last reference to it shuts it down
+    pub fn release_randoms_thread_pool(&mut self, context: &mut ShutdownContext<'_, Self>) {
+        context.release_child_pool(Arc::clone(&self.randoms_thread_pool));
+    }
+
     #[messaging(SumOfSumsRequest, SumOfSumsResponse)]
     pub fn sum_of_sums(&self) -> u128 {
         // to get the sum of sums need to message the controls Randoms to get their sums
@@ -0,0 +1,42 @@
+/// How a pool thread chooses which of its queued messages to process next.
+///
+/// By default ([`Scheduling::Fifo`]) a thread processes the messages it receives strictly in the
+/// order they arrive, so one chatty pool item can keep every other pool item on its thread waiting.
+///
+/// With [`Scheduling::Fair`] the thread splits the messages it has received into a mailbox per pool
+/// item and takes turns between the pool items that have messages waiting. Each turn processes at
+/// most `budget` messages from one mailbox. Messages for the same pool item are still processed in
+/// the order they were received.
+///
+/// Each [`Priority`](crate::Priority) lane has its own mailboxes, and the turns of a lane are only
+/// taken once every higher priority lane's mailboxes are empty, so a low priority pool item never
+/// takes a turn ahead of a high priority one.
+///
+/// Messages that target the thread itself (such as an abort request) are not put in a mailbox. They
+/// are processed once the messages received before them on their own lane, and the mailboxes of
+/// every higher priority lane, have been processed, ahead of the mailboxes of lower priority lanes.
+/// A shutdown request still waits for every mailbox, as the thread processes everything that it
+/// has received before it shuts down.
+///
+/// The scheduling of a pool item is taken from [`PoolItem::scheduling`](crate::PoolItem::scheduling).
+/// [`InlinePool`](crate::InlinePool) and [`SimulatedPool`](crate::simulation::SimulatedPool) process
+/// each message as it is sent and so are unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scheduling {
+    #[default]
+    Fifo,
+    Fair {
+        /// The maximum number of messages processed for one pool item before moving on to the next
+        budget: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scheduling;
+
+    #[test]
+    fn default_scheduling_is_fifo() {
+        assert_eq!(Scheduling::Fifo, Scheduling::default());
+    }
+}
@@ -2,7 +2,8 @@ use crossbeam_channel::{SendError, Sender};
 use tracing::{Level, event, instrument};
 
 use crate::{
-    ThreadPool, id_targeted::IdTargeted, pool_item::PoolItem,
+    ThreadPool, cancellation_token::CancellationToken, deadlock::WaitingOn,
+    id_targeted::IdTargeted, pool_error::PoolError, pool_item::PoolItem, priority::Priority,
     request_with_response::RequestWithResponse, sender_couplet::SenderCouplet,
     thread_request_response::ThreadRequestResponse,
 };
@@ -18,15 +19,32 @@ where
     /// The work is distributed within the thread pool and returned as a vec of responses
     ///
     /// The work will be distributed based on the mod of the id of the requests target
-    #[instrument(skip(self, send_back_to, requests), fields(name=P::name()))]
+    ///
+    /// The requests are sent on the lane for the passed in priority, or on the lane for the request type's
+    /// [`RequestWithResponse::PRIORITY`] if no priority is passed
+    ///
+    /// If a cancellation token is passed it is attached to every request
+    ///
+    /// If `waiting_on` is passed each targeted thread is recorded in it before the request is sent;
+    /// sending stops with [`PoolError::Deadlock`] if waiting on that thread would deadlock the
+    /// calling pool thread
+    #[instrument(
+        skip(self, send_back_to, requests, cancellation_token, waiting_on),
+        fields(name=P::name())
+    )]
     pub(super) fn send<T>(
         &self,
         send_back_to: Sender<ThreadRequestResponse<P>>,
         requests: impl Iterator<Item = T>,
-    ) -> Result<usize, SendError<SenderCouplet<P>>>
+        priority: Option<Priority>,
+        cancellation_token: Option<&CancellationToken>,
+        mut waiting_on: Option<&mut WaitingOn>,
+    ) -> Result<usize, PoolError<P>>
     where
-        T: RequestWithResponse<P> + IdTargeted,
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
     {
+        let priority = priority.unwrap_or(T::PRIORITY);
+
         let thread_count = self
             .thread_endpoints
             .read()
@@ -37,18 +55,33 @@ where
 
         let mut request_count = 0;
         for request in requests {
-            // route to correct thread; share the load based on id and the mod of the thread count
-            let targeted = P::id_thread_router(request.id(), thread_count);
+            if guard.is_empty() {
+                // the pool has been shut down
+                return Err(SendError(SenderCouplet::new(send_back_to, request)).into());
+            }
+            // route to correct thread; share the load based on the routing key (usually the id)
+            // and the mod of the thread count
+            let targeted = P::id_thread_router(request.routing_key(), thread_count);
             event!(
                 Level::DEBUG,
-                "Sending to target=[{}-{}], id=[{}], message type=[{}]",
+                "Sending to target=[{}-{}], id=[{:?}], routing key=[{}], message type=[{}], priority=[{:?}]",
                 P::name(),
                 targeted,
                 request.id(),
-                std::any::type_name::<T>()
+                request.routing_key(),
+                std::any::type_name::<T>(),
+                priority
             );
             event!(Level::TRACE, ?request);
-            guard[targeted as usize].send(&send_back_to.clone(), request)?;
+            if let Some(waiting_on) = waiting_on.as_deref_mut() {
+                waiting_on.wait_on(guard[targeted as usize].thread())?;
+            }
+            guard[targeted as usize].send(
+                &send_back_to.clone(),
+                request,
+                priority,
+                cancellation_token,
+            )?;
             request_count += 1;
         }
 
@@ -70,7 +103,9 @@ mod tests {
 
         let requests = (0..2u64).map(|i| ThreadEchoRequest::new(i, "ping".to_string()));
 
-        target.send(send_back_to, requests).unwrap();
+        target
+            .send(send_back_to, requests, None, None, None)
+            .unwrap();
 
         let mut responses = Vec::<ThreadEchoResponse>::new();
 
@@ -91,7 +126,9 @@ mod tests {
 
         let requests = (0..2u64).map(|i| ThreadEchoRequest::new(i, "ping2".to_string()));
 
-        target.send(send_back_to, requests).unwrap();
+        target
+            .send(send_back_to, requests, None, None, None)
+            .unwrap();
 
         let mut responses = Vec::<ThreadEchoResponse>::new();
 
@@ -111,7 +148,9 @@ mod tests {
 
         let requests = (0..1u64).map(|i| ThreadEchoRequest::new(i, "ping".to_string()));
 
-        target.send(send_back_to, requests).unwrap();
+        target
+            .send(send_back_to, requests, None, None, None)
+            .unwrap();
 
         let thread_echo_response: ThreadEchoResponse = receive_from_thread.recv().unwrap().into();
 
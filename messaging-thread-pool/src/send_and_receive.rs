@@ -1,9 +1,16 @@
-use crossbeam_channel::{SendError, unbounded};
+use crossbeam_channel::unbounded;
 use tracing::instrument;
 
 use crate::{
-    ThreadPool, id_targeted::IdTargeted, pool_item::PoolItem,
-    request_with_response::RequestWithResponse, sender_couplet::SenderCouplet,
+    ThreadPool,
+    cancellation_token::{CancelOnDrop, CancellationToken},
+    deadlock::WaitingOn,
+    id_targeted::IdTargeted,
+    pool_error::PoolError,
+    pool_item::PoolItem,
+    priority::Priority,
+    request_with_response::RequestWithResponse,
+    stream_response::StreamResponse,
     thread_request_response::ThreadRequestResponse,
 };
 
@@ -14,40 +21,273 @@ where
     /// This function sends a request to a worker thread and receives a response back
     ///
     /// The request is received as an iterator and the responses are received back as an iterator
+    ///
+    /// If the returned iterator is dropped before all of the responses have been received, any requests
+    /// that have not yet been started are discarded by the threads without being processed; use
+    /// [`send_and_receive_detached`](Self::send_and_receive_detached) to have them processed regardless
+    ///
+    /// When called from a pool thread (that is, from within a pool item) the call fails with
+    /// [`PoolError::Deadlock`] if a request targets the calling thread, or a thread that is itself
+    /// (directly or indirectly) waiting on the calling thread, as its response could never be received
     #[instrument(skip(self, requests))]
     pub fn send_and_receive<T>(
         &self,
         requests: impl Iterator<Item = T>,
-    ) -> Result<impl Iterator<Item = T::Response>, SendError<SenderCouplet<P>>>
+    ) -> Result<impl Iterator<Item = T::Response>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
+    {
+        self.send_and_receive_batch(requests, None, CancellationToken::new())
+    }
+
+    /// This function is the same as [`send_and_receive`](Self::send_and_receive) except that
+    /// the batch of requests is tied to the passed in [`CancellationToken`]
+    ///
+    /// Cancelling the token causes the threads to discard any requests in the batch that have not yet
+    /// been started; no responses are returned for the discarded requests.
+    /// The token is also cancelled when the returned iterator is dropped.
+    #[instrument(skip(self, requests, cancellation_token))]
+    pub fn send_and_receive_with_token<T>(
+        &self,
+        requests: impl Iterator<Item = T>,
+        cancellation_token: CancellationToken,
+    ) -> Result<impl Iterator<Item = T::Response>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
+    {
+        self.send_and_receive_batch(requests, None, cancellation_token)
+    }
+
+    /// This function is the same as [`send_and_receive`](Self::send_and_receive) except that
+    /// all of the requests are sent on the lane for the given priority, overriding the request type's
+    /// [`RequestWithResponse::PRIORITY`]
+    ///
+    /// Higher priority lanes are drained by each thread before lower priority lanes.
+    /// Requests targeting the same id are only guaranteed to be processed in order if they are sent
+    /// on the same lane.
+    #[instrument(skip(self, requests))]
+    pub fn send_and_receive_with_priority<T>(
+        &self,
+        requests: impl Iterator<Item = T>,
+        priority: Priority,
+    ) -> Result<impl Iterator<Item = T::Response>, PoolError<P>>
     where
-        T: RequestWithResponse<P> + IdTargeted,
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
+    {
+        self.send_and_receive_batch(requests, Some(priority), CancellationToken::new())
+    }
+
+    /// This function is the same as [`send_and_receive`](Self::send_and_receive) except that
+    /// the batch is not cancelled when the returned iterator is dropped
+    ///
+    /// Every request is processed whether or not its response is received, so the iterator can be
+    /// dropped straight away to "fire and forget" the requests.
+    #[instrument(skip(self, requests))]
+    pub fn send_and_receive_detached<T>(
+        &self,
+        requests: impl Iterator<Item = T>,
+    ) -> Result<impl Iterator<Item = T::Response>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
     {
         let (return_back_to, receive_from_worker) = unbounded::<ThreadRequestResponse<P>>();
-        self.send(return_back_to, requests)?;
-        Ok(self.receive::<T>(receive_from_worker))
+        let mut waiting_on = WaitingOn::new();
+        self.send(return_back_to, requests, None, None, Some(&mut waiting_on))?;
+        Ok(waiting_on.hold(self.receive::<T>(receive_from_worker)))
     }
 
+    /// This function sends a request to a streaming messaging method and receives the items it produces
+    ///
+    /// The items are received as they are produced by the pool item; the iterator ends when the
+    /// end-of-stream marker is received. Dropping the iterator early cancels the request.
     #[instrument(skip(self, request))]
-    pub fn send_and_receive_once<T>(
+    pub fn send_and_receive_stream<T>(
         &self,
         request: T,
-    ) -> Result<T::Response, SendError<SenderCouplet<P>>>
+    ) -> Result<impl Iterator<Item = <T::Response as StreamResponse>::Item>, PoolError<P>>
     where
-        T: RequestWithResponse<P> + IdTargeted,
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
+        T::Response: StreamResponse,
     {
-        let mut responses = self.send_and_receive(std::iter::once(request))?;
-        if let Some(response) = responses.next()
-            && responses.next().is_none()
-        {
-            return Ok(response);
+        let responses = self.send_and_receive(std::iter::once(request))?;
+        Ok(responses.map_while(|response| response.into_chunk().into_item()))
+    }
+
+    fn send_and_receive_batch<T>(
+        &self,
+        requests: impl Iterator<Item = T>,
+        priority: Option<Priority>,
+        cancellation_token: CancellationToken,
+    ) -> Result<impl Iterator<Item = T::Response>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
+    {
+        let (return_back_to, receive_from_worker) = unbounded::<ThreadRequestResponse<P>>();
+        let mut waiting_on = WaitingOn::new();
+        if let Err(err) = self.send(
+            return_back_to,
+            requests,
+            priority,
+            Some(&cancellation_token),
+            Some(&mut waiting_on),
+        ) {
+            // nobody will receive the responses to the part of the batch that was sent
+            cancellation_token.cancel();
+            return Err(err);
         }
-        panic!("too many responses");
+        Ok(waiting_on.hold(CancelOnDrop::new(
+            self.receive::<T>(receive_from_worker),
+            cancellation_token,
+        )))
+    }
+
+    /// This function is the same as [`send_and_receive`](Self::send_and_receive) except that
+    /// the requests are messages of the pool item's API, so one batch can mix requests of different types
+    ///
+    /// Each request can be given as the pool item's API or as anything that converts into it (such as the
+    /// request structs generated by `#[pool_item]`). The responses are of the API's response type;
+    /// for a `#[pool_item]` this is the generated `{Item}Response` enum with a variant per response type.
+    ///
+    /// All of the requests are sent on the [`Priority::Normal`] lane, whatever the priority of their
+    /// request type; use [`send_and_receive_with_priority`](Self::send_and_receive_with_priority)
+    /// to send the batch on another lane.
+    #[instrument(skip(self, requests))]
+    pub fn send_and_receive_api<T>(
+        &self,
+        requests: impl Iterator<Item = T>,
+    ) -> Result<impl Iterator<Item = <P::Api as RequestWithResponse<P>>::Response>, PoolError<P>>
+    where
+        T: Into<P::Api>,
+        P::Api: RequestWithResponse<P>,
+    {
+        self.send_and_receive(requests.map(Into::into))
+    }
+
+    /// Sends a single request and waits for its response.
+    ///
+    /// Returns [`PoolError::NoResponse`] if the request is not answered, which happens when the
+    /// pool item panics processing it and [`PoolItem::on_message_error`] handles the panic.
+    #[instrument(skip(self, request))]
+    pub fn send_and_receive_once<T>(&self, request: T) -> Result<T::Response, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key>,
+    {
+        let id = request.id();
+        let mut responses = self.send_and_receive(std::iter::once(request))?;
+        let Some(response) = responses.next() else {
+            return Err(PoolError::NoResponse(id));
+        };
+        assert!(responses.next().is_none(), "too many responses");
+        Ok(response)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ThreadPool, samples::*, thread_request_response::*};
+    use crate::{
+        CancellationToken, MessageError, PoolError, Priority, ThreadPool, pool_item, samples::*,
+        thread_request_response::*,
+    };
+
+    /// A pool item that panics on request and survives the panic
+    #[derive(Debug)]
+    pub struct Fragile {
+        id: u64,
+    }
+
+    #[pool_item(MessageError = "failed")]
+    impl Fragile {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+
+        pub fn failed(&mut self, _error: MessageError<u64>) {
+            // the panic is handled; the pool item carries on
+        }
+
+        #[messaging(BreakRequest, BreakResponse)]
+        pub fn break_now(&self) -> u64 {
+            panic!("{} broke", self.id);
+        }
+    }
+
+    #[test]
+    fn cancelled_token_requests_discarded_no_responses_received() {
+        let target = ThreadPool::<Randoms>::new(2);
+
+        let cancellation_token = CancellationToken::new();
+        cancellation_token.cancel();
+
+        let results: Vec<AddResponse> = target
+            .send_and_receive_with_token((0..4u64).map(RandomsAddRequest), cancellation_token)
+            .unwrap()
+            .collect();
+
+        assert!(results.is_empty());
+
+        // none of the adds were processed so the items do not exist
+        let results: Vec<RemovePoolItemResponse> = target
+            .send_and_receive((0..4u64).map(RemovePoolItemRequest))
+            .unwrap()
+            .collect();
+        assert!(results.iter().all(|r| !r.item_existed()));
+    }
+
+    #[test]
+    fn detached_requests_processed_after_response_iterator_dropped() {
+        let target = ThreadPool::<Randoms>::new(2);
+
+        drop(
+            target
+                .send_and_receive_detached((0..4u64).map(RandomsAddRequest))
+                .unwrap(),
+        );
+
+        // the removes are queued behind the adds on the same lanes
+        let results: Vec<RemovePoolItemResponse> = target
+            .send_and_receive((0..4u64).map(RemovePoolItemRequest))
+            .unwrap()
+            .collect();
+        assert_eq!(4, results.len());
+        assert!(results.iter().all(|r| r.item_existed()));
+    }
+
+    #[test]
+    fn dropping_response_iterator_cancels_token() {
+        let target = ThreadPool::<Randoms>::new(2);
+
+        let cancellation_token = CancellationToken::new();
+        let mut responses = target
+            .send_and_receive_with_token(
+                (0..4u64).map(|i| ThreadEchoRequest::new(i, "ping".to_string())),
+                cancellation_token.clone(),
+            )
+            .unwrap();
+
+        let _first: ThreadEchoResponse = responses.next().unwrap();
+        assert!(!cancellation_token.is_cancelled());
+
+        drop(responses);
+
+        assert!(cancellation_token.is_cancelled());
+    }
+
+    #[test]
+    fn two_threads_echoes_sent_on_every_lane_receives_expected_responses() {
+        let target = ThreadPool::<Randoms>::new(2);
+
+        for priority in [Priority::High, Priority::Normal, Priority::Low] {
+            let requests = (0..2u64).map(|i| ThreadEchoRequest::new(i, format!("{priority:?}")));
+
+            let results: Vec<ThreadEchoResponse> = target
+                .send_and_receive_with_priority(requests, priority)
+                .unwrap()
+                .collect();
+
+            assert!(results.contains(&ThreadEchoResponse::new(0, format!("{priority:?}"), 0)));
+            assert!(results.contains(&ThreadEchoResponse::new(1, format!("{priority:?}"), 1)));
+        }
+    }
 
     #[test]
     fn two_threads_three_echoes_receives_expected_response() {
@@ -75,4 +315,22 @@ mod tests {
         assert_eq!(result.len(), 1);
         assert_eq!(0, result[0].id());
     }
+
+    #[test]
+    fn request_left_unanswered_by_a_handled_panic_returns_no_response() {
+        let target = ThreadPool::<Fragile>::new(1);
+        target
+            .send_and_receive_once(FragileInit(1))
+            .expect("thread pool to be available");
+
+        let result = target.send_and_receive_once(BreakRequest(1));
+
+        assert!(matches!(result, Err(PoolError::NoResponse(1))));
+        assert!(result.unwrap_err().to_string().ends_with("Fragile id 1"));
+        // the pool item is still there
+        assert!(matches!(
+            target.send_and_receive_once(BreakRequest(1)),
+            Err(PoolError::NoResponse(1))
+        ));
+    }
 }
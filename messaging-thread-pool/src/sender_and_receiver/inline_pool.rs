@@ -0,0 +1,502 @@
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::{self, ThreadId};
+
+use crate::pool_error::PoolError;
+use crossbeam_channel::{SendError, never, unbounded};
+
+use crate::{
+    deadlock::{DeadlockError, thread_name},
+    id_targeted::IdTargeted,
+    pool_item::PoolItem,
+    pool_thread::PoolThread,
+    request_with_response::RequestWithResponse,
+    sender_couplet::SenderCouplet,
+    thread_request_response::*,
+};
+
+use super::{SenderAndReceiver, ThreadSafeSenderAndReceiver};
+
+/// A single threaded implementation of [`SenderAndReceiver`] that processes all requests
+/// synchronously on the calling thread.
+///
+/// Unlike the mocks, an `InlinePool` hosts real pool items. Requests are processed with exactly
+/// the same semantics as a thread within a [`ThreadPool`](crate::ThreadPool); `new_pool_item`,
+/// `process_message`, removal, `id_not_found`, the lifecycle hooks and the pre/post process hooks are all called in
+/// the same way. The difference is that no threads are spawned, so tests that exercise the
+/// real pool item logic are fast and deterministic.
+///
+/// All pool items live on a single (virtual) thread with an id of 0. Dropping the pool shuts it
+/// down (calling `on_shutdown`, `on_removed` and `thread_stop`) unless [`shutdown`](Self::shutdown)
+/// has already been called, as dropping a [`ThreadPool`](crate::ThreadPool) does.
+///
+/// # Example
+///
+/// ```rust
+/// use messaging_thread_pool::{InlinePool, SenderAndReceiver, samples::*};
+///
+/// let pool = InlinePool::<ChatRoom>::new();
+///
+/// pool.send_and_receive_one(ChatRoomInit(1)).expect("pool available");
+/// pool.send_and_receive_one(PostRequest(1, "Alice".into(), "Hello!".into()))
+///     .expect("pool available");
+///
+/// let history = pool.send_and_receive_one(GetHistoryRequest(1)).expect("pool available");
+/// assert_eq!(history.result, vec!["Alice: Hello!"]);
+/// ```
+///
+/// # Note
+///
+/// Requests are processed whilst holding an internal lock. A request sent by a pool item back into
+/// the same `InlinePool` that is hosting it fails with [`PoolError::Deadlock`] rather than waiting
+/// for the lock; nested pools should each use their own `InlinePool`.
+///
+/// A pool item that panics unwinds out of the call that sent the request, as it would on the
+/// calling thread; the pool remains usable afterwards.
+///
+/// The pool's [thread resources](crate::with_thread_resource) belong to the pool, but as they need
+/// not be `Send` they can only be borrowed on the thread that created them. If the pool is dropped
+/// on another thread they are dropped when the thread that created them ends.
+pub struct InlinePool<P>
+where
+    P: PoolItem,
+{
+    state: Mutex<InlinePoolState<P>>,
+    /// The thread currently processing requests, used to detect re-entrant calls
+    processing_on: Mutex<Option<ThreadId>>,
+}
+
+struct InlinePoolState<P>
+where
+    P: PoolItem,
+{
+    pool_thread: PoolThread<P>,
+    thread_start_info: Option<P::ThreadStartInfo>,
+    is_shutdown: bool,
+}
+
+impl<P> InlinePool<P>
+where
+    P: PoolItem,
+{
+    /// Creates a new, empty, `InlinePool`
+    ///
+    /// [`PoolItem::thread_start`] is called immediately on the calling thread.
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(InlinePoolState {
+                // requests are passed directly to the pool thread so it never needs to receive
+                pool_thread: PoolThread::new(0, never()),
+                thread_start_info: P::thread_start(),
+                is_shutdown: false,
+            }),
+            processing_on: Mutex::new(None),
+        }
+    }
+
+    /// Locks the pool's state for the current thread
+    ///
+    /// Fails if the current thread is already processing a request for this pool (as the lock is not
+    /// re-entrant). A pool item that panicked whilst the lock was held does not prevent the pool
+    /// from being used again.
+    fn lock(&self) -> Result<StateGuard<'_, P>, DeadlockError> {
+        let current = thread::current();
+        if *self
+            .processing_on
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            == Some(current.id())
+        {
+            return Err(DeadlockError::SameThread {
+                thread: thread_name(&current),
+            });
+        }
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        *self
+            .processing_on
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(current.id());
+        Ok(StateGuard {
+            state,
+            processing_on: &self.processing_on,
+        })
+    }
+
+    /// Returns the number of pool items currently hosted by the pool
+    ///
+    /// # Panics
+    ///
+    /// Panics if called by a pool item hosted by this pool
+    pub fn pool_item_count(&self) -> usize {
+        self.lock()
+            .expect("not to be called from a pool item hosted by this pool")
+            .pool_thread
+            .pool_item_count()
+    }
+
+    /// Shuts down the pool in the same way as [`ThreadPool::shutdown`](crate::ThreadPool::shutdown).
+    ///
+    /// All pool items are dropped (giving them the opportunity to shutdown any child pools).
+    /// Any requests sent after shutdown will fail.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called by a pool item hosted by this pool
+    pub fn shutdown(&self) -> Vec<ThreadShutdownResponse> {
+        let mut state = self
+            .lock()
+            .expect("not to be called from a pool item hosted by this pool");
+        state.shutdown()
+    }
+}
+
+impl<P> InlinePoolState<P>
+where
+    P: PoolItem,
+{
+    /// Processes a shutdown request, unless the pool has already been shut down
+    fn shutdown(&mut self) -> Vec<ThreadShutdownResponse> {
+        if self.is_shutdown {
+            return vec![];
+        }
+
+        let (send_back_to, receive_from) = unbounded::<ThreadRequestResponse<P>>();
+        self.process(SenderCouplet::new(send_back_to, ThreadShutdownRequest(0)));
+
+        vec![
+            receive_from
+                .recv()
+                .expect("the single response to the shutdown request")
+                .into(),
+        ]
+    }
+
+    fn process(&mut self, sender_couplet: SenderCouplet<P>) {
+        if self
+            .pool_thread
+            .process_sender_couplet(sender_couplet, &mut self.thread_start_info)
+            .is_break()
+        {
+            self.is_shutdown = true;
+        }
+    }
+}
+
+/// The locked state of an [`InlinePool`]; records the thread holding the lock until dropped
+struct StateGuard<'a, P>
+where
+    P: PoolItem,
+{
+    state: MutexGuard<'a, InlinePoolState<P>>,
+    processing_on: &'a Mutex<Option<ThreadId>>,
+}
+
+impl<P> Deref for StateGuard<'_, P>
+where
+    P: PoolItem,
+{
+    type Target = InlinePoolState<P>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl<P> DerefMut for StateGuard<'_, P>
+where
+    P: PoolItem,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.state
+    }
+}
+
+impl<P> Drop for StateGuard<'_, P>
+where
+    P: PoolItem,
+{
+    fn drop(&mut self) {
+        *self
+            .processing_on
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+impl<P> Drop for InlinePool<P>
+where
+    P: PoolItem,
+{
+    /// Shuts the pool down if it has not already been, as dropping a
+    /// [`ThreadPool`](crate::ThreadPool) does
+    fn drop(&mut self) {
+        self.state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .shutdown();
+    }
+}
+
+impl<P> Default for InlinePool<P>
+where
+    P: PoolItem,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> Debug for InlinePool<P>
+where
+    P: PoolItem,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InlinePool")
+            .field("name", &P::name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> SenderAndReceiver<P> for InlinePool<P>
+where
+    P: PoolItem,
+{
+    fn send_and_receive<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        let (send_back_to, receive_from) = unbounded::<ThreadRequestResponse<P>>();
+
+        let mut state = self.lock()?;
+        for request in requests {
+            let sender_couplet = SenderCouplet::new(send_back_to.clone(), request);
+            if state.is_shutdown {
+                // mirror the behaviour of sending to a thread pool whose threads have ended
+                return Err(SendError(sender_couplet).into());
+            }
+            state.process(sender_couplet);
+        }
+        drop(state);
+        drop(send_back_to);
+
+        Ok(Box::new(receive_from.into_iter().map(|r| r.into())))
+    }
+}
+
+impl<P> ThreadSafeSenderAndReceiver<P> for InlinePool<P>
+where
+    P: PoolItem + Send,
+    P::ThreadStartInfo: Send,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        hint::black_box,
+        panic::{AssertUnwindSafe, catch_unwind},
+        sync::LazyLock,
+    };
+
+    use crate::{
+        PoolError, RemovalReason, SenderAndReceiver, ThreadSafeSenderAndReceiver, pool_item,
+        samples::*, thread_request_response::*,
+    };
+
+    use super::InlinePool;
+
+    /// The pool hosting the `Caller` pool items
+    static CALLERS: LazyLock<InlinePool<Caller>> = LazyLock::new(InlinePool::new);
+
+    /// Sends a request back into the pool that hosts it
+    #[derive(Debug)]
+    pub struct Caller;
+
+    #[pool_item]
+    impl Caller {
+        pub fn new(_id: u64) -> Self {
+            Self
+        }
+
+        /// Returns true if the request back into the pool was refused as a deadlock
+        #[messaging(CallBackRequest, CallBackResponse)]
+        pub fn call_back(&self) -> bool {
+            matches!(
+                CALLERS.send_and_receive_one(ThreadEchoRequest::new(0, "ping".to_string())),
+                Err(PoolError::Deadlock(_))
+            )
+        }
+    }
+
+    #[test]
+    fn request_back_into_the_same_pool_fails_with_deadlock() {
+        CALLERS.send_and_receive_one(CallerInit(1)).unwrap();
+
+        assert!(
+            CALLERS
+                .send_and_receive_one(CallBackRequest(1))
+                .unwrap()
+                .result
+        );
+        // the pool is still usable once the call has returned
+        assert_eq!(1, CALLERS.pool_item_count());
+    }
+
+    #[test]
+    fn pool_is_usable_after_a_pool_item_panics() {
+        let target = InlinePool::<Randoms>::new();
+        target.send_and_receive_one(RandomsAddRequest(1)).unwrap();
+
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            target.send_and_receive_one(PanicRequest(1)).unwrap();
+        }));
+        assert!(panicked.is_err());
+
+        let response = target.send_and_receive_one(SumRequest(1)).unwrap();
+        assert_eq!(Randoms::new(1).sum(), response.sum());
+    }
+
+    #[test]
+    fn add_then_query_returns_same_result_as_thread_pool() {
+        let target = InlinePool::<Randoms>::new();
+
+        let add_responses: Vec<AddResponse> = target
+            .send_and_receive((0..3u64).map(RandomsAddRequest))
+            .unwrap()
+            .collect();
+        assert!(add_responses.iter().all(|r| r.result().is_ok()));
+        assert_eq!(3, target.pool_item_count());
+
+        let response = target.send_and_receive_one(SumRequest(1)).unwrap();
+
+        assert_eq!(Randoms::new(1).sum(), response.sum());
+    }
+
+    #[test]
+    fn add_same_id_twice_second_add_fails() {
+        let target = InlinePool::<Randoms>::new();
+
+        target.send_and_receive_one(RandomsAddRequest(2)).unwrap();
+        let response = target.send_and_receive_one(RandomsAddRequest(2)).unwrap();
+
+        assert_eq!(
+            "failed to add; pool item already exists",
+            response.result().err().unwrap()
+        );
+    }
+
+    #[test]
+    fn remove_pool_item_removes_item() {
+        let target = InlinePool::<Randoms>::new();
+
+        target.send_and_receive_one(RandomsAddRequest(2)).unwrap();
+        let response = target
+            .send_and_receive_one(RemovePoolItemRequest(2))
+            .unwrap();
+
+        assert!(response.item_existed());
+        assert_eq!(0, target.pool_item_count());
+    }
+
+    #[test]
+    #[should_panic(expected = "pool item with id 1 not found")]
+    fn request_to_missing_id_calls_id_not_found() {
+        let target = InlinePool::<Randoms>::new();
+
+        target.send_and_receive_one(SumRequest(1)).unwrap();
+    }
+
+    #[test]
+    fn echo_is_processed_by_thread_0() {
+        let target = InlinePool::<Randoms>::new();
+
+        let response = target
+            .send_and_receive_one(ThreadEchoRequest::new(5, "ping".to_string()))
+            .unwrap();
+
+        assert_eq!(ThreadEchoResponse::new(5, "ping".to_string(), 0), response);
+    }
+
+    #[test]
+    fn shutdown_drains_items_and_subsequent_sends_fail() {
+        let target = InlinePool::<Randoms>::new();
+        target.send_and_receive_one(RandomsAddRequest(3)).unwrap();
+
+        assert_eq!(
+            target.shutdown(),
+            &[ThreadShutdownResponse::new(
+                0,
+                vec![ThreadShutdownResponse::new(3, vec![])]
+            )]
+        );
+        assert_eq!(0, target.pool_item_count());
+
+        assert!(target.send_and_receive_one(SumRequest(3)).is_err());
+        assert!(target.shutdown().is_empty());
+    }
+
+    thread_local! {
+        /// The reasons that `Tracked` pool items have been removed on this thread
+        static REMOVED: RefCell<Vec<RemovalReason>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Records its removal
+    #[derive(Debug)]
+    pub struct Tracked;
+
+    #[pool_item(Removed = "removed")]
+    impl Tracked {
+        pub fn new(_id: u64) -> Self {
+            Self
+        }
+
+        fn removed(&mut self, reason: RemovalReason) {
+            REMOVED.with_borrow_mut(|removed| removed.push(reason));
+        }
+
+        #[messaging(TapRequest, TapResponse)]
+        pub fn tap(&self) {}
+    }
+
+    #[test]
+    fn dropping_the_pool_shuts_it_down() {
+        let target = InlinePool::<Tracked>::new();
+        target
+            .send_and_receive((1..=2).map(TrackedInit))
+            .unwrap()
+            .for_each(drop);
+
+        drop(target);
+
+        assert_eq!(vec![RemovalReason::Shutdown; 2], REMOVED.take());
+    }
+
+    #[test]
+    fn dropping_a_shut_down_pool_does_not_shut_it_down_again() {
+        let target = InlinePool::<Tracked>::new();
+        target.send_and_receive_one(TrackedInit(1)).unwrap();
+        assert_eq!(1, target.shutdown().len());
+        assert_eq!(vec![RemovalReason::Shutdown], REMOVED.take());
+
+        drop(target);
+
+        assert!(REMOVED.take().is_empty());
+    }
+
+    #[test]
+    fn check_inline_pool_is_thread_safe() {
+        thread_safe(InlinePool::<Randoms>::new());
+    }
+
+    fn thread_safe<T>(_check_me: T)
+    where
+        T: ThreadSafeSenderAndReceiver<Randoms>,
+    {
+        black_box(())
+    }
+}
@@ -8,6 +8,7 @@
 //! - [`SenderAndReceiver`] - Main trait for sending requests and receiving responses
 //! - [`SenderAndReceiverMock`] - Mock implementation for testing
 //! - [`ThreadSafeSenderAndReceiver`] - Thread-safe version for nested thread pools
+//! - [`InlinePool`] - Single threaded implementation that hosts real pool items on the calling thread
 //!
 //! ## Testing with Mocks
 //!
@@ -45,6 +46,7 @@
 //! // The mock verifies requests match expectations and returns predefined responses
 //! ```
 
+mod inline_pool;
 mod sender_and_receiver_mock;
 pub mod sender_and_receiver_raw_mock;
 mod thread_pool;
@@ -52,13 +54,29 @@ mod thread_pool;
 use std::iter;
 
 use crate::{
-    id_targeted::IdTargeted, pool_item::PoolItem, request_with_response::RequestWithResponse,
-    sender_couplet::SenderCouplet,
+    cancellation_token::CancellationToken, id_targeted::IdTargeted, pool_item::PoolItem,
+    priority::Priority, request_with_response::RequestWithResponse,
+    stream_response::StreamResponse,
 };
 
-use crossbeam_channel::SendError;
+use crate::pool_error::PoolError;
+pub use inline_pool::InlinePool;
 pub use sender_and_receiver_mock::SenderAndReceiverMock;
 
+/// The items of a streamed response, or the request if it could not be sent
+type StreamItems<'a, T, P> = Result<
+    Box<
+        dyn Iterator<Item = <<T as RequestWithResponse<P>>::Response as StreamResponse>::Item> + 'a,
+    >,
+    PoolError<P>,
+>;
+
+/// The responses to a batch of messages of the pool item's API, or the request if it could not be sent
+type ApiResponses<'a, P> = Result<
+    Box<dyn Iterator<Item = <<P as PoolItem>::Api as RequestWithResponse<P>>::Response> + 'a>,
+    PoolError<P>,
+>;
+
 /// Trait for types that can send requests to pool items and receive responses.
 ///
 /// This trait abstracts the communication mechanism with pool items, allowing:
@@ -112,16 +130,74 @@ where
     /// # Returns
     ///
     /// A boxed iterator of responses. The iterator will yield one response per request.
+    /// For a [`ThreadPool`](crate::ThreadPool), dropping the iterator before all of the responses
+    /// have been received discards the requests that have not yet been started; use
+    /// [`send_and_receive_detached`](Self::send_and_receive_detached) to have them processed regardless.
     ///
     /// # Errors
     ///
-    /// Returns `SendError` if the thread pool has been shut down.
+    /// Returns [`PoolError::Disconnected`] if the thread pool has been shut down, or
+    /// [`PoolError::Deadlock`] if waiting for the responses would deadlock the calling pool thread.
     fn send_and_receive<'a, T>(
         &'a self,
         requests: impl Iterator<Item = T> + 'a,
-    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, SendError<SenderCouplet<P>>>
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a;
+
+    /// Send multiple requests on the lane for the given priority and receive their responses.
+    ///
+    /// The priority overrides the request type's
+    /// [`RequestWithResponse::PRIORITY`](crate::RequestWithResponse::PRIORITY).
+    /// See [`Priority`] for the ordering guarantees.
+    ///
+    /// The default implementation ignores the priority and calls `send_and_receive`;
+    /// this is appropriate for implementations (such as the mocks) that do not queue requests.
+    fn send_and_receive_with_priority<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+        _priority: Priority,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        self.send_and_receive(requests)
+    }
+
+    /// Send multiple requests tied to the given [`CancellationToken`] and receive their responses.
+    ///
+    /// Requests that have not yet been processed when the token is cancelled are discarded and
+    /// no response is returned for them.
+    ///
+    /// The default implementation ignores the token and calls `send_and_receive`;
+    /// this is appropriate for implementations that process requests before returning.
+    fn send_and_receive_with_token<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+        _cancellation_token: CancellationToken,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        self.send_and_receive(requests)
+    }
+
+    /// Send multiple requests and receive their responses without cancelling the requests when the
+    /// returned iterator is dropped.
+    ///
+    /// Every request is processed whether or not its response is received.
+    ///
+    /// The default implementation calls `send_and_receive`;
+    /// this is appropriate for implementations that process requests before returning.
+    fn send_and_receive_detached<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
     where
-        T: RequestWithResponse<P> + IdTargeted + 'a;
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        self.send_and_receive(requests)
+    }
 
     /// Convenience method for sending a single request and receiving its response.
     ///
@@ -140,19 +216,16 @@ where
     /// let response = mock.send_and_receive_one(MeanRequest(1)).expect("mock works");
     /// assert_eq!(response.mean(), 42);
     /// ```
-    fn send_and_receive_one<'a, T>(
-        &'a self,
-        request: T,
-    ) -> Result<T::Response, SendError<SenderCouplet<P>>>
+    fn send_and_receive_one<'a, T>(&'a self, request: T) -> Result<T::Response, PoolError<P>>
     where
-        T: RequestWithResponse<P> + IdTargeted + 'a,
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
     {
         let id = request.id();
         let mut responses = self.send_and_receive(iter::once(request))?;
 
         let Some(response) = responses.next() else {
-            // panics if there has been a down stream panic
-            panic!("response not received for request id {:?}", id);
+            // the pool item panicked processing the request (or its thread ended)
+            return Err(PoolError::NoResponse(id));
         };
 
         assert!(
@@ -162,6 +235,61 @@ where
 
         Ok(response)
     }
+
+    /// Send a batch of messages of the pool item's API and receive their responses.
+    ///
+    /// Unlike `send_and_receive`, the batch can mix requests of different types; each request can be
+    /// given as the pool item's API or as anything that converts into it. The responses are of the
+    /// API's response type; for a `#[pool_item]` this is the generated `{Item}Response` enum.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use messaging_thread_pool::{SenderAndReceiver, ThreadPool, samples::*};
+    ///
+    /// let pool = ThreadPool::<Randoms>::new(2);
+    /// pool.send_and_receive_once(RandomsAddRequest(1)).expect("pool available");
+    ///
+    /// let requests: [RandomsApi; 2] = [SumRequest(1).into(), MeanRequest(1).into()];
+    /// for response in SenderAndReceiver::send_and_receive_api(&pool, requests.into_iter())
+    ///     .expect("pool available")
+    /// {
+    ///     match response {
+    ///         RandomsResponse::SumResponse(response) => assert!(response.sum() > 0),
+    ///         RandomsResponse::MeanResponse(response) => assert!(response.mean() > 0),
+    ///         response => panic!("unexpected response {response:?}"),
+    ///     }
+    /// }
+    /// ```
+    fn send_and_receive_api<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+    ) -> ApiResponses<'a, P>
+    where
+        T: Into<P::Api> + 'a,
+        P::Api: RequestWithResponse<P> + 'a,
+    {
+        self.send_and_receive(requests.map(Into::into))
+    }
+
+    /// Send a request to a streaming messaging method and receive the items it produces.
+    ///
+    /// A streaming method (`#[messaging(Req, Resp, stream)]`) sends one response per item followed by
+    /// an end-of-stream marker; the returned iterator yields the items in the order they were produced
+    /// and ends at the marker. Items are received as they are produced rather than once the method completes.
+    ///
+    /// To see the raw chunks (including the end-of-stream marker) use `send_and_receive`.
+    fn send_and_receive_stream<'a, T>(&'a self, request: T) -> StreamItems<'a, T, P>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+        T::Response: StreamResponse + 'a,
+    {
+        let responses = self.send_and_receive(iter::once(request))?;
+
+        Ok(Box::new(
+            responses.map_while(|response| response.into_chunk().into_item()),
+        ))
+    }
 }
 
 /// A thread-safe version of [`SenderAndReceiver`].
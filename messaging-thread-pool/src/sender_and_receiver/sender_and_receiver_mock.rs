@@ -1,10 +1,10 @@
 use std::fmt::Debug;
 use std::sync::Mutex;
 
-use crossbeam_channel::SendError;
+use crate::pool_error::PoolError;
 
 use crate::{
-    pool_item::PoolItem, request_with_response::RequestWithResponse, sender_couplet::SenderCouplet,
+    pool_item::PoolItem, request_with_response::RequestWithResponse,
     thread_request_response::ThreadRequestResponse,
 };
 
@@ -207,7 +207,7 @@ where
     fn send_and_receive<'a, T>(
         &'a self,
         requests: impl Iterator<Item = T> + 'a,
-    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, SendError<SenderCouplet<P>>>
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
     where
         T: RequestWithResponse<P> + 'a,
     {
@@ -1,8 +1,8 @@
-use crossbeam_channel::SendError;
+use crate::pool_error::PoolError;
 
 use crate::{
-    ThreadPool, id_targeted::IdTargeted, pool_item::PoolItem,
-    request_with_response::RequestWithResponse, sender_couplet::SenderCouplet,
+    ThreadPool, cancellation_token::CancellationToken, id_targeted::IdTargeted,
+    pool_item::PoolItem, priority::Priority, request_with_response::RequestWithResponse,
 };
 
 use super::SenderAndReceiver;
@@ -15,13 +15,54 @@ where
     fn send_and_receive<'a, T>(
         &'a self,
         requests: impl Iterator<Item = T> + 'a,
-    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, SendError<SenderCouplet<P>>>
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
     where
-        T: RequestWithResponse<P> + IdTargeted + 'a,
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
     {
         match self.send_and_receive(requests) {
             Ok(result) => Ok(Box::new(result)),
             Err(err) => Err(err),
         }
     }
+
+    fn send_and_receive_with_priority<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+        priority: Priority,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        match self.send_and_receive_with_priority(requests, priority) {
+            Ok(result) => Ok(Box::new(result)),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn send_and_receive_with_token<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+        cancellation_token: CancellationToken,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        match self.send_and_receive_with_token(requests, cancellation_token) {
+            Ok(result) => Ok(Box::new(result)),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn send_and_receive_detached<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        match self.send_and_receive_detached(requests) {
+            Ok(result) => Ok(Box::new(result)),
+            Err(err) => Err(err),
+        }
+    }
 }
@@ -1,8 +1,8 @@
 use crossbeam_channel::Sender;
 
 use crate::{
-    pool_item::PoolItem, request_with_response::RequestWithResponse,
-    thread_request_response::ThreadRequestResponse,
+    cancellation_token::CancellationToken, pool_item::PoolItem,
+    request_with_response::RequestWithResponse, thread_request_response::ThreadRequestResponse,
 };
 
 /// A struct that defines the contents of a message sent to the thread pool.
@@ -13,6 +13,8 @@ where
 {
     pub return_to: Sender<ThreadRequestResponse<P>>,
     pub request: ThreadRequestResponse<P>,
+    /// When present, the request is discarded without being processed if the token has been cancelled
+    pub cancellation_token: Option<CancellationToken>,
 }
 
 impl<P> SenderCouplet<P>
@@ -27,9 +29,33 @@ where
         Self {
             return_to,
             request: request.into(),
+            cancellation_token: None,
         }
     }
 
+    /// Creates a new SenderCouplet whose request can be cancelled with the given token.
+    pub fn new_with_cancellation_token<T>(
+        return_to: Sender<ThreadRequestResponse<P>>,
+        request: T,
+        cancellation_token: CancellationToken,
+    ) -> Self
+    where
+        T: RequestWithResponse<P>,
+    {
+        Self {
+            return_to,
+            request: request.into(),
+            cancellation_token: Some(cancellation_token),
+        }
+    }
+
+    /// Returns true if the couplet has a cancellation token and that token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
     /// Returns the request contained in the couplet.
     pub fn request(&self) -> &ThreadRequestResponse<P> {
         &self.request
@@ -1,7 +1,8 @@
 use crossbeam_channel::bounded;
 
 use crate::{
-    ThreadPool, pool_item::PoolItem, request_response::RequestResponse, thread_request_response::*,
+    ThreadPool, pool_item::PoolItem, priority::Priority, request_response::RequestResponse,
+    thread_request_response::*,
 };
 
 impl<P> ThreadPool<P>
@@ -12,8 +13,6 @@ where
     /// It sends the shutdown message to each of it's contained PoolThreads
     /// The sending of this message should cause the message loop to exit and the thread to end
     pub fn shutdown(&self) -> Vec<ThreadShutdownResponse> {
-        let (send_to_pool, receive_back_from) = bounded::<ThreadRequestResponse<P>>(0);
-
         let mut return_codes = Vec::with_capacity(
             self.thread_endpoints
                 .read()
@@ -28,31 +27,39 @@ where
             .drain(..)
             .enumerate()
         {
-            // send straight to each of the thread endpoints
-            if endpoint
-                .send(&send_to_pool, ThreadShutdownRequest(id as u64))
-                .is_ok()
-            {
-                let mut child_threads = Vec::<ThreadShutdownResponse>::default();
+            // each thread responds on its own channel; if the thread ends without responding (it may
+            // have panicked after the request was sent) the channel is disconnected rather than
+            // waiting forever on a sender that this loop still holds for the next thread
+            let (send_to_pool, receive_back_from) = bounded::<ThreadRequestResponse<P>>(0);
 
+            // send straight to each of the thread endpoints
+            // the shutdown is sent on the high priority lane so that a busy lane cannot starve it;
+            // the thread still processes everything already queued before it shuts down
+            let response = endpoint
+                .send(
+                    &send_to_pool,
+                    ThreadShutdownRequest(id as u64),
+                    Priority::High,
+                    None,
+                )
+                .ok()
+                .and_then(|_| {
+                    drop(send_to_pool);
+                    receive_back_from.recv().ok()
+                });
+            if let Some(response) = response {
                 // verify the response back from the message loop; the message loop should now have ended
-                match receive_back_from
-                    .recv()
-                    .expect("the single response to the shutdown request")
-                {
+                let thread_shutdown_payload = match response {
                     ThreadRequestResponse::ThreadShutdown(RequestResponse::Response(
                         thread_shutdown_payload,
-                    )) => {
-                        child_threads.append(&mut thread_shutdown_payload.take_children());
-                    }
+                    )) => thread_shutdown_payload,
                     _ => panic!("only a shutdown response expected"),
-                }
+                };
 
                 // now join the thread
-                return_codes.push(ThreadShutdownResponse::new(
-                    endpoint.join_handle().join().expect("join to succeed"),
-                    child_threads,
-                ));
+                let thread_id = endpoint.join_handle().join().expect("join to succeed");
+                debug_assert_eq!(thread_id, thread_shutdown_payload.thread_id());
+                return_codes.push(thread_shutdown_payload);
             } else {
                 // the send of the shutdown request failed this probably means that the thread that the
                 // endpoint refers to has panicked; just try to re-join as in shutdown anyway
@@ -72,7 +79,41 @@ where
 mod tests {
     use std::iter;
 
-    use crate::{ThreadPool, samples::*, thread_request_response::*};
+    use crate::{ThreadPool, pool_item, samples::*, thread_request_response::*};
+
+    /// A pool item that panics when its thread shuts down
+    #[derive(Debug)]
+    pub struct Brittle {
+        id: u64,
+    }
+
+    #[pool_item(Shutdown = "shatter")]
+    impl Brittle {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+
+        pub fn shatter(&self) -> Vec<ThreadShutdownResponse> {
+            panic!("{} shattered on shutdown", self.id);
+        }
+
+        #[messaging(TapRequest, TapResponse)]
+        pub fn tap(&self) -> u64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn thread_panicking_after_accepting_shutdown_is_skipped() {
+        let target = ThreadPool::<Brittle>::new(2);
+
+        // only thread 0 holds a pool item; thread 1 shuts down cleanly
+        target
+            .send_and_receive_once(BrittleInit(0))
+            .expect("thread pool to be available");
+
+        assert_eq!(target.shutdown(), &[ThreadShutdownResponse::new(1, vec![])]);
+    }
 
     #[test]
     fn single_thread_errors_trying_to_send_request_shutdown_does_not_panic() {
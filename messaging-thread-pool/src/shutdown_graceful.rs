@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::unbounded;
+use tracing::{Level, event};
+
+use crate::{
+    ThreadPool, pool_item::PoolItem, priority::Priority, request_response::RequestResponse,
+    thread_request_response::*,
+};
+
+/// The outcome of [`ThreadPool::shutdown_graceful`]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShutdownReport {
+    /// The shutdown responses of the threads that finished draining before the deadline
+    pub shutdown_responses: Vec<ThreadShutdownResponse>,
+    /// The number of messages for pool items queued when the pool stopped accepting requests that
+    /// were taken by their thread before the deadline
+    pub processed: usize,
+    /// The number of messages for pool items still queued at the deadline; they are discarded
+    /// without being processed
+    ///
+    /// Only messages processed by pool items ([`ThreadRequestResponse::MessagePoolItem`] and
+    /// [`ThreadRequestResponse::MessagePoolItems`]) are counted, and discarded. Requests that add or
+    /// remove pool items, and requests for the thread itself, are still processed after the deadline.
+    pub discarded: usize,
+    /// The ids of the threads that had not finished draining by the deadline; they are detached
+    pub missed_deadline: Vec<u64>,
+}
+
+impl<P> ThreadPool<P>
+where
+    P: PoolItem,
+{
+    /// This function shuts the thread pool down, giving its threads up to `timeout` to process the
+    /// messages already queued
+    ///
+    /// The pool stops accepting new requests immediately; any later send fails with
+    /// [`PoolError::Disconnected`](crate::PoolError::Disconnected).
+    /// Each thread then processes everything on its lanes before shutting down its pool items as
+    /// [`shutdown`](Self::shutdown) does.
+    ///
+    /// Threads that have not finished by the deadline are detached rather than joined. They finish the
+    /// message they are processing, discard any other messages queued for pool items (see
+    /// [`ShutdownReport::discarded`]) and then shut down.
+    pub fn shutdown_graceful(&self, timeout: Duration) -> ShutdownReport {
+        let deadline = Instant::now() + timeout;
+        let (send_to_pool, receive_back_from) = unbounded::<ThreadRequestResponse<P>>();
+
+        // taking the endpoints stops the pool accepting new requests
+        let endpoints: Vec<_> = self
+            .thread_endpoints
+            .write()
+            .expect("no poisoned locks")
+            .drain(..)
+            .collect();
+
+        let mut queued = 0;
+        let mut draining = HashMap::with_capacity(endpoints.len());
+        for (id, endpoint) in endpoints.into_iter().enumerate() {
+            let id = id as u64;
+            let queued_messages = endpoint.queued_pool_item_messages();
+            // the shutdown is sent on the high priority lane so that a busy lane cannot starve it;
+            // the thread still processes everything already queued before it shuts down
+            if endpoint
+                .send(
+                    &send_to_pool,
+                    ThreadShutdownRequest(id),
+                    Priority::High,
+                    None,
+                )
+                .is_ok()
+            {
+                queued += queued_messages;
+                draining.insert(id, endpoint);
+            } else {
+                // the thread has probably panicked; as with shutdown ignore the failed join
+                let _ = endpoint.join_handle().join();
+            }
+        }
+
+        let mut report = ShutdownReport::default();
+        while !draining.is_empty() {
+            let Ok(response) = receive_back_from.recv_deadline(deadline) else {
+                break;
+            };
+            let ThreadRequestResponse::ThreadShutdown(RequestResponse::Response(response)) =
+                response
+            else {
+                panic!("only shutdown responses expected");
+            };
+            let endpoint = draining
+                .remove(&response.thread_id())
+                .expect("one shutdown response per thread");
+            let thread_id = endpoint.join_handle().join().expect("join to succeed");
+            debug_assert_eq!(thread_id, response.thread_id());
+            report.shutdown_responses.push(response);
+        }
+        report
+            .shutdown_responses
+            .sort_by_key(ThreadShutdownResponse::thread_id);
+
+        if !draining.is_empty() {
+            // the threads that are still draining discard whatever they have left
+            self.drain_deadline.cancel();
+        }
+        for (id, endpoint) in draining {
+            // the pool item messages that the thread has not started are discarded
+            report.discarded += endpoint.queued_pool_item_messages();
+            report.missed_deadline.push(id);
+            event!(
+                Level::WARN,
+                "Thread {}-{} missed the shutdown deadline and has been detached",
+                P::name(),
+                id
+            );
+        }
+        report.missed_deadline.sort();
+        report.processed = queued.saturating_sub(report.discarded);
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::LazyLock, time::Duration};
+
+    use crossbeam_channel::{Receiver, Sender, unbounded};
+
+    use crate::{PoolError, ThreadPool, pool_item, samples::*, thread_request_response::*};
+
+    use super::ShutdownReport;
+
+    /// Signalled by a [`Gate`] when it starts to wait
+    static WAITING: LazyLock<(Sender<u64>, Receiver<u64>)> = LazyLock::new(unbounded);
+    /// Releases a waiting [`Gate`]
+    static RELEASE: LazyLock<(Sender<()>, Receiver<()>)> = LazyLock::new(unbounded);
+
+    #[derive(Debug)]
+    pub struct Gate {
+        id: u64,
+    }
+
+    impl Gate {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+    }
+
+    #[pool_item]
+    impl Gate {
+        /// Blocks the pool thread until the test releases it
+        #[messaging(WaitRequest, WaitResponse)]
+        pub fn wait(&self) -> u64 {
+            WAITING.0.send(self.id).unwrap();
+            RELEASE.1.recv().unwrap();
+            self.id
+        }
+
+        #[messaging(PassRequest, PassResponse)]
+        pub fn pass(&self) -> u64 {
+            self.id
+        }
+    }
+
+    #[test]
+    fn queued_messages_are_processed_before_the_threads_shut_down() {
+        let target = ThreadPool::<Randoms>::new(2);
+        target
+            .send_and_receive((0..4u64).map(RandomsAddRequest))
+            .unwrap()
+            .for_each(|response| assert!(response.result().is_ok()));
+
+        let (send_back_to, receive_from_thread) = unbounded::<ThreadRequestResponse<Randoms>>();
+        target
+            .send(send_back_to, (0..4u64).map(SumRequest), None, None, None)
+            .unwrap();
+
+        let report = target.shutdown_graceful(Duration::from_secs(10));
+
+        assert_eq!(4, receive_from_thread.iter().count());
+        assert_eq!(4, report.processed);
+        assert_eq!(0, report.discarded);
+        assert!(report.missed_deadline.is_empty());
+        assert_eq!(
+            vec![0, 1],
+            report
+                .shutdown_responses
+                .iter()
+                .map(ThreadShutdownResponse::thread_id)
+                .collect::<Vec<_>>()
+        );
+
+        // no more requests are accepted
+        assert!(matches!(
+            target.send_and_receive_once(SumRequest(0)),
+            Err(PoolError::Disconnected(_))
+        ));
+        assert_eq!(
+            ShutdownReport::default(),
+            target.shutdown_graceful(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn threads_that_miss_the_deadline_are_detached_and_discard_their_queue() {
+        let target = ThreadPool::<Gate>::new(2);
+        target
+            .send_and_receive((0..2u64).map(GateInit))
+            .unwrap()
+            .for_each(|response| assert!(response.result().is_ok()));
+
+        // thread 0 is held until after the deadline; thread 1 finishes its queue before it
+        let (send_back_to, receive_from_thread) = unbounded::<ThreadRequestResponse<Gate>>();
+        target
+            .send(
+                send_back_to.clone(),
+                [WaitRequest(0)].into_iter(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(0, WAITING.1.recv().unwrap());
+        target
+            .send(
+                send_back_to.clone(),
+                [
+                    PassRequest(0),
+                    PassRequest(0),
+                    PassRequest(1),
+                    PassRequest(1),
+                ]
+                .into_iter(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        // a message for the thread itself is not discarded (or counted)
+        target
+            .send(
+                send_back_to,
+                [ThreadEchoRequest::new(0, "after".to_string())].into_iter(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        let report = target.shutdown_graceful(Duration::from_millis(500));
+
+        assert_eq!(vec![0], report.missed_deadline);
+        assert_eq!(2, report.discarded);
+        assert_eq!(2, report.processed);
+        assert_eq!(1, report.shutdown_responses.len());
+        assert_eq!(1, report.shutdown_responses[0].thread_id());
+
+        // the message that thread 0 was processing at the deadline is still answered, as is the echo
+        RELEASE.0.send(()).unwrap();
+        let mut answered: Vec<String> = receive_from_thread
+            .iter()
+            .map(|response| match response {
+                ThreadRequestResponse::ThreadEcho(_) => {
+                    ThreadEchoResponse::from(response).message().to_string()
+                }
+                ThreadRequestResponse::MessagePoolItem(GateApi::WaitRequest(_)) => {
+                    format!("wait {}", WaitResponse::from(response).result)
+                }
+                response => format!("pass {}", PassResponse::from(response).result),
+            })
+            .collect();
+        answered.sort();
+        assert_eq!(vec!["after", "pass 1", "pass 1", "wait 0"], answered);
+    }
+}
@@ -0,0 +1,183 @@
+//! # Deterministic Simulation
+//!
+//! This module provides a simulation backend that runs all of the threads of a pool
+//! cooperatively on a single OS thread.
+//!
+//! Concurrency bugs between nested pools (such as a [`RandomsBatch`](crate::samples::RandomsBatch)
+//! talking to an inner pool of [`Randoms`](crate::samples::Randoms)) depend on the order in which
+//! the threads happen to process their messages, which makes them hard to reproduce.
+//! A [`SimulatedPool`] removes this source of nondeterminism:
+//! - Every simulated thread has its own FIFO queue, exactly as a real pool thread does
+//! - Which thread processes its next message is chosen by a seeded random number generator
+//! - Time is provided by a [`VirtualClock`] that only advances as messages are processed
+//!
+//! Running the same scenario with the same seed always produces the same interleaving, so a
+//! failing interleaving can be replayed exactly from its seed.
+//!
+//! [`Priority`](crate::Priority) lanes are not modelled: a simulated thread processes its
+//! messages in the order they were sent, whatever their priority. Code that depends on a high
+//! priority request overtaking queued requests must be tested against a [`ThreadPool`](crate::ThreadPool).
+//!
+//! ## Virtual Time
+//!
+//! The [`VirtualClock`] is only read by code that asks for it; it is intended for the timers and
+//! timeouts of pool items (and of the test driving the simulation). The library's own timing uses
+//! real time and is not part of a simulation: the [`Watchdog`](crate::Watchdog), the deadline of
+//! [`ThreadPool::shutdown_graceful`](crate::ThreadPool::shutdown_graceful) and the receive timeouts
+//! of pool threads belong to [`ThreadPool`](crate::ThreadPool), which a [`SimulatedPool`] does not use.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use messaging_thread_pool::{SenderAndReceiver, samples::*, simulation::*};
+//!
+//! let simulation = Simulation::new(42);
+//! let pool = SimulatedPool::<Randoms>::new(4, simulation.clone());
+//!
+//! pool.send_and_receive((0..8u64).map(RandomsAddRequest))
+//!     .expect("pool available")
+//!     .for_each(|response| assert!(response.result().is_ok()));
+//!
+//! // every processed message advanced the virtual clock by one tick
+//! assert_eq!(simulation.clock().now(), 8 * simulation.tick());
+//! ```
+//!
+//! ## Nested Pools
+//!
+//! Pools that share a [`Simulation`] share its random number generator and clock, so a whole
+//! hierarchy of pools is replayed from a single seed.
+//! A simulated pool processes requests whilst holding an internal lock; a pool item must not send
+//! requests back into the pool that is hosting it.
+
+mod simulated_pool;
+mod virtual_clock;
+
+use std::{
+    cell::RefCell,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256Plus;
+use tracing::{Level, event};
+
+pub use simulated_pool::SimulatedPool;
+pub use virtual_clock::VirtualClock;
+
+thread_local! {
+    static CURRENT_SIMULATION: RefCell<Option<Simulation>> = const { RefCell::new(None) };
+}
+
+/// The default amount of virtual time that passes each time a message is processed
+pub const DEFAULT_TICK: Duration = Duration::from_millis(1);
+
+/// The shared state of a simulation run; a seeded scheduler and a virtual clock.
+///
+/// `Simulation` is cheap to clone; all clones share the same random number generator and clock.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    inner: Arc<SimulationInner>,
+}
+
+#[derive(Debug)]
+struct SimulationInner {
+    seed: u64,
+    tick: Duration,
+    rng: Mutex<Xoshiro256Plus>,
+    clock: VirtualClock,
+}
+
+impl Simulation {
+    /// Creates a new simulation whose scheduling decisions are derived from the given seed
+    pub fn new(seed: u64) -> Self {
+        Self::new_with_tick(seed, DEFAULT_TICK)
+    }
+
+    /// Creates a new simulation where each processed message advances the virtual clock by `tick`
+    pub fn new_with_tick(seed: u64, tick: Duration) -> Self {
+        event!(Level::INFO, seed, "starting simulation");
+        Self {
+            inner: Arc::new(SimulationInner {
+                seed,
+                tick,
+                rng: Mutex::new(Xoshiro256Plus::seed_from_u64(seed)),
+                clock: VirtualClock::default(),
+            }),
+        }
+    }
+
+    /// Creates a new simulation with a randomly chosen seed
+    ///
+    /// The seed is logged and is available from [`seed`](Self::seed) so that a failing run
+    /// can be replayed with [`new`](Self::new).
+    pub fn random() -> Self {
+        Self::new(rand::random())
+    }
+
+    /// The seed that this simulation was created with
+    pub fn seed(&self) -> u64 {
+        self.inner.seed
+    }
+
+    /// The amount of virtual time that passes each time a message is processed
+    pub fn tick(&self) -> Duration {
+        self.inner.tick
+    }
+
+    /// The virtual clock shared by all pools in this simulation
+    pub fn clock(&self) -> &VirtualClock {
+        &self.inner.clock
+    }
+
+    /// Returns the simulation that is processing the message currently being handled on this thread
+    ///
+    /// This allows pool items to access the virtual clock without it being passed to them.
+    /// Returns `None` when called outside of a simulated pool.
+    pub fn current() -> Option<Simulation> {
+        CURRENT_SIMULATION.with(|current| current.borrow().clone())
+    }
+
+    /// Chooses an index in the range `0..count` using the simulation's random number generator
+    pub(crate) fn choose(&self, count: usize) -> usize {
+        self.inner
+            .rng
+            .lock()
+            .expect("simulation rng lock not to be poisoned")
+            .random_range(0..count)
+    }
+
+    /// Runs the passed in function with this simulation set as the current simulation
+    pub(crate) fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        let previous = CURRENT_SIMULATION.replace(Some(self.clone()));
+        let result = f();
+        CURRENT_SIMULATION.set(previous);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Simulation;
+
+    #[test]
+    fn same_seed_same_choices() {
+        let first = Simulation::new(7);
+        let second = Simulation::new(7);
+
+        let first_choices: Vec<_> = (0..20).map(|_| first.choose(5)).collect();
+        let second_choices: Vec<_> = (0..20).map(|_| second.choose(5)).collect();
+
+        assert_eq!(first_choices, second_choices);
+    }
+
+    #[test]
+    fn current_only_available_when_entered() {
+        let target = Simulation::new(1);
+
+        assert!(Simulation::current().is_none());
+        let seed = target.enter(|| Simulation::current().unwrap().seed());
+        assert_eq!(1, seed);
+        assert!(Simulation::current().is_none());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::VecDeque, fmt::Debug, sync::Mutex};
+
+use crate::pool_error::PoolError;
+use crossbeam_channel::{SendError, never, unbounded};
+use tracing::{Level, event};
+
+use crate::{
+    SenderAndReceiver, ThreadSafeSenderAndReceiver, id_targeted::IdTargeted, pool_item::PoolItem,
+    pool_thread::PoolThread, request_with_response::RequestWithResponse,
+    sender_couplet::SenderCouplet, thread_request_response::*,
+};
+
+use super::Simulation;
+
+/// A pool whose threads are simulated cooperatively on the calling thread.
+///
+/// Requests are routed to simulated threads exactly as they would be in a
+/// [`ThreadPool`](crate::ThreadPool), and each simulated thread processes its queue in FIFO order
+/// with the same semantics as a real pool thread.
+/// Whenever more than one simulated thread has work queued, the thread that gets to process its
+/// next message is chosen by the [`Simulation`]'s seeded random number generator.
+///
+/// `send_and_receive` runs the simulation until all of the queues are empty before returning.
+///
+/// See the [module documentation](super) for an example.
+pub struct SimulatedPool<P>
+where
+    P: PoolItem,
+{
+    simulation: Simulation,
+    threads: Mutex<Vec<SimulatedThread<P>>>,
+}
+
+struct SimulatedThread<P>
+where
+    P: PoolItem,
+{
+    pool_thread: PoolThread<P>,
+    queue: VecDeque<SenderCouplet<P>>,
+    thread_start_info: Option<P::ThreadStartInfo>,
+    is_shutdown: bool,
+}
+
+impl<P> SimulatedPool<P>
+where
+    P: PoolItem,
+{
+    /// Creates a new simulated pool with the given number of simulated threads
+    ///
+    /// [`PoolItem::thread_start`] is called once for each simulated thread.
+    pub fn new(thread_pool_size: u64, simulation: Simulation) -> Self {
+        assert!(
+            thread_pool_size > 0,
+            "thread pool must have at least one thread"
+        );
+
+        let threads = (0..thread_pool_size)
+            .map(|i| SimulatedThread {
+                // messages are queued in the simulated thread so the pool thread never needs to receive
+                pool_thread: PoolThread::new(i, never()),
+                queue: VecDeque::default(),
+                thread_start_info: P::thread_start(),
+                is_shutdown: false,
+            })
+            .collect();
+
+        Self {
+            simulation,
+            threads: Mutex::new(threads),
+        }
+    }
+
+    /// Returns the number of simulated threads in this pool
+    pub fn thread_count(&self) -> usize {
+        self.threads
+            .lock()
+            .expect("simulated pool lock not to be poisoned")
+            .len()
+    }
+
+    /// The simulation that is driving this pool
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// Shuts down each of the simulated threads in the same way as
+    /// [`ThreadPool::shutdown`](crate::ThreadPool::shutdown)
+    pub fn shutdown(&self) -> Vec<ThreadShutdownResponse> {
+        let (send_back_to, receive_from) = unbounded::<ThreadRequestResponse<P>>();
+
+        let mut threads = self
+            .threads
+            .lock()
+            .expect("simulated pool lock not to be poisoned");
+        for (id, thread) in threads.iter_mut().enumerate() {
+            if !thread.is_shutdown {
+                thread.queue.push_back(SenderCouplet::new(
+                    send_back_to.clone(),
+                    ThreadShutdownRequest(id as u64),
+                ));
+            }
+        }
+        self.run(&mut threads);
+        drop(threads);
+        drop(send_back_to);
+
+        let mut responses: Vec<ThreadShutdownResponse> =
+            receive_from.into_iter().map(|r| r.into()).collect();
+        // the simulation will have processed the shutdowns in a random order
+        responses.sort_by_key(|r| r.thread_id());
+        responses
+    }
+
+    /// Runs the simulation until all of the simulated threads have empty queues
+    fn run(&self, threads: &mut [SimulatedThread<P>]) {
+        self.simulation.enter(|| {
+            loop {
+                let ready: Vec<usize> = threads
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, thread)| !thread.queue.is_empty())
+                    .map(|(i, _)| i)
+                    .collect();
+                if ready.is_empty() {
+                    return;
+                }
+
+                let chosen = ready[self.simulation.choose(ready.len())];
+                event!(
+                    Level::TRACE,
+                    "simulation scheduling thread [{}-{}] at {:?}",
+                    P::name(),
+                    chosen,
+                    self.simulation.clock().now()
+                );
+                threads[chosen].process_next();
+                self.simulation.clock().advance(self.simulation.tick());
+            }
+        });
+    }
+}
+
+impl<P> SimulatedThread<P>
+where
+    P: PoolItem,
+{
+    fn process_next(&mut self) {
+        let sender_couplet = self
+            .queue
+            .pop_front()
+            .expect("only threads with queued messages are scheduled");
+        if self
+            .pool_thread
+            .process_sender_couplet(sender_couplet, &mut self.thread_start_info)
+            .is_break()
+        {
+            self.is_shutdown = true;
+            // a real thread would have ended; anything left in its queue is never processed
+            self.queue.clear();
+        }
+    }
+}
+
+impl<P> Debug for SimulatedPool<P>
+where
+    P: PoolItem,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimulatedPool")
+            .field("name", &P::name())
+            .field("simulation", &self.simulation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P> SenderAndReceiver<P> for SimulatedPool<P>
+where
+    P: PoolItem,
+{
+    fn send_and_receive<'a, T>(
+        &'a self,
+        requests: impl Iterator<Item = T> + 'a,
+    ) -> Result<Box<dyn Iterator<Item = T::Response> + 'a>, PoolError<P>>
+    where
+        T: RequestWithResponse<P> + IdTargeted<P::Key> + 'a,
+    {
+        let (send_back_to, receive_from) = unbounded::<ThreadRequestResponse<P>>();
+
+        let mut threads = self
+            .threads
+            .lock()
+            .expect("simulated pool lock not to be poisoned");
+        let thread_count = threads.len();
+        for request in requests {
+            // route to correct thread in exactly the same way as a real thread pool
+            let targeted = P::id_thread_router(request.routing_key(), thread_count) as usize;
+            let sender_couplet = SenderCouplet::new(send_back_to.clone(), request);
+            if threads[targeted].is_shutdown {
+                return Err(SendError(sender_couplet).into());
+            }
+            threads[targeted].queue.push_back(sender_couplet);
+        }
+        self.run(&mut threads);
+        drop(threads);
+        drop(send_back_to);
+
+        Ok(Box::new(receive_from.into_iter().map(|r| r.into())))
+    }
+}
+
+impl<P> ThreadSafeSenderAndReceiver<P> for SimulatedPool<P>
+where
+    P: PoolItem + Send,
+    P::ThreadStartInfo: Send,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        SenderAndReceiver,
+        samples::*,
+        simulation::{SimulatedPool, Simulation},
+        thread_request_response::*,
+    };
+
+    fn echo_order(seed: u64) -> Vec<u64> {
+        let target = SimulatedPool::<Randoms>::new(4, Simulation::new(seed));
+
+        target
+            .send_and_receive((0..16u64).map(|i| ThreadEchoRequest::new(i, "ping".to_string())))
+            .unwrap()
+            .map(|response: ThreadEchoResponse| response.thread_id())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_replays_same_interleaving() {
+        assert_eq!(echo_order(3), echo_order(3));
+    }
+
+    #[test]
+    fn per_thread_ordering_is_preserved() {
+        let order = echo_order(11);
+
+        for thread in 0..4u64 {
+            let on_thread: Vec<_> = order.iter().filter(|id| *id % 4 == thread).collect();
+            assert!(on_thread.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn echo_processed_by_routed_thread() {
+        let target = SimulatedPool::<Randoms>::new(2, Simulation::new(0));
+
+        let response = target
+            .send_and_receive_one(ThreadEchoRequest::new(3, "ping".to_string()))
+            .unwrap();
+
+        assert_eq!(ThreadEchoResponse::new(3, "ping".to_string(), 1), response);
+    }
+
+    #[test]
+    fn clock_advances_one_tick_per_message() {
+        let simulation = Simulation::new_with_tick(5, Duration::from_secs(1));
+        let target = SimulatedPool::<Randoms>::new(2, simulation.clone());
+
+        target
+            .send_and_receive((0..3u64).map(RandomsAddRequest))
+            .unwrap()
+            .for_each(|_: AddResponse| {});
+
+        assert_eq!(Duration::from_secs(3), simulation.clock().now());
+    }
+
+    #[test]
+    fn shutdown_returns_response_per_thread_then_sends_fail() {
+        let target = SimulatedPool::<Randoms>::new(2, Simulation::new(9));
+        target.send_and_receive_one(RandomsAddRequest(1)).unwrap();
+
+        assert_eq!(
+            target.shutdown(),
+            &[
+                ThreadShutdownResponse::new(0, vec![]),
+                ThreadShutdownResponse::new(1, vec![ThreadShutdownResponse::new(1, vec![])]),
+            ]
+        );
+        assert!(target.send_and_receive_one(SumRequest(1)).is_err());
+    }
+}
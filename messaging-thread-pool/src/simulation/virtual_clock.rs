@@ -0,0 +1,86 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// A clock that only moves when it is told to.
+///
+/// Within a [`Simulation`](super::Simulation) the clock is advanced by one tick for every
+/// message processed, so timers and timeouts measured against it are deterministic.
+/// Only code that reads this clock sees virtual time; the library's own timeouts (such as the
+/// watchdog threshold and the graceful shutdown deadline of a [`ThreadPool`](crate::ThreadPool))
+/// are measured in real time.
+/// Time is measured as a [`Duration`] since the start of the simulation.
+///
+/// Clones share the same underlying time.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// The amount of virtual time that has passed since the simulation started
+    pub fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+
+    /// Moves the clock forward by the given duration
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(
+            u64::try_from(by.as_nanos()).expect("virtual time to fit in u64 nanoseconds"),
+            Ordering::SeqCst,
+        );
+    }
+
+    /// The virtual equivalent of `thread::sleep`; returns immediately having advanced the clock
+    pub fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    /// Returns the virtual time that is `after` from now; for use as a timer or timeout
+    pub fn deadline(&self, after: Duration) -> Duration {
+        self.now() + after
+    }
+
+    /// Returns true if the virtual time has reached the given deadline
+    pub fn has_passed(&self, deadline: Duration) -> bool {
+        self.now() >= deadline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::VirtualClock;
+
+    #[test]
+    fn new_clock_starts_at_zero() {
+        assert_eq!(Duration::ZERO, VirtualClock::default().now());
+    }
+
+    #[test]
+    fn clones_share_time() {
+        let target = VirtualClock::default();
+        let clone = target.clone();
+
+        clone.sleep(Duration::from_secs(2));
+
+        assert_eq!(Duration::from_secs(2), target.now());
+    }
+
+    #[test]
+    fn deadline_passes_once_clock_advanced() {
+        let target = VirtualClock::default();
+        let deadline = target.deadline(Duration::from_millis(10));
+
+        target.advance(Duration::from_millis(9));
+        assert!(!target.has_passed(deadline));
+
+        target.advance(Duration::from_millis(1));
+        assert!(target.has_passed(deadline));
+    }
+}
@@ -0,0 +1,124 @@
+use std::{fmt::Debug, ops::ControlFlow};
+
+/// A single chunk of the output of a streaming messaging method.
+///
+/// A streaming request receives one response per item produced by the method, each containing
+/// a `StreamChunk::Item`, followed by a final response containing `StreamChunk::End`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamChunk<T> {
+    /// An item produced by the streaming method
+    Item(T),
+    /// Marks the end of the stream; no more chunks will be sent for the request
+    End,
+}
+
+impl<T> StreamChunk<T> {
+    /// Returns the item contained in the chunk, or None if this is the end of the stream
+    pub fn into_item(self) -> Option<T> {
+        match self {
+            StreamChunk::Item(item) => Some(item),
+            StreamChunk::End => None,
+        }
+    }
+
+    /// Returns true if this chunk marks the end of the stream
+    pub fn is_end(&self) -> bool {
+        matches!(self, StreamChunk::End)
+    }
+}
+
+/// Implemented by the response types of streaming messaging methods.
+///
+/// This allows the chunks of a stream to be recovered from the responses;
+/// see [`SenderAndReceiver::send_and_receive_stream`](crate::SenderAndReceiver::send_and_receive_stream).
+/// The `#[pool_item]` macro implements this for methods marked `#[messaging(Req, Resp, stream)]`.
+pub trait StreamResponse {
+    /// The type of the items produced by the stream
+    type Item;
+
+    /// Converts the response into the chunk that it carries
+    fn into_chunk(self) -> StreamChunk<Self::Item>;
+}
+
+/// A sink that a streaming messaging method can push its items into.
+///
+/// Each item pushed is sent back to the caller immediately as a separate response.
+/// Once the caller has stopped listening (or has cancelled the request) the sink closes;
+/// further items are discarded and the method can use [`push`](Self::push)'s return value to stop early.
+pub struct StreamSink<'a, T> {
+    send: &'a mut dyn FnMut(T) -> ControlFlow<()>,
+    closed: bool,
+}
+
+impl<'a, T> StreamSink<'a, T> {
+    /// Creates a sink that passes each item to `send`; `send` returns `Break` to close the sink
+    pub fn new(send: &'a mut dyn FnMut(T) -> ControlFlow<()>) -> Self {
+        Self {
+            send,
+            closed: false,
+        }
+    }
+
+    /// Sends an item back to the caller
+    ///
+    /// Returns false if the sink is closed (the item is discarded); the method should stop producing items.
+    pub fn push(&mut self, item: T) -> bool {
+        if !self.closed && (self.send)(item).is_break() {
+            self.closed = true;
+        }
+        !self.closed
+    }
+
+    /// Returns true if the caller is no longer listening for items
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+impl<T> Debug for StreamSink<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamSink")
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::{StreamChunk, StreamSink};
+
+    #[test]
+    fn item_chunk_into_item_returns_item() {
+        assert_eq!(Some(3), StreamChunk::Item(3).into_item());
+        assert!(!StreamChunk::Item(3).is_end());
+    }
+
+    #[test]
+    fn end_chunk_into_item_returns_none() {
+        assert_eq!(None, StreamChunk::<u32>::End.into_item());
+        assert!(StreamChunk::<u32>::End.is_end());
+    }
+
+    #[test]
+    fn sink_closes_once_send_breaks() {
+        let mut received = vec![];
+        let mut send = |item: u32| {
+            received.push(item);
+            if received.len() == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let mut target = StreamSink::new(&mut send);
+
+        assert!(target.push(1));
+        assert!(!target.push(2));
+        assert!(!target.push(3));
+        assert!(target.is_closed());
+
+        assert_eq!(vec![1, 2], received);
+    }
+}
@@ -1,34 +1,60 @@
 mod send;
 
-use std::thread::JoinHandle;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{JoinHandle, Thread},
+};
 
 use crossbeam_channel::Sender;
 
-use crate::{pool_item::PoolItem, sender_couplet::SenderCouplet};
+use crate::{pool_item::PoolItem, priority::Priority, sender_couplet::SenderCouplet};
 
 /// A thread endpoint represents a thread within a thread pool
 ///
-/// It consists of a channel to make requests on and a join handle
+/// It consists of a channel per priority lane to make requests on and a join handle
 #[derive(Debug)]
 pub(crate) struct ThreadEndpoint<P>
 where
     P: PoolItem,
 {
-    sender: Sender<SenderCouplet<P>>,
+    senders: [Sender<SenderCouplet<P>>; Priority::COUNT],
     join_handle: JoinHandle<u64>,
+    /// The number of pool item messages sent to the thread that it has not yet started to process;
+    /// shared with the thread, which counts them off
+    queued_pool_item_messages: Arc<AtomicUsize>,
 }
 
 impl<P> ThreadEndpoint<P>
 where
     P: PoolItem,
 {
-    pub(crate) fn new(sender: Sender<SenderCouplet<P>>, join_handle: JoinHandle<u64>) -> Self {
+    pub(crate) fn new(
+        senders: [Sender<SenderCouplet<P>>; Priority::COUNT],
+        join_handle: JoinHandle<u64>,
+        queued_pool_item_messages: Arc<AtomicUsize>,
+    ) -> Self {
         Self {
-            sender,
+            senders,
             join_handle,
+            queued_pool_item_messages,
         }
     }
 
+    /// Returns the thread that this endpoint sends to
+    pub(crate) fn thread(&self) -> &Thread {
+        self.join_handle.thread()
+    }
+
+    /// Returns the number of messages for pool items (see
+    /// [`is_pool_item_message`](crate::ThreadRequestResponse::is_pool_item_message)) that the thread has
+    /// not yet started to process
+    pub(crate) fn queued_pool_item_messages(&self) -> usize {
+        self.queued_pool_item_messages.load(Ordering::Acquire)
+    }
+
     pub(crate) fn join_handle(self) -> JoinHandle<u64> {
         self.join_handle
     }
@@ -1,7 +1,10 @@
+use std::sync::atomic::Ordering;
+
 use crossbeam_channel::{SendError, Sender};
 
 use crate::{
-    pool_item::PoolItem, request_with_response::RequestWithResponse, sender_couplet::SenderCouplet,
+    cancellation_token::CancellationToken, pool_item::PoolItem, priority::Priority,
+    request_with_response::RequestWithResponse, sender_couplet::SenderCouplet,
     thread_request_response::ThreadRequestResponse,
 };
 
@@ -11,17 +14,48 @@ impl<P> ThreadEndpoint<P>
 where
     P: PoolItem,
 {
-    /// This function send an asynchronous request to a thread pool
+    /// This function send an asynchronous request to a thread pool on the lane for the given priority
+    ///
+    /// If a cancellation token is passed the thread will discard the request if the token is cancelled
+    /// before the request is processed
+    ///
+    /// Requests that shutdown or abort the thread are always sent on the high priority lane so that
+    /// they cannot be starved by a busy lane, whatever priority is asked for
     pub fn send<T>(
         &self,
         sender: &Sender<ThreadRequestResponse<P>>,
         request: T,
+        priority: Priority,
+        cancellation_token: Option<&CancellationToken>,
     ) -> Result<(), SendError<SenderCouplet<P>>>
     where
         T: RequestWithResponse<P>,
     {
-        self.sender
-            .send(SenderCouplet::<P>::new(sender.clone(), request))
+        let sender_couplet = match cancellation_token {
+            Some(cancellation_token) => SenderCouplet::<P>::new_with_cancellation_token(
+                sender.clone(),
+                request,
+                cancellation_token.clone(),
+            ),
+            None => SenderCouplet::<P>::new(sender.clone(), request),
+        };
+        let priority = match sender_couplet.request() {
+            ThreadRequestResponse::ThreadShutdown(_) | ThreadRequestResponse::ThreadAbort(_) => {
+                Priority::High
+            }
+            _ => priority,
+        };
+        let pool_item_message = sender_couplet.request().is_pool_item_message();
+        if pool_item_message {
+            self.queued_pool_item_messages
+                .fetch_add(1, Ordering::AcqRel);
+        }
+        let result = self.senders[priority.lane()].send(sender_couplet);
+        if pool_item_message && result.is_err() {
+            self.queued_pool_item_messages
+                .fetch_sub(1, Ordering::AcqRel);
+        }
+        result
     }
 }
 
@@ -32,8 +66,8 @@ mod tests {
     use crossbeam_channel::unbounded;
 
     use crate::{
-        samples::*, sender_couplet::SenderCouplet, thread_endpoint::ThreadEndpoint,
-        thread_request_response::*,
+        priority::Priority, samples::*, sender_couplet::SenderCouplet,
+        thread_endpoint::ThreadEndpoint, thread_request_response::*,
     };
 
     #[test]
@@ -46,14 +80,22 @@ mod tests {
         // create channels to send and receive responses
         let (to_thread_sender, receiver_from_endpoint) = unbounded::<SenderCouplet<Randoms>>();
         let (to_endpoint, from_thread) = unbounded::<ThreadRequestResponse<Randoms>>();
+        let (high_sender, high_receiver) = unbounded::<SenderCouplet<Randoms>>();
+        let (low_sender, _low_receiver) = unbounded::<SenderCouplet<Randoms>>();
 
         let target = ThreadEndpoint {
-            sender: to_thread_sender,
+            senders: [high_sender, to_thread_sender, low_sender],
             join_handle,
+            queued_pool_item_messages: Default::default(),
         };
 
         // call send
-        target.send(&to_endpoint, echo_request.clone()).unwrap();
+        target
+            .send(&to_endpoint, echo_request.clone(), Priority::Normal, None)
+            .unwrap();
+
+        // nothing should have been sent on the other lanes
+        assert!(high_receiver.is_empty());
 
         // get the message sent
         let sender_couplet = receiver_from_endpoint.recv().unwrap();
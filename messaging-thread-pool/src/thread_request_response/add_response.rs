@@ -1,43 +1,69 @@
-use crate::{pool_item::PoolItem, request_response::RequestResponse};
+use crate::{
+    pool_item::{NewPoolItemError, PoolItem},
+    pool_key::PoolKey,
+    request_response::RequestResponse,
+};
 
 use super::ThreadRequestResponse;
 
 /// This struct is returned in response to a request to add a pool item to the thread pool
 /// The success field indicates that the pool item was successfully constructed
+///
+/// If the pool item could not be constructed the response holds the [`NewPoolItemError`], including
+/// any typed error that it carries.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct AddResponse {
-    id: u64,
-    result: Result<u64, String>,
+pub struct AddResponse<K = u64> {
+    id: K,
+    result: Result<K, NewPoolItemError>,
 }
 
-impl AddResponse {
-    pub fn new(id: u64, result: Result<u64, String>) -> Self {
+impl<K> AddResponse<K>
+where
+    K: PoolKey,
+{
+    pub fn new(id: K, result: Result<K, String>) -> Self {
         assert!(
-            result.is_err() || result.clone().unwrap() == id,
+            result.as_ref().is_err() || result.as_ref().unwrap() == &id,
             "id in the success result must match the result"
         );
-        Self { id, result }
+        Self {
+            id,
+            result: result.map_err(NewPoolItemError::new),
+        }
+    }
+
+    /// A response for a pool item that could not be constructed
+    pub fn failed(id: K, error: NewPoolItemError) -> Self {
+        Self {
+            id,
+            result: Err(error),
+        }
+    }
+
+    pub fn id(&self) -> K {
+        self.id.clone()
     }
 
-    pub fn id(&self) -> u64 {
-        self.id
+    pub fn result(&self) -> Result<&K, &String> {
+        self.result.as_ref().map_err(|error| &error.error_message)
     }
 
-    pub fn result(&self) -> Result<&u64, &String> {
-        self.result.as_ref()
+    /// The reason that the pool item could not be constructed, if it failed
+    pub fn error(&self) -> Option<&NewPoolItemError> {
+        self.result.as_ref().err()
     }
 }
 
-impl<P> From<AddResponse> for ThreadRequestResponse<P>
+impl<P> From<AddResponse<P::Key>> for ThreadRequestResponse<P>
 where
     P: PoolItem,
 {
-    fn from(response: AddResponse) -> Self {
+    fn from(response: AddResponse<P::Key>) -> Self {
         ThreadRequestResponse::AddPoolItem(RequestResponse::<P, P::Init>::Response(response))
     }
 }
 
-impl<P> From<ThreadRequestResponse<P>> for AddResponse
+impl<P> From<ThreadRequestResponse<P>> for AddResponse<P::Key>
 where
     P: PoolItem,
 {
@@ -4,14 +4,58 @@ impl<P> ThreadRequestResponse<P>
 where
     P: PoolItem,
 {
-    pub fn id(&self) -> u64 {
+    /// Returns the id of the pool item that this message targets
+    ///
+    /// Returns `None` for messages that target a thread rather than a pool item
+    pub fn pool_item_id(&self) -> Option<P::Key> {
+        match self {
+            ThreadRequestResponse::ThreadShutdown(_)
+            | ThreadRequestResponse::ThreadAbort(_)
+            | ThreadRequestResponse::ThreadEcho(_) => None,
+            ThreadRequestResponse::AddPoolItem(request_response) => Some(request_response.id()),
+            ThreadRequestResponse::RemovePoolItem(request_response) => Some(request_response.id()),
+            ThreadRequestResponse::MessagePoolItem(pool_item_api) => Some(pool_item_api.id()),
+            ThreadRequestResponse::MessagePoolItems(pool_item_api) => Some(pool_item_api.id()),
+        }
+    }
+
+    /// Returns the id of the thread that this message targets
+    ///
+    /// Returns `None` for messages that target a pool item rather than a thread
+    pub fn thread_id(&self) -> Option<u64> {
         match self {
-            ThreadRequestResponse::ThreadShutdown(request_response) => request_response.id(),
-            ThreadRequestResponse::ThreadAbort(request_response) => request_response.id(),
-            ThreadRequestResponse::ThreadEcho(request_response) => request_response.id(),
-            ThreadRequestResponse::AddPoolItem(request_response) => request_response.id(),
-            ThreadRequestResponse::RemovePoolItem(request_response) => request_response.id(),
-            ThreadRequestResponse::MessagePoolItem(pool_item_api) => pool_item_api.id(),
+            ThreadRequestResponse::ThreadShutdown(request_response) => Some(request_response.id()),
+            ThreadRequestResponse::ThreadAbort(request_response) => Some(request_response.id()),
+            ThreadRequestResponse::ThreadEcho(request_response) => Some(request_response.id()),
+            _ => None,
         }
     }
 }
+
+impl<P> ThreadRequestResponse<P>
+where
+    P: PoolItem<Key = u64>,
+{
+    /// Returns the id of the pool item or thread that this message targets
+    #[deprecated(note = "use `pool_item_id` or `thread_id`, which say what the id identifies")]
+    pub fn id(&self) -> u64 {
+        self.pool_item_id()
+            .or_else(|| self.thread_id())
+            .expect("every message targets a pool item or a thread")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{samples::*, thread_request_response::*};
+
+    #[test]
+    #[allow(deprecated)]
+    fn id_returns_the_pool_item_or_thread_id() {
+        let pool_item: ThreadRequestResponse<Randoms> = MeanRequest(3).into();
+        let thread: ThreadRequestResponse<Randoms> = ThreadShutdownRequest(2).into();
+
+        assert_eq!(3, pool_item.id());
+        assert_eq!(2, thread.id());
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{PoolItem, ThreadRequestResponse};
+
+impl<P> ThreadRequestResponse<P>
+where
+    P: PoolItem,
+{
+    /// Returns the name of the type of this message, for diagnostics
+    ///
+    /// Messages for pool items are named by [`PoolItem::message_type`].
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            ThreadRequestResponse::ThreadShutdown(_) => "ThreadShutdown",
+            ThreadRequestResponse::ThreadAbort(_) => "ThreadAbort",
+            ThreadRequestResponse::ThreadEcho(_) => "ThreadEcho",
+            ThreadRequestResponse::AddPoolItem(_) => "AddPoolItem",
+            ThreadRequestResponse::RemovePoolItem(_) => "RemovePoolItem",
+            ThreadRequestResponse::MessagePoolItem(request)
+            | ThreadRequestResponse::MessagePoolItems(request) => P::message_type(request),
+        }
+    }
+
+    /// Returns true if this message is processed by one or more pool items (rather than creating or
+    /// removing a pool item, or targeting the thread)
+    pub(crate) fn is_pool_item_message(&self) -> bool {
+        matches!(
+            self,
+            ThreadRequestResponse::MessagePoolItem(_) | ThreadRequestResponse::MessagePoolItems(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{samples::*, thread_request_response::*};
+
+    #[test]
+    fn pool_item_messages_are_named_by_their_request_type() {
+        let target: ThreadRequestResponse<Randoms> = MeanRequest(1).into();
+
+        assert_eq!("MeanRequest", target.message_type());
+    }
+
+    #[test]
+    fn thread_messages_are_named_by_their_variant() {
+        let target: ThreadRequestResponse<Randoms> = ThreadEchoRequest::new(0, "hi".into()).into();
+
+        assert_eq!("ThreadEcho", target.message_type());
+    }
+}
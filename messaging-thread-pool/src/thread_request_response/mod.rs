@@ -13,7 +13,8 @@
 //!
 //! ### For Thread Management (Advanced)
 //!
-//! - [`ThreadShutdownRequest`] / [`ThreadShutdownResponse`] - Graceful shutdown
+//! - [`ThreadShutdownRequest`] / [`ThreadShutdownResponse`] - Graceful shutdown; the response carries
+//!   any [`ThreadStopData`] returned by `PoolItem::thread_stop`
 //! - [`ThreadAbortRequest`] / [`ThreadAbortResponse`] - Immediate abort (testing)
 //! - [`ThreadEchoRequest`] / [`ThreadEchoResponse`] - Echo for testing
 //!
@@ -60,6 +61,7 @@
 
 mod add_response;
 mod id;
+mod message_type;
 mod remove_pool_item_request;
 mod remove_pool_item_response;
 mod thread_abort_request;
@@ -75,11 +77,15 @@ use crate::{
 };
 
 pub use self::{
-    add_response::AddResponse, remove_pool_item_request::RemovePoolItemRequest,
-    remove_pool_item_response::RemovePoolItemResponse, thread_abort_request::ThreadAbortRequest,
-    thread_abort_response::ThreadAbortResponse, thread_echo_request::ThreadEchoRequest,
-    thread_echo_response::ThreadEchoResponse, thread_shutdown_request::ThreadShutdownRequest,
-    thread_shutdown_response::ThreadShutdownResponse,
+    add_response::AddResponse,
+    remove_pool_item_request::RemovePoolItemRequest,
+    remove_pool_item_response::RemovePoolItemResponse,
+    thread_abort_request::ThreadAbortRequest,
+    thread_abort_response::ThreadAbortResponse,
+    thread_echo_request::ThreadEchoRequest,
+    thread_echo_response::ThreadEchoResponse,
+    thread_shutdown_request::ThreadShutdownRequest,
+    thread_shutdown_response::{ThreadShutdownResponse, ThreadStopData},
 };
 
 /// The internal message type for all thread pool communication.
@@ -97,6 +103,7 @@ pub use self::{
 /// - `AddPoolItem` - Create a new pool item
 /// - `RemovePoolItem` - Remove an existing pool item
 /// - `MessagePoolItem` - Send a user-defined message to a pool item
+/// - `MessagePoolItems` - Send a user-defined message to several co-located pool items
 #[derive(Debug, PartialEq)]
 pub enum ThreadRequestResponse<P>
 where
@@ -116,10 +123,14 @@ where
     AddPoolItem(RequestResponse<P, P::Init>),
     /// Requests that an item be removed from the thread pool.
     /// The request is routed to the thread that has ownership and the pool item is dropped.
-    RemovePoolItem(RequestResponse<P, RemovePoolItemRequest>),
+    RemovePoolItem(RequestResponse<P, RemovePoolItemRequest<P::Key>>),
     /// Send a message from the pool item's defined API to a given pool item.
     /// The message is routed to the owning thread and any work is performed there.
     MessagePoolItem(P::Api),
+    /// Send a message from the pool item's defined API to several co-located pool items at once.
+    /// The message is routed to the thread that owns them and all of them must be present there.
+    /// Responses are returned as [`MessagePoolItem`](ThreadRequestResponse::MessagePoolItem).
+    MessagePoolItems(P::Api),
 }
 
 /// A [`ThreadRequestResponse`] is always a RequestWithResponse
@@ -1,35 +1,38 @@
 use crate::{
-    id_targeted::IdTargeted, pool_item::PoolItem, request_response::RequestResponse,
-    request_with_response::RequestWithResponse,
+    id_targeted::IdTargeted, pool_item::PoolItem, pool_key::PoolKey,
+    request_response::RequestResponse, request_with_response::RequestWithResponse,
 };
 
 use super::{RemovePoolItemResponse, ThreadRequestResponse};
 
 /// A request to remove a pool item
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct RemovePoolItemRequest(pub u64);
+pub struct RemovePoolItemRequest<K = u64>(pub K);
 
-impl IdTargeted for RemovePoolItemRequest {
-    fn id(&self) -> u64 {
-        self.0
+impl<K> IdTargeted<K> for RemovePoolItemRequest<K>
+where
+    K: PoolKey,
+{
+    fn id(&self) -> K {
+        self.0.clone()
     }
 }
 
-impl<P> RequestWithResponse<P> for RemovePoolItemRequest
+impl<P> RequestWithResponse<P> for RemovePoolItemRequest<P::Key>
 where
     P: PoolItem,
 {
-    type Response = RemovePoolItemResponse;
+    type Response = RemovePoolItemResponse<P::Key>;
 }
 
-impl<P> From<RemovePoolItemRequest> for ThreadRequestResponse<P>
+impl<P> From<RemovePoolItemRequest<P::Key>> for ThreadRequestResponse<P>
 where
     P: PoolItem,
 {
-    fn from(request: RemovePoolItemRequest) -> Self {
-        ThreadRequestResponse::RemovePoolItem(RequestResponse::<P, RemovePoolItemRequest>::Request(
-            request,
-        ))
+    fn from(request: RemovePoolItemRequest<P::Key>) -> Self {
+        ThreadRequestResponse::RemovePoolItem(
+            RequestResponse::<P, RemovePoolItemRequest<P::Key>>::Request(request),
+        )
     }
 }
 
@@ -42,14 +45,14 @@ mod tests {
 
     #[test]
     fn request_id_2_id_returns_2() {
-        let target = RemovePoolItemRequest(2);
+        let target = RemovePoolItemRequest(2u64);
 
         assert_eq!(2, target.id());
     }
 
     #[test]
     fn request_id_1_id_returns_1() {
-        let target = RemovePoolItemRequest(1);
+        let target = RemovePoolItemRequest(1u64);
 
         assert_eq!(1, target.id());
     }
@@ -1,21 +1,24 @@
-use crate::{pool_item::PoolItem, request_response::RequestResponse};
+use crate::{pool_item::PoolItem, pool_key::PoolKey, request_response::RequestResponse};
 
 use super::ThreadRequestResponse;
 
 /// The response received after a request to remove a pool item
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct RemovePoolItemResponse {
-    id: u64,
+pub struct RemovePoolItemResponse<K = u64> {
+    id: K,
     item_existed: bool,
 }
 
-impl RemovePoolItemResponse {
-    pub fn new(id: u64, item_existed: bool) -> Self {
+impl<K> RemovePoolItemResponse<K>
+where
+    K: PoolKey,
+{
+    pub fn new(id: K, item_existed: bool) -> Self {
         Self { id, item_existed }
     }
 
-    pub fn id(&self) -> u64 {
-        self.id
+    pub fn id(&self) -> K {
+        self.id.clone()
     }
 
     pub fn item_existed(&self) -> bool {
@@ -23,16 +26,16 @@ impl RemovePoolItemResponse {
     }
 }
 
-impl<T> From<RemovePoolItemResponse> for ThreadRequestResponse<T>
+impl<T> From<RemovePoolItemResponse<T::Key>> for ThreadRequestResponse<T>
 where
     T: PoolItem,
 {
-    fn from(request: RemovePoolItemResponse) -> Self {
+    fn from(request: RemovePoolItemResponse<T::Key>) -> Self {
         ThreadRequestResponse::RemovePoolItem(RequestResponse::Response(request))
     }
 }
 
-impl<P> From<ThreadRequestResponse<P>> for RemovePoolItemResponse
+impl<P> From<ThreadRequestResponse<P>> for RemovePoolItemResponse<P::Key>
 where
     P: PoolItem,
 {
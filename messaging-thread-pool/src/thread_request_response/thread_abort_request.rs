@@ -1,6 +1,6 @@
 use crate::{
-    id_targeted::IdTargeted, pool_item::PoolItem, request_response::RequestResponse,
-    request_with_response::RequestWithResponse,
+    id_targeted::IdTargeted, pool_item::PoolItem, priority::Priority,
+    request_response::RequestResponse, request_with_response::RequestWithResponse,
 };
 
 use super::{ThreadAbortResponse, ThreadRequestResponse};
@@ -19,6 +19,9 @@ impl<P> RequestWithResponse<P> for ThreadAbortRequest
 where
     P: PoolItem,
 {
+    // thread control messages must not be starved by a busy lane
+    const PRIORITY: Priority = Priority::High;
+
     type Response = ThreadAbortResponse;
 }
 
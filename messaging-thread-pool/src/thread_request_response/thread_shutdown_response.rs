@@ -1,22 +1,80 @@
+use std::{any::Any, fmt::Debug, sync::Arc};
+
 use crate::{pool_item::PoolItem, request_response::RequestResponse};
 
 use super::ThreadRequestResponse;
 
+/// The data returned by [`PoolItem::thread_stop`], forwarded in the thread's [`ThreadShutdownResponse`]
+///
+/// The data can be of any type; it is read back with [`downcast_ref`](ThreadStopData::downcast_ref).
+/// As the data need not be comparable `ThreadStopData` does not implement `PartialEq`.
+#[derive(Clone)]
+pub struct ThreadStopData(Arc<dyn Any + Send + Sync>);
+
+impl ThreadStopData {
+    pub fn new<T>(data: T) -> Self
+    where
+        T: Any + Send + Sync,
+    {
+        Self(Arc::new(data))
+    }
+
+    /// Returns the data if it is of type `T`
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: Any,
+    {
+        self.0.downcast_ref()
+    }
+}
+
+impl Debug for ThreadStopData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ThreadStopData").finish_non_exhaustive()
+    }
+}
+
 /// The response received from a ThreadShutdownRequest
-#[derive(Debug, PartialEq, Eq, Clone)]
+///
+/// Two responses are equal if their threads, children and whether they hold stop data match; the
+/// stop data itself is not compared.
+#[derive(Debug, Clone)]
 pub struct ThreadShutdownResponse {
     thread_id: u64,
     children: Vec<ThreadShutdownResponse>,
+    stop_data: Option<ThreadStopData>,
+}
+
+impl PartialEq for ThreadShutdownResponse {
+    fn eq(&self, other: &Self) -> bool {
+        self.thread_id == other.thread_id
+            && self.children == other.children
+            && self.stop_data.is_some() == other.stop_data.is_some()
+    }
 }
 
+impl Eq for ThreadShutdownResponse {}
+
 impl ThreadShutdownResponse {
     pub fn new(id: u64, children: Vec<ThreadShutdownResponse>) -> Self {
         Self {
             thread_id: id,
             children,
+            stop_data: None,
         }
     }
 
+    /// Attaches the data returned by the thread's [`PoolItem::thread_stop`]
+    pub fn with_stop_data(mut self, stop_data: Option<ThreadStopData>) -> Self {
+        self.stop_data = stop_data;
+        self
+    }
+
+    /// The data returned by the thread's [`PoolItem::thread_stop`], if any
+    pub fn stop_data(&self) -> Option<&ThreadStopData> {
+        self.stop_data.as_ref()
+    }
+
     pub fn take_children(self) -> Vec<ThreadShutdownResponse> {
         self.children
     }
@@ -52,3 +110,22 @@ where
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ThreadShutdownResponse, ThreadStopData};
+
+    #[test]
+    fn equality_compares_whether_stop_data_is_present_not_its_value() {
+        let with_data = |data: u32| {
+            ThreadShutdownResponse::new(1, vec![]).with_stop_data(Some(ThreadStopData::new(data)))
+        };
+
+        assert_eq!(with_data(1), with_data(2));
+        assert_ne!(with_data(1), ThreadShutdownResponse::new(1, vec![]));
+        assert_eq!(
+            Some(&2),
+            with_data(2).stop_data().unwrap().downcast_ref::<u32>()
+        );
+    }
+}
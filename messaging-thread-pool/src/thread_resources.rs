@@ -0,0 +1,373 @@
+use std::{
+    any::{Any, TypeId, type_name},
+    cell::RefCell,
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::{self, ThreadId},
+};
+
+/// The resources of one pool thread that were created on this thread, keyed by their type;
+/// `None` while the resource is borrowed
+type Resources = HashMap<TypeId, Option<Box<dyn Any>>>;
+
+/// The thread that created each of a pool thread's resources, keyed by their type
+type Owners = Arc<Mutex<HashMap<TypeId, ThreadId>>>;
+
+thread_local! {
+    /// The resources created on this thread, keyed by the id of the scope that they belong to
+    static RESOURCES: RefCell<HashMap<u64, Resources>> = RefCell::new(HashMap::new());
+    /// The scope of the pool thread that is currently processing a message on this thread
+    static CURRENT_SCOPE: RefCell<Option<ScopeRef>> = const { RefCell::new(None) };
+}
+
+/// Borrows the pool thread's resource of type `T`, creating it with `T::default()` the first time
+/// it is used
+///
+/// Each pool thread has its own set of resources, one per type, that every pool item on the thread
+/// shares. Resources are only used on the thread that created them, so they do not need to be `Send`
+/// or `Sync`; an `Rc` interner, a cache or a database connection can be shared by all of the pool
+/// items on a thread without locks. They are dropped with their pool thread.
+///
+/// The resources belong to the pool thread rather than to the OS thread that runs it. An
+/// [`InlinePool`](crate::InlinePool) processes requests on whichever thread sends them, so its
+/// resources can only be borrowed on the thread that created them. If the pool is dropped on
+/// another thread they are dropped when the thread that created them ends.
+///
+/// ```rust
+/// use std::collections::HashSet;
+///
+/// use messaging_thread_pool::{ThreadPool, pool_item, with_thread_resource};
+///
+/// #[derive(Debug)]
+/// pub struct Visitor {
+///     id: u64,
+/// }
+///
+/// #[pool_item]
+/// impl Visitor {
+///     pub fn new(id: u64) -> Self {
+///         Self { id }
+///     }
+///
+///     /// Returns the number of distinct pages that the visitors on this thread have seen
+///     #[messaging(VisitRequest, VisitResponse)]
+///     pub fn visit(&self, page: String) -> usize {
+///         with_thread_resource(|seen: &mut HashSet<String>| {
+///             seen.insert(page);
+///             seen.len()
+///         })
+///     }
+/// }
+///
+/// // both visitors are on the one thread
+/// let pool = ThreadPool::<Visitor>::new(1);
+/// pool.send_and_receive((0..2u64).map(VisitorInit)).unwrap().for_each(drop);
+///
+/// assert_eq!(1, pool.send_and_receive_once(VisitRequest(0, "home".into())).unwrap().result);
+/// assert_eq!(2, pool.send_and_receive_once(VisitRequest(1, "about".into())).unwrap().result);
+/// assert_eq!(2, pool.send_and_receive_once(VisitRequest(1, "home".into())).unwrap().result);
+/// ```
+///
+/// # Panics
+///
+/// Panics if it is not called while a pool thread is processing a message, if the resource is
+/// already borrowed (by an enclosing call for the same type) or if it was created on another thread.
+pub fn with_thread_resource<T, R>(borrow: impl FnOnce(&mut T) -> R) -> R
+where
+    T: Default + 'static,
+{
+    with_thread_resource_or_insert_with(T::default, borrow)
+}
+
+/// Borrows the pool thread's resource of type `T`, creating it with `create` the first time it is
+/// used
+///
+/// See [`with_thread_resource`].
+///
+/// # Panics
+///
+/// Panics if it is not called while a pool thread is processing a message, if the resource is
+/// already borrowed (by an enclosing call for the same type) or if it was created on another thread.
+pub fn with_thread_resource_or_insert_with<T, R>(
+    create: impl FnOnce() -> T,
+    borrow: impl FnOnce(&mut T) -> R,
+) -> R
+where
+    T: 'static,
+{
+    let scope = CURRENT_SCOPE.with_borrow(Option::clone).unwrap_or_else(|| {
+        panic!(
+            "thread resource {} is only available while a pool thread is processing a message",
+            type_name::<T>()
+        )
+    });
+    let key = TypeId::of::<T>();
+
+    // the resource is taken out of the map while it is borrowed so that other resources can be
+    // borrowed at the same time
+    let held = RESOURCES.with_borrow_mut(|resources| {
+        resources
+            .get_mut(&scope.id)
+            .and_then(|resources| resources.get_mut(&key))
+            .map(Option::take)
+    });
+    let resource = match held {
+        Some(Some(resource)) => resource,
+        Some(None) => panic!("thread resource {} is already borrowed", type_name::<T>()),
+        None => {
+            // the resource is kept on the thread that creates it, as it need not be `Send`
+            let current = thread::current().id();
+            let owner = *scope
+                .owners
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(key)
+                .or_insert(current);
+            assert!(
+                owner == current,
+                "thread resource {} was created on another thread",
+                type_name::<T>()
+            );
+            // the resource is created before it is entered in the map so that creating it can
+            // borrow other resources
+            let resource: Box<dyn Any> = Box::new(create());
+            RESOURCES.with_borrow_mut(|resources| {
+                resources.entry(scope.id).or_default().insert(key, None)
+            });
+            resource
+        }
+    };
+    let mut borrowed = Borrowed {
+        scope: scope.id,
+        key,
+        resource: Some(resource),
+    };
+    borrow(
+        borrowed
+            .resource
+            .as_mut()
+            .and_then(|resource| resource.downcast_mut())
+            .expect("resources are keyed by their type"),
+    )
+}
+
+/// Returns a borrowed resource to its scope, even if the borrower panics
+struct Borrowed {
+    scope: u64,
+    key: TypeId,
+    resource: Option<Box<dyn Any>>,
+}
+
+impl Drop for Borrowed {
+    fn drop(&mut self) {
+        let resource = self.resource.take();
+        // a resource that cannot be returned is dropped once the map has been released
+        let unreturned = RESOURCES.with_borrow_mut(|resources| {
+            match resources
+                .get_mut(&self.scope)
+                .and_then(|resources| resources.get_mut(&self.key))
+            {
+                Some(held) => {
+                    *held = resource;
+                    None
+                }
+                None => resource,
+            }
+        });
+        drop(unreturned);
+    }
+}
+
+/// Identifies the resources of one pool thread
+#[derive(Debug, Clone)]
+struct ScopeRef {
+    id: u64,
+    owners: Owners,
+}
+
+/// The resources of one pool thread
+///
+/// The scope only holds an id; as resources need not be `Send` each is kept in the thread-local
+/// storage of the thread that created it, and can only be borrowed on that thread. Dropping the
+/// scope drops the resources that were created on the current thread. Any that were created on
+/// another thread are dropped on that thread when it ends.
+#[derive(Debug)]
+pub(crate) struct ResourceScope(ScopeRef);
+
+impl ResourceScope {
+    pub(crate) fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(ScopeRef {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            owners: Owners::default(),
+        })
+    }
+
+    /// Makes this scope's resources available until the returned guard is dropped
+    pub(crate) fn enter(&self) -> EnteredScope {
+        EnteredScope {
+            previous: CURRENT_SCOPE.replace(Some(self.0.clone())),
+        }
+    }
+}
+
+impl Drop for ResourceScope {
+    fn drop(&mut self) {
+        // the resources are dropped after the map has been released; dropping them may use it.
+        // If the thread is ending its thread-local storage (and the resources) may already be gone
+        let released = RESOURCES
+            .try_with(|resources| resources.borrow_mut().remove(&self.0.id))
+            .ok()
+            .flatten();
+        drop(released);
+    }
+}
+
+/// Restores the previously entered resource scope (if any) when dropped
+pub(crate) struct EnteredScope {
+    previous: Option<ScopeRef>,
+}
+
+impl Drop for EnteredScope {
+    fn drop(&mut self) {
+        CURRENT_SCOPE.set(self.previous.take());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        panic::catch_unwind,
+        rc::Rc,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        thread,
+    };
+
+    use super::{ResourceScope, with_thread_resource, with_thread_resource_or_insert_with};
+
+    #[test]
+    fn each_scope_has_its_own_resources() {
+        let first = ResourceScope::new();
+        let second = ResourceScope::new();
+
+        {
+            let _entered = first.enter();
+            with_thread_resource(|count: &mut usize| *count += 1);
+            with_thread_resource(|count: &mut usize| *count += 1);
+            assert_eq!(2, with_thread_resource(|count: &mut usize| *count));
+        }
+        let _entered = second.enter();
+        assert_eq!(0, with_thread_resource(|count: &mut usize| *count));
+    }
+
+    #[test]
+    fn different_resources_can_be_borrowed_together() {
+        let scope = ResourceScope::new();
+        let _entered = scope.enter();
+
+        let total = with_thread_resource(|count: &mut usize| {
+            *count = 2;
+            with_thread_resource_or_insert_with(
+                || vec![1, 2, 3],
+                |values: &mut Vec<usize>| values.iter().sum::<usize>() + *count,
+            )
+        });
+        assert_eq!(8, total);
+    }
+
+    #[test]
+    fn resources_are_dropped_with_their_scope() {
+        let dropped = Rc::new(RefCell::new(false));
+        struct Flag(Rc<RefCell<bool>>);
+        impl Drop for Flag {
+            fn drop(&mut self) {
+                *self.0.borrow_mut() = true;
+            }
+        }
+
+        let scope = ResourceScope::new();
+        {
+            let _entered = scope.enter();
+            with_thread_resource_or_insert_with(|| Flag(dropped.clone()), |_: &mut Flag| ());
+        }
+        assert!(!*dropped.borrow());
+
+        drop(scope);
+        assert!(*dropped.borrow());
+    }
+
+    #[test]
+    fn resources_are_only_borrowed_on_the_thread_that_created_them() {
+        let scope = ResourceScope::new();
+        {
+            let _entered = scope.enter();
+            with_thread_resource(|count: &mut usize| *count = 1);
+        }
+
+        // the scope moves with its pool thread; the resource created here stays behind
+        let refused = thread::spawn(move || {
+            let _entered = scope.enter();
+            with_thread_resource(|name: &mut String| name.push_str("created here"));
+            catch_unwind(|| with_thread_resource(|count: &mut usize| *count)).is_err()
+        })
+        .join()
+        .expect("the thread not to panic");
+        assert!(refused);
+    }
+
+    #[test]
+    fn resources_created_on_another_thread_are_dropped_when_that_thread_ends() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        struct Flag(Arc<AtomicBool>);
+        impl Drop for Flag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let owner = thread::spawn({
+            let dropped = dropped.clone();
+            move || {
+                let scope = ResourceScope::new();
+                {
+                    let _entered = scope.enter();
+                    with_thread_resource_or_insert_with(
+                        || Flag(dropped.clone()),
+                        |_: &mut Flag| (),
+                    );
+                }
+                // the scope is dropped on a thread that cannot reach the resource created here
+                thread::spawn(move || drop(scope))
+                    .join()
+                    .expect("the scope to be dropped");
+                assert!(!dropped.load(Ordering::SeqCst));
+            }
+        });
+        owner.join().expect("the owning thread not to panic");
+
+        // the resource is dropped on the thread that created it, as that thread ends
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[should_panic(expected = "is already borrowed")]
+    fn borrowing_a_borrowed_resource_panics() {
+        let scope = ResourceScope::new();
+        let _entered = scope.enter();
+
+        with_thread_resource(|_: &mut usize| with_thread_resource(|_: &mut usize| ()));
+    }
+
+    #[test]
+    #[should_panic(expected = "only available while a pool thread is processing a message")]
+    fn borrowing_outside_of_a_pool_thread_panics() {
+        with_thread_resource(|_: &mut usize| ());
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread::{Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{RecvTimeoutError, Sender, bounded};
+use tracing::{Level, event};
+
+use crate::{pool_key::PoolKey, priority::Priority};
+
+/// Configures the watchdog of a [`ThreadPool`](crate::ThreadPool).
+///
+/// The watchdog is a thread owned by the pool that periodically looks at what each pool thread is
+/// doing. When a thread has been processing the same message for longer than the threshold it logs
+/// a warning (at most once per message) describing the pool item, the message type and the number of
+/// messages queued behind it, and calls the optional callback with the same details.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use messaging_thread_pool::{ThreadPool, Watchdog, samples::*};
+///
+/// let pool = ThreadPool::<Randoms>::new_with_watchdog(
+///     2,
+///     Watchdog::new(Duration::from_secs(30))
+///         .on_slow_message(|slow| eprintln!("slow {} on thread {}", slow.message_type, slow.thread_id)),
+/// );
+/// # drop(pool);
+/// ```
+pub struct Watchdog<K = u64> {
+    threshold: Duration,
+    poll_interval: Duration,
+    on_slow_message: Option<SlowMessageCallback<K>>,
+}
+
+type SlowMessageCallback<K> = Box<dyn Fn(&SlowMessage<K>) + Send>;
+
+/// The shortest time between checks of the pool threads; a shorter interval would leave the
+/// watchdog thread spinning
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+impl<K> Watchdog<K> {
+    /// Creates a watchdog that reports messages that take longer than `threshold` to process
+    ///
+    /// The pool threads are checked every tenth of the threshold (but no more than once a millisecond).
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            poll_interval: (threshold / 10).max(MIN_POLL_INTERVAL),
+            on_slow_message: None,
+        }
+    }
+
+    /// Sets how often the watchdog checks the pool threads
+    ///
+    /// Intervals of less than a millisecond are rounded up to a millisecond.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval.max(MIN_POLL_INTERVAL);
+        self
+    }
+
+    /// Sets a callback that is called (on the watchdog thread) for each slow message
+    pub fn on_slow_message(mut self, callback: impl Fn(&SlowMessage<K>) + Send + 'static) -> Self {
+        self.on_slow_message = Some(Box::new(callback));
+        self
+    }
+}
+
+impl<K> std::fmt::Debug for Watchdog<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("threshold", &self.threshold)
+            .field("poll_interval", &self.poll_interval)
+            .field("on_slow_message", &self.on_slow_message.is_some())
+            .finish()
+    }
+}
+
+/// The details of a message that a pool thread has been processing for longer than the
+/// watchdog's threshold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowMessage<K = u64> {
+    /// The id of the pool thread processing the message
+    pub thread_id: u64,
+    /// The key of the pool item processing the message; `None` for messages that target the thread
+    pub pool_item_id: Option<K>,
+    /// The type of the message (see [`PoolItem::message_type`](crate::PoolItem::message_type))
+    pub message_type: &'static str,
+    /// How long the message has been processing for
+    pub elapsed: Duration,
+    /// The number of messages waiting on the thread's lanes
+    pub queue_depth: usize,
+}
+
+/// The message that a pool thread is currently processing
+#[derive(Debug, Clone)]
+struct CurrentMessage<K> {
+    pool_item_id: Option<K>,
+    message_type: &'static str,
+    started: Instant,
+}
+
+/// The "currently processing" state of a pool thread, shared with the watchdog
+#[derive(Debug)]
+pub(crate) struct ThreadActivity<K> {
+    current: Mutex<Option<CurrentMessage<K>>>,
+}
+
+impl<K> ThreadActivity<K>
+where
+    K: PoolKey,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Records that the thread has started processing a message
+    pub(crate) fn started(&self, pool_item_id: Option<K>, message_type: &'static str) {
+        *self.current.lock().expect("no poisoned locks") = Some(CurrentMessage {
+            pool_item_id,
+            message_type,
+            started: Instant::now(),
+        });
+    }
+
+    /// Records that the thread has finished processing its message
+    pub(crate) fn finished(&self) {
+        *self.current.lock().expect("no poisoned locks") = None;
+    }
+
+    fn current(&self) -> Option<CurrentMessage<K>> {
+        self.current.lock().expect("no poisoned locks").clone()
+    }
+}
+
+/// What the watchdog observes of a single pool thread
+pub(crate) struct WatchedThread<K, T> {
+    pub(crate) thread_id: u64,
+    pub(crate) activity: Arc<ThreadActivity<K>>,
+    /// The sending ends of the thread's lanes, used to measure its queue depth; holding the
+    /// receiving ends would keep the lanes connected after the thread has ended
+    pub(crate) lanes: [Sender<T>; Priority::COUNT],
+}
+
+/// The running watchdog thread; it stops when this is dropped
+#[derive(Debug)]
+pub(crate) struct WatchdogThread {
+    _stop: Sender<()>,
+    _join_handle: JoinHandle<()>,
+}
+
+impl WatchdogThread {
+    /// Starts a watchdog thread that observes the given pool threads
+    pub(crate) fn spawn<K, T>(
+        name: &str,
+        watchdog: Watchdog<K>,
+        watched: Vec<WatchedThread<K, T>>,
+    ) -> Self
+    where
+        K: PoolKey,
+        T: Send + 'static,
+    {
+        let (stop, stopped) = bounded::<()>(0);
+        let join_handle = Builder::new()
+            .name(format!("{name}-watchdog"))
+            .spawn(move || {
+                // the start time of the last message reported on each thread
+                let mut reported: Vec<Option<Instant>> = vec![None; watched.len()];
+                while let Err(RecvTimeoutError::Timeout) =
+                    stopped.recv_timeout(watchdog.poll_interval)
+                {
+                    for (thread, reported) in watched.iter().zip(reported.iter_mut()) {
+                        if let Some(slow_message) = Self::check(&watchdog, thread, reported) {
+                            event!(
+                                Level::WARN,
+                                thread_id = slow_message.thread_id,
+                                pool_item_id = ?slow_message.pool_item_id,
+                                message_type = slow_message.message_type,
+                                elapsed_ms = slow_message.elapsed.as_millis() as u64,
+                                queue_depth = slow_message.queue_depth,
+                                "slow message"
+                            );
+                            if let Some(callback) = &watchdog.on_slow_message {
+                                callback(&slow_message);
+                            }
+                        }
+                    }
+                }
+            })
+            .expect("watchdog thread to spawn");
+
+        Self {
+            _stop: stop,
+            _join_handle: join_handle,
+        }
+    }
+
+    /// Returns the message that the thread is processing if it has exceeded the threshold and has
+    /// not already been reported
+    fn check<K, T>(
+        watchdog: &Watchdog<K>,
+        thread: &WatchedThread<K, T>,
+        reported: &mut Option<Instant>,
+    ) -> Option<SlowMessage<K>>
+    where
+        K: PoolKey,
+    {
+        let current = thread.activity.current()?;
+        let elapsed = current.started.elapsed();
+        if elapsed < watchdog.threshold || *reported == Some(current.started) {
+            return None;
+        }
+        *reported = Some(current.started);
+
+        Some(SlowMessage {
+            thread_id: thread.thread_id,
+            pool_item_id: current.pool_item_id,
+            message_type: current.message_type,
+            elapsed,
+            queue_depth: thread.lanes.iter().map(Sender::len).sum(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        thread::sleep,
+        time::Duration,
+    };
+
+    use crossbeam_channel::unbounded;
+
+    use crate::{ThreadPool, pool_item};
+
+    use super::{ThreadActivity, Watchdog, WatchdogThread, WatchedThread};
+
+    #[derive(Debug)]
+    pub struct Sleeper {
+        id: u64,
+    }
+
+    impl Sleeper {
+        pub fn new(id: u64) -> Self {
+            Self { id }
+        }
+    }
+
+    #[pool_item]
+    impl Sleeper {
+        #[messaging(SleepRequest, SleepResponse)]
+        pub fn sleep(&self, millis: u64) -> u64 {
+            sleep(Duration::from_millis(millis));
+            self.id
+        }
+
+        #[messaging(FailRequest, FailResponse)]
+        pub fn fail(&self) -> u64 {
+            panic!("pool item {} failed", self.id)
+        }
+    }
+
+    #[test]
+    fn slow_message_is_reported_once_with_its_queue_depth() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let reported_clone = Arc::clone(&reported);
+        let target = ThreadPool::<Sleeper>::new_with_watchdog(
+            1,
+            Watchdog::new(Duration::from_millis(100))
+                .poll_interval(Duration::from_millis(10))
+                .on_slow_message(move |slow| reported_clone.lock().unwrap().push(slow.clone())),
+        );
+
+        target.send_and_receive_once(SleeperInit(3)).unwrap();
+        let responses: Vec<u64> = target
+            .send_and_receive([SleepRequest(3, 400), SleepRequest(3, 0)].into_iter())
+            .unwrap()
+            .map(|response| response.result)
+            .collect();
+        assert_eq!(vec![3, 3], responses);
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(1, reported.len());
+        assert_eq!(0, reported[0].thread_id);
+        assert_eq!(Some(3), reported[0].pool_item_id);
+        assert_eq!("SleepRequest", reported[0].message_type);
+        assert_eq!(1, reported[0].queue_depth);
+        assert!(reported[0].elapsed >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn idle_and_fast_threads_are_not_reported() {
+        let activity = Arc::new(ThreadActivity::<u64>::new());
+        let (lane, _) = unbounded::<()>();
+        let watched = WatchedThread {
+            thread_id: 0,
+            activity: Arc::clone(&activity),
+            lanes: [lane.clone(), lane.clone(), lane],
+        };
+        let watchdog = Watchdog::new(Duration::from_secs(60));
+        let mut reported = None;
+
+        assert_eq!(
+            None,
+            WatchdogThread::check(&watchdog, &watched, &mut reported)
+        );
+
+        activity.started(Some(1), "FastRequest");
+        assert_eq!(
+            None,
+            WatchdogThread::check(&watchdog, &watched, &mut reported)
+        );
+
+        activity.finished();
+        assert_eq!(
+            None,
+            WatchdogThread::check(&watchdog, &watched, &mut reported)
+        );
+    }
+
+    #[test]
+    fn poll_interval_is_at_least_a_millisecond() {
+        assert_eq!(
+            Duration::from_millis(1),
+            Watchdog::<u64>::new(Duration::ZERO).poll_interval
+        );
+        assert_eq!(
+            Duration::from_millis(1),
+            Watchdog::<u64>::new(Duration::from_secs(1))
+                .poll_interval(Duration::ZERO)
+                .poll_interval
+        );
+    }
+
+    #[test]
+    fn requests_to_an_ended_thread_are_not_left_waiting() {
+        let target =
+            ThreadPool::<Sleeper>::new_with_watchdog(1, Watchdog::new(Duration::from_secs(60)));
+
+        target.send_and_receive_once(SleeperInit(0)).unwrap();
+
+        // the panic ends the pool thread
+        let failed: Vec<FailResponse> = target
+            .send_and_receive([FailRequest(0)].into_iter())
+            .unwrap()
+            .collect();
+        assert!(failed.is_empty());
+
+        // the request either cannot be sent or is dropped with the thread's lanes; either way the
+        // caller is not left waiting for a response
+        if let Ok(responses) = target.send_and_receive([SleepRequest(0, 0)].into_iter()) {
+            assert_eq!(0, responses.count());
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use messaging_thread_pool::{ThreadPool, pool_item};
+
+/// How lines are matched by a `Log`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    case_sensitive: bool,
+}
+
+/// A log whose methods borrow their parameters; the requests own them instead
+#[derive(Debug)]
+pub struct Log {
+    lines: Vec<String>,
+}
+
+#[pool_item]
+impl Log {
+    pub fn new(_id: u64) -> Self {
+        Self { lines: Vec::new() }
+    }
+
+    /// `&str` is sent as a `String`
+    #[messaging(AppendRequest, AppendResponse)]
+    pub fn append(&mut self, line: &str) -> usize {
+        self.lines.push(line.to_string());
+        self.lines.len()
+    }
+
+    /// `&[u8]` is sent as a `Vec<u8>`
+    #[messaging(AppendBytesRequest, AppendBytesResponse)]
+    pub fn append_bytes(&mut self, bytes: &[u8]) -> usize {
+        self.lines.push(String::from_utf8_lossy(bytes).into_owned());
+        self.lines.len()
+    }
+
+    /// Any other `&T` is sent as a `T`
+    #[messaging(MatchesRequest, MatchesResponse, stream)]
+    pub fn matches(&self, pattern: &str, filter: &Filter) -> Vec<String> {
+        self.lines
+            .iter()
+            .filter(|line| {
+                if filter.case_sensitive {
+                    line.contains(pattern)
+                } else {
+                    line.to_lowercase().contains(&pattern.to_lowercase())
+                }
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[test]
+pub fn example_borrowed_params() {
+    let pool = ThreadPool::<Log>::new(2);
+    pool.send_and_receive_once(LogInit(1))
+        .expect("pool to be available");
+
+    let client = LogClient::new(&pool);
+    assert_eq!(
+        1,
+        client
+            .append(1, "Started".to_string())
+            .expect("pool to be available")
+    );
+    assert_eq!(
+        2,
+        pool.send_and_receive_once(AppendBytesRequest(1, b"stopped".to_vec()))
+            .expect("pool to be available")
+            .result
+    );
+
+    let matches: Vec<String> = pool
+        .send_and_receive_stream(MatchesRequest(
+            1,
+            "STOP".to_string(),
+            Filter {
+                case_sensitive: false,
+            },
+        ))
+        .expect("pool to be available")
+        .collect();
+    assert_eq!(vec!["stopped"], matches);
+
+    let matches: Vec<String> = client
+        .matches(
+            1,
+            "STOP".to_string(),
+            Filter {
+                case_sensitive: true,
+            },
+        )
+        .expect("pool to be available")
+        .collect();
+    assert!(matches.is_empty());
+}
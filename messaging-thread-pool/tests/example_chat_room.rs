@@ -73,3 +73,29 @@ pub fn example_chat_room() {
     assert_eq!(history.len(), 1);
     assert_eq!(history[0], "Charlie: Rust is cool");
 }
+
+#[test]
+pub fn example_chat_room_stream_history() {
+    let thread_pool = ThreadPool::<ChatRoom>::new(2);
+
+    thread_pool.send_and_receive_once(ChatRoomInit(1)).unwrap();
+    for (user, text) in [("Alice", "Hello"), ("Bob", "Hi"), ("Alice", "Bye")] {
+        thread_pool
+            .send_and_receive_once(PostRequest(1, user.to_string(), text.to_string()))
+            .unwrap();
+    }
+
+    // each message is received as a separate response; the iterator ends at the end-of-stream marker
+    let history: Vec<String> = thread_pool
+        .send_and_receive_stream(StreamHistoryRequest(1))
+        .expect("request to send")
+        .collect();
+    assert_eq!(history, vec!["Alice: Hello", "Bob: Hi", "Alice: Bye"]);
+
+    // dropping the stream early cancels the rest of it
+    let first = thread_pool
+        .send_and_receive_stream(StreamHistoryRequest(1))
+        .expect("request to send")
+        .next();
+    assert_eq!(first.as_deref(), Some("Alice: Hello"));
+}
@@ -0,0 +1,90 @@
+use messaging_thread_pool::{SenderAndReceiver, SenderAndReceiverMock, ThreadPool, pool_item};
+
+/// A bank account; every `#[messaging]` method gets a method on the generated `AccountClient`
+#[derive(Debug, PartialEq)]
+pub struct Account {
+    balance: i64,
+}
+
+#[pool_item]
+impl Account {
+    pub fn new(_id: u64) -> Self {
+        Self { balance: 0 }
+    }
+
+    /// Adds the amount to the balance, returning the new balance
+    #[messaging(DepositRequest, DepositResponse)]
+    pub fn deposit(&mut self, amount: i64) -> i64 {
+        self.balance += amount;
+        self.balance
+    }
+
+    #[messaging(BalanceRequest, BalanceResponse)]
+    pub fn balance(&self) -> i64 {
+        self.balance
+    }
+
+    #[messaging(StatementRequest, StatementResponse, stream)]
+    pub fn statement(&self, lines: usize) -> Vec<String> {
+        (0..lines)
+            .map(|line| format!("{line}: {}", self.balance))
+            .collect()
+    }
+}
+
+/// Code that works against any pool holds a client rather than building requests
+fn total_balance<S: SenderAndReceiver<Account>>(client: AccountClient<'_, S>, ids: &[u64]) -> i64 {
+    client
+        .balance_batch(ids.iter().copied())
+        .expect("pool to be available")
+        .iter()
+        .map(|response| response.result)
+        .sum()
+}
+
+#[test]
+pub fn example_client_with_a_thread_pool() {
+    let pool = ThreadPool::<Account>::new(2);
+    pool.send_and_receive((0..3u64).map(AccountInit))
+        .expect("pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    let client = AccountClient::new(&pool);
+    assert_eq!(10, client.deposit(0, 10).expect("pool to be available"));
+    assert_eq!(15, client.deposit(0, 5).expect("pool to be available"));
+
+    let mut responses = client
+        .deposit_batch([(1, 20), (2, 30)])
+        .expect("pool to be available");
+    responses.sort_by_key(|response| response.id);
+    assert_eq!(
+        vec![
+            DepositResponse { id: 1, result: 20 },
+            DepositResponse { id: 2, result: 30 }
+        ],
+        responses
+    );
+
+    assert_eq!(65, total_balance(client, &[0, 1, 2]));
+    assert_eq!(
+        vec!["0: 15", "1: 15"],
+        client
+            .statement(0, 2)
+            .expect("pool to be available")
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+pub fn example_client_with_a_mock() {
+    let mock = SenderAndReceiverMock::<Account, BalanceRequest>::new_with_expected_requests(
+        vec![BalanceRequest(1), BalanceRequest(2)],
+        vec![
+            BalanceResponse { id: 1, result: 100 },
+            BalanceResponse { id: 2, result: 200 },
+        ],
+    );
+
+    assert_eq!(300, total_balance(AccountClient::new(&mock), &[1, 2]));
+    mock.assert_is_complete();
+}
@@ -0,0 +1,79 @@
+use std::sync::OnceLock;
+
+use messaging_thread_pool::{DeadlockError, PoolError, ThreadPool, pool_item};
+
+/// The pool that relays call back into
+static POOL: OnceLock<ThreadPool<Relay>> = OnceLock::new();
+
+/// A pool item that passes a request on to the next pool item on a route, waiting for its response
+#[derive(Debug)]
+pub struct Relay {
+    id: u64,
+}
+
+impl Relay {
+    pub fn new(id: u64) -> Self {
+        Self { id }
+    }
+}
+
+#[pool_item(ApiResponse = "RelayMessage")]
+impl Relay {
+    /// Returns the id of the last pool item on the route, or the error that stopped the relay
+    #[messaging(RelayRequest, RelayResponse)]
+    pub fn relay(&self, route: Vec<u64>) -> Result<u64, DeadlockError> {
+        let Some((next, rest)) = route.split_first() else {
+            return Ok(self.id);
+        };
+        match POOL
+            .get()
+            .expect("pool to be created")
+            .send_and_receive_once(RelayRequest(*next, rest.to_vec()))
+        {
+            Ok(response) => response.result,
+            Err(PoolError::Deadlock(error)) => Err(error),
+            Err(error) => panic!("unexpected error {error}"),
+        }
+    }
+}
+
+#[test]
+pub fn example_deadlock_detection() {
+    // ids 0 and 2 are on thread 0; id 1 is on thread 1
+    let pool = POOL.get_or_init(|| ThreadPool::<Relay>::new(2));
+    pool.send_and_receive((0..3u64).map(RelayInit))
+        .expect("thread pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    // calls that wait on another thread are fine
+    let response = pool
+        .send_and_receive_once(RelayRequest(0, vec![1]))
+        .expect("thread pool to be available");
+    assert_eq!(Ok(1), response.result);
+
+    // a pool item cannot wait on a pool item on its own thread
+    let response = pool
+        .send_and_receive_once(RelayRequest(0, vec![2]))
+        .expect("thread pool to be available");
+    assert!(matches!(
+        response.result,
+        Err(DeadlockError::SameThread { .. })
+    ));
+
+    // nor on a thread that is waiting on it
+    let response = pool
+        .send_and_receive_once(RelayRequest(0, vec![1, 2]))
+        .expect("thread pool to be available");
+    let Err(DeadlockError::Cycle { threads }) = response.result else {
+        panic!("expected a cycle, got {:?}", response.result);
+    };
+    assert_eq!(2, threads.len());
+    assert!(threads[0].ends_with("Relay-1"));
+    assert!(threads[1].ends_with("Relay-0"));
+
+    // the waits of the earlier relays end when they return
+    let response = pool
+        .send_and_receive_once(RelayRequest(1, vec![0]))
+        .expect("thread pool to be available");
+    assert_eq!(Ok(0), response.result);
+}
@@ -0,0 +1,111 @@
+use std::{
+    fmt::{Debug, Display},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use messaging_thread_pool::{ThreadPool, pool_item};
+
+/// The reason that a `Connection` could not be created
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectError {
+    InvalidAddress(String),
+}
+
+impl Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::InvalidAddress(address) => write!(f, "invalid address {address}"),
+        }
+    }
+}
+
+/// The reason that a message could not be sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendError {
+    pub limit: usize,
+}
+
+/// The number of failed responses seen by the `ErrorResponse` hook and the last one reported
+static ERROR_COUNT: AtomicUsize = AtomicUsize::new(0);
+static LAST_ERROR: Mutex<String> = Mutex::new(String::new());
+
+/// A connection whose `new` and `send` can both fail
+#[derive(Debug)]
+pub struct Connection {
+    limit: usize,
+    sent: usize,
+}
+
+#[pool_item(ErrorResponse = "error_response")]
+impl Connection {
+    pub fn new(_id: u64, address: &str, limit: usize) -> Result<Self, ConnectError> {
+        if !address.contains(':') {
+            return Err(ConnectError::InvalidAddress(address.to_string()));
+        }
+        Ok(Self { limit, sent: 0 })
+    }
+
+    /// Sends the bytes, returning the total sent; fails if the total would exceed the limit
+    #[messaging(SendRequest, SendResponse)]
+    pub fn send(&mut self, bytes: usize) -> Result<usize, SendError> {
+        if self.sent + bytes > self.limit {
+            return Err(SendError { limit: self.limit });
+        }
+        self.sent += bytes;
+        Ok(self.sent)
+    }
+
+    /// Sees every response of a method that returned an `Err`, whichever pool item it came from
+    fn error_response(
+        pool_item_id: &u64,
+        message_type: &'static str,
+        error: &dyn Debug,
+        _thread_start_info: Option<&mut ()>,
+    ) {
+        ERROR_COUNT.fetch_add(1, Ordering::SeqCst);
+        *LAST_ERROR.lock().unwrap() = format!("{message_type} {pool_item_id}: {error:?}");
+    }
+}
+
+#[test]
+pub fn example_fallible() {
+    let pool = ThreadPool::<Connection>::new(2);
+
+    let response = pool
+        .send_and_receive_once(ConnectionInit(1, "localhost:80".to_string(), 10))
+        .expect("pool to be available");
+    assert!(response.result().is_ok());
+
+    // the error returned by `new` is carried back in the add response
+    let response = pool
+        .send_and_receive_once(ConnectionInit(2, "localhost".to_string(), 10))
+        .expect("pool to be available");
+    assert_eq!(
+        Err(&"invalid address localhost".to_string()),
+        response.result()
+    );
+    assert_eq!(
+        Some(&ConnectError::InvalidAddress("localhost".to_string())),
+        response
+            .error()
+            .and_then(|error| error.downcast_ref::<ConnectError>())
+    );
+
+    let client = ConnectionClient::new(&pool);
+    assert_eq!(Ok(8), client.send(1, 8).expect("pool to be available"));
+    assert_eq!(0, ERROR_COUNT.load(Ordering::SeqCst));
+
+    // the error is returned to the caller and reported to the pool item's error hook
+    assert_eq!(
+        Err(SendError { limit: 10 }),
+        client.send(1, 8).expect("pool to be available")
+    );
+    assert_eq!(1, ERROR_COUNT.load(Ordering::SeqCst));
+    assert_eq!(
+        "SendRequest 1: SendError { limit: 10 }",
+        *LAST_ERROR.lock().unwrap()
+    );
+}
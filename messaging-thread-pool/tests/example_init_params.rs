@@ -0,0 +1,169 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use messaging_thread_pool::{ThreadPool, pool_item};
+
+/// The range of readings that a sensor accepts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    min: f64,
+    max: f64,
+}
+
+/// A sensor whose `new` takes more than its id; the extra parameters become fields of `SensorInit`
+#[derive(Debug)]
+pub struct Sensor {
+    name: String,
+    limits: Limits,
+    readings: Vec<f64>,
+}
+
+#[pool_item]
+impl Sensor {
+    pub fn new(_id: u64, name: &str, limits: Limits) -> Self {
+        Self {
+            name: name.to_string(),
+            limits,
+            readings: Vec::new(),
+        }
+    }
+
+    /// Records the reading if it is within the sensor's limits
+    #[messaging(RecordRequest, RecordResponse)]
+    pub fn record(&mut self, value: f64) -> bool {
+        let accepted = (self.limits.min..=self.limits.max).contains(&value);
+        if accepted {
+            self.readings.push(value);
+        }
+        accepted
+    }
+
+    #[messaging(DescribeRequest, DescribeResponse)]
+    pub fn describe(&self) -> String {
+        format!("{} ({} readings)", self.name, self.readings.len())
+    }
+}
+
+#[test]
+pub fn example_init_params() {
+    let pool = ThreadPool::<Sensor>::new(2);
+    let limits = Limits {
+        min: 0.0,
+        max: 100.0,
+    };
+    pool.send_and_receive(
+        [
+            SensorInit(1, "boiler".to_string(), limits.clone()),
+            SensorInit(
+                2,
+                "freezer".to_string(),
+                Limits {
+                    min: -40.0,
+                    ..limits
+                },
+            ),
+        ]
+        .into_iter(),
+    )
+    .expect("pool to be available")
+    .for_each(|response| assert!(response.result().is_ok()));
+
+    let client = SensorClient::new(&pool);
+    assert!(client.record(1, 80.0).expect("pool to be available"));
+    assert!(!client.record(1, -10.0).expect("pool to be available"));
+    assert!(client.record(2, -10.0).expect("pool to be available"));
+
+    assert_eq!(
+        "boiler (1 readings)",
+        client.describe(1).expect("pool to be available")
+    );
+}
+
+/// A generic pool item; its init request carries the capacity and the `PhantomData` of its type
+#[derive(Debug)]
+pub struct Recent<T>
+where
+    T: Debug + Clone + PartialEq + Send + 'static,
+{
+    capacity: usize,
+    values: Vec<T>,
+}
+
+#[pool_item]
+impl<T> Recent<T>
+where
+    T: Debug + Clone + PartialEq + Send + 'static,
+{
+    pub fn new(_id: u64, capacity: usize) -> Self {
+        Self {
+            capacity,
+            values: Vec::new(),
+        }
+    }
+
+    /// Adds the value, returning the most recent values (at most the capacity)
+    #[messaging(PushRequest, PushResponse)]
+    pub fn push(&mut self, value: T) -> Vec<T> {
+        self.values.push(value);
+        if self.values.len() > self.capacity {
+            self.values.remove(0);
+        }
+        self.values.clone()
+    }
+}
+
+#[test]
+pub fn example_generic_init_params() {
+    let pool = ThreadPool::<Recent<String>>::new(1);
+    pool.send_and_receive_once(RecentInit(1, 2, PhantomData))
+        .expect("pool to be available");
+
+    let client = RecentClient::new(&pool);
+    for value in ["a", "b"] {
+        client
+            .push(1, value.to_string())
+            .expect("pool to be available");
+    }
+    assert_eq!(
+        vec!["b", "c"],
+        client
+            .push(1, "c".to_string())
+            .expect("pool to be available")
+    );
+}
+
+/// With `NamedFields` the init request is named after the parameters of `new`
+#[derive(Debug)]
+pub struct Gauge {
+    unit: String,
+}
+
+#[pool_item(NamedFields)]
+impl Gauge {
+    pub fn new(_id: u64, unit: &str) -> Self {
+        Self {
+            unit: unit.to_string(),
+        }
+    }
+
+    #[messaging(UnitRequest, UnitResponse)]
+    pub fn unit(&self) -> String {
+        self.unit.clone()
+    }
+}
+
+#[test]
+pub fn example_named_init_params() {
+    let pool = ThreadPool::<Gauge>::new(1);
+    pool.send_and_receive_once(GaugeInit {
+        unit: "bar".to_string(),
+        id: 1,
+    })
+    .expect("pool to be available");
+
+    assert_eq!(
+        "bar",
+        GaugeClient::new(&pool)
+            .unit(1)
+            .expect("pool to be available")
+    );
+}
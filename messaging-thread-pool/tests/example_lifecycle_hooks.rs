@@ -0,0 +1,85 @@
+use std::sync::Mutex;
+
+use messaging_thread_pool::{
+    MessageError, RemovalReason, RemovePoolItemRequest, ThreadPool, pool_item,
+};
+
+/// The lifecycle events of every session, in the order that they happened
+static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(event: String) {
+    EVENTS.lock().expect("no poisoned locks").push(event);
+}
+
+/// A pool item that records its lifecycle and survives panics in its message handlers
+#[derive(Debug)]
+pub struct Session {
+    id: u64,
+    requests: usize,
+}
+
+#[pool_item(Added = "added", Removed = "removed", MessageError = "failed")]
+impl Session {
+    pub fn new(id: u64) -> Self {
+        Self { id, requests: 0 }
+    }
+
+    pub fn added(&mut self) {
+        record(format!("added {}", self.id));
+    }
+
+    pub fn removed(&mut self, reason: RemovalReason) {
+        record(format!("removed {} {reason:?}", self.id));
+    }
+
+    pub fn failed(&mut self, error: MessageError<u64>) {
+        // the panic is handled; the session and its thread carry on
+        record(format!("failed {}", error.message().unwrap_or_default()));
+    }
+
+    #[messaging(TouchRequest, TouchResponse)]
+    pub fn touch(&mut self, fail: bool) -> usize {
+        self.requests += 1;
+        if fail {
+            panic!("touch {} failed", self.requests);
+        }
+        self.requests
+    }
+}
+
+#[test]
+pub fn example_lifecycle_hooks() {
+    let pool = ThreadPool::<Session>::new(1);
+    pool.send_and_receive((1..3u64).map(SessionInit))
+        .expect("thread pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    pool.send_and_receive_once(RemovePoolItemRequest(1))
+        .expect("thread pool to be available");
+
+    // the message that panicked is not answered
+    assert_eq!(
+        0,
+        pool.send_and_receive([TouchRequest(2, true)].into_iter())
+            .expect("thread pool to be available")
+            .count()
+    );
+    // but the session is still there
+    let response = pool
+        .send_and_receive_once(TouchRequest(2, false))
+        .expect("thread pool to be available");
+    assert_eq!(2, response.result);
+
+    pool.shutdown();
+
+    assert_eq!(
+        vec![
+            "added 1",
+            "added 2",
+            "removed 1 Removed",
+            "failed touch 1 failed",
+            "removed 2 Shutdown",
+        ],
+        *EVENTS.lock().expect("no poisoned locks")
+    );
+}
@@ -0,0 +1,128 @@
+use messaging_thread_pool::{
+    Routed, SenderAndReceiver, SenderAndReceiverMock, ThreadPool, pool_item,
+};
+
+/// A storage bin; all of the bins in an aisle are co-located on one thread
+///
+/// Bin ids are of the form `aisle * 100 + n`
+#[derive(Debug, PartialEq)]
+pub struct Bin {
+    id: u64,
+    count: u32,
+}
+
+#[pool_item]
+impl Bin {
+    pub fn new(id: u64) -> Self {
+        Self { id, count: 0 }
+    }
+
+    #[messaging(StockRequest, StockResponse, routing_key = aisle)]
+    pub fn stock(&self, aisle: u64) -> u32 {
+        assert_eq!(aisle, self.id / 100);
+        self.count
+    }
+
+    #[messaging(RestockRequest, RestockResponse, routing_key = aisle)]
+    pub fn restock(&mut self, aisle: u64, count: u32) -> u32 {
+        assert_eq!(aisle, self.id / 100);
+        self.count += count;
+        self.count
+    }
+
+    /// Moves stock from the first of the targeted bins to the second
+    #[messaging(MoveRequest, MoveResponse, multi, routing_key = aisle)]
+    pub fn move_stock(bins: &mut [&mut Self], aisle: u64, count: u32) -> bool {
+        assert!(bins.iter().all(|bin| bin.id / 100 == aisle));
+        if bins[0].count < count {
+            return false;
+        }
+        bins[0].count -= count;
+        bins[1].count += count;
+        true
+    }
+}
+
+/// Restocks a bin and reports the stock of another in one round trip
+fn restock_and_check<S>(pool: &S, restock: u64, check: u64, aisle: u64) -> (u32, u32)
+where
+    S: SenderAndReceiver<Bin>,
+{
+    let requests: [BinApi; 2] = [
+        RestockRequest(restock, aisle, 10).into(),
+        StockRequest(check, aisle).into(),
+    ];
+    let (mut restocked, mut stock) = (0, 0);
+    for response in pool
+        .send_and_receive_api(requests.into_iter())
+        .expect("pool to be available")
+    {
+        match response {
+            BinResponse::RestockResponse(response) => restocked = response.result,
+            BinResponse::StockResponse(response) => stock = response.result,
+            response => panic!("unexpected response {response:?}"),
+        }
+    }
+    (restocked, stock)
+}
+
+#[test]
+pub fn example_mixed_batch() {
+    let pool = ThreadPool::<Bin>::new(4);
+    for (id, aisle) in [(701, 7), (702, 7), (801, 8)] {
+        pool.send_and_receive_once(Routed::new(aisle, BinInit(id)))
+            .expect("pool to be available");
+    }
+
+    // requests of different types (including multi item requests) go in one batch; each is routed
+    // by its own routing key and requests for the same thread are processed in order
+    let requests: [BinApi; 4] = [
+        RestockRequest(701, 7, 50).into(),
+        RestockRequest(801, 8, 5).into(),
+        MoveRequest(vec![701, 702], 7, 20).into(),
+        StockRequest(801, 8).into(),
+    ];
+    let mut responses: Vec<BinResponse> = pool
+        .send_and_receive_api(requests.into_iter())
+        .expect("pool to be available")
+        .collect();
+    responses.sort_by_key(|response| format!("{response:?}"));
+
+    assert_eq!(
+        vec![
+            BinResponse::MoveResponse(MoveResponse {
+                id: 701,
+                result: Ok(true)
+            }),
+            BinResponse::RestockResponse(RestockResponse {
+                id: 701,
+                result: 50
+            }),
+            BinResponse::RestockResponse(RestockResponse { id: 801, result: 5 }),
+            BinResponse::StockResponse(StockResponse { id: 801, result: 5 }),
+        ],
+        responses
+    );
+
+    assert_eq!((40, 20), restock_and_check(&pool, 701, 702, 7));
+}
+
+#[test]
+pub fn example_mixed_batch_with_a_mock() {
+    let mock = SenderAndReceiverMock::<Bin, BinApi>::new_with_expected_requests(
+        vec![
+            RestockRequest(101, 1, 10).into(),
+            StockRequest(102, 1).into(),
+        ],
+        vec![
+            BinResponse::RestockResponse(RestockResponse {
+                id: 101,
+                result: 10,
+            }),
+            BinResponse::StockResponse(StockResponse { id: 102, result: 3 }),
+        ],
+    );
+
+    assert_eq!((10, 3), restock_and_check(&mock, 101, 102, 1));
+    mock.assert_is_complete();
+}
@@ -0,0 +1,145 @@
+use messaging_thread_pool::{IdTargeted, ThreadPool, pool_item};
+
+/// A forum thread whose requests have fields named after the parameters of its methods
+#[derive(Debug)]
+pub struct Topic {
+    posts: Vec<(String, String)>,
+}
+
+#[pool_item(NamedFields)]
+impl Topic {
+    pub fn new(_id: u64) -> Self {
+        Self { posts: Vec::new() }
+    }
+
+    /// Adds a post, returning the number of posts in the topic
+    #[messaging(PostRequest, PostResponse)]
+    pub fn post(&mut self, user: String, text: String) -> usize {
+        self.posts.push((user, text));
+        self.posts.len()
+    }
+
+    /// A parameter called `id` does not clash with the topic's id, which becomes `pool_item_id`
+    #[messaging(QuoteRequest, QuoteResponse)]
+    pub fn quote(&self, id: usize) -> Option<String> {
+        self.posts
+            .get(id)
+            .map(|(user, text)| format!("{user} wrote \"{text}\""))
+    }
+
+    #[messaging(PostsByRequest, PostsByResponse, stream)]
+    pub fn posts_by(&self, user: String) -> Vec<String> {
+        self.posts
+            .iter()
+            .filter(|(author, _)| *author == user)
+            .map(|(_, text)| text.clone())
+            .collect()
+    }
+}
+
+#[test]
+pub fn example_named_fields() {
+    let pool = ThreadPool::<Topic>::new(2);
+    pool.send_and_receive_once(TopicInit(1))
+        .expect("pool to be available");
+
+    // the fields can be given in any order
+    let response = pool
+        .send_and_receive_once(PostRequest {
+            text: "Hello everyone!".to_string(),
+            user: "Alice".to_string(),
+            id: 1,
+        })
+        .expect("pool to be available");
+    assert_eq!(1, response.result);
+
+    // or passed to the constructor in the order of the method's parameters
+    let request = PostRequest::new(1, "Bob".to_string(), "Hi Alice!".to_string());
+    assert_eq!(1, request.id());
+    assert_eq!("Bob", request.user);
+    assert_eq!(
+        2,
+        pool.send_and_receive_once(request)
+            .expect("pool to be available")
+            .result
+    );
+
+    let response = pool
+        .send_and_receive_once(QuoteRequest {
+            pool_item_id: 1,
+            id: 1,
+        })
+        .expect("pool to be available");
+    assert_eq!(Some("Bob wrote \"Hi Alice!\"".to_string()), response.result);
+
+    let posts: Vec<String> = pool
+        .send_and_receive_stream(PostsByRequest {
+            id: 1,
+            user: "Alice".to_string(),
+        })
+        .expect("pool to be available")
+        .collect();
+    assert_eq!(vec!["Hello everyone!"], posts);
+
+    // the generated client builds the named requests
+    let client = TopicClient::new(&pool);
+    assert_eq!(
+        3,
+        client
+            .post(1, "Alice".to_string(), "Welcome Bob".to_string())
+            .expect("pool to be available")
+    );
+}
+
+/// A pool item where only one method opts in to named fields
+#[derive(Debug)]
+pub struct Shelf {
+    books: Vec<String>,
+}
+
+#[pool_item]
+impl Shelf {
+    pub fn new(_id: u64) -> Self {
+        Self { books: Vec::new() }
+    }
+
+    #[messaging(ShelveRequest, ShelveResponse, named_fields, routing_key = library)]
+    pub fn shelve(&mut self, library: u64, title: String) -> usize {
+        let _ = library;
+        self.books.push(title);
+        self.books.len()
+    }
+
+    #[messaging(CountRequest, CountResponse)]
+    pub fn count(&self) -> usize {
+        self.books.len()
+    }
+}
+
+#[test]
+pub fn example_named_fields_for_one_method() {
+    let pool = ThreadPool::<Shelf>::new(2);
+    pool.send_and_receive_once(ShelfInit(3))
+        .expect("pool to be available");
+
+    let request = ShelveRequest {
+        id: 3,
+        library: 3,
+        title: "Dune".to_string(),
+    };
+    assert_eq!(3, request.routing_key());
+    assert_eq!(
+        1,
+        pool.send_and_receive_once(request)
+            .expect("pool to be available")
+            .result
+    );
+
+    // the other methods keep their tuple structs
+    assert_eq!(
+        1,
+        pool.send_and_receive_once(CountRequest(3))
+            .expect("pool to be available")
+            .result
+    );
+}
@@ -0,0 +1,251 @@
+use std::{fmt::Debug, sync::Mutex};
+
+use messaging_thread_pool::{
+    Implements, SenderAndReceiver, SenderAndReceiverMock, ThreadPool, pool_item, pool_protocol,
+};
+
+/// Reports whether a pool item is healthy; spoken by every kind of pool item below
+#[pool_protocol]
+pub trait Health {
+    #[messaging(HealthRequest, HealthResponse)]
+    fn health(&self) -> bool;
+
+    /// Marks the pool item as failed with the given reason
+    #[messaging(FailRequest, FailResponse, priority = high)]
+    fn fail(&mut self, reason: &str);
+}
+
+/// Copies out the state of a pool item
+#[pool_protocol(NamedFields)]
+pub trait Snapshot {
+    #[messaging(SnapshotRequest, SnapshotResponse)]
+    fn snapshot(&self, limit: usize) -> Vec<String>;
+}
+
+/// A disk, identified by its number
+#[derive(Debug, PartialEq)]
+pub struct Disk {
+    files: Vec<String>,
+    failure: Option<String>,
+}
+
+impl Health for Disk {
+    fn health(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    fn fail(&mut self, reason: &str) {
+        self.failure = Some(reason.to_string());
+    }
+}
+
+impl Snapshot for Disk {
+    fn snapshot(&self, limit: usize) -> Vec<String> {
+        self.files.iter().take(limit).cloned().collect()
+    }
+}
+
+#[pool_item(Implements = Health, Implements = Snapshot)]
+impl Disk {
+    pub fn new(_id: u64) -> Self {
+        Self {
+            files: Vec::new(),
+            failure: None,
+        }
+    }
+
+    /// The pool item's own methods sit alongside the protocols
+    #[messaging(WriteRequest, WriteResponse)]
+    pub fn write(&mut self, file: &str) -> usize {
+        self.files.push(file.to_string());
+        self.files.len()
+    }
+}
+
+/// A cache, identified by its name; it only speaks the `Health` protocol
+#[derive(Debug)]
+pub struct Cache {
+    failed: bool,
+}
+
+impl Health for Cache {
+    fn health(&self) -> bool {
+        !self.failed
+    }
+
+    fn fail(&mut self, _reason: &str) {
+        self.failed = true;
+    }
+}
+
+#[pool_item(Key = String, Implements = Health)]
+impl Cache {
+    pub fn new(_id: String) -> Self {
+        Self { failed: false }
+    }
+}
+
+/// Generic code can use the protocol with the pool of any pool item that implements it
+fn unhealthy<P, S>(pool: &S, ids: impl IntoIterator<Item = P::Key>) -> Vec<P::Key>
+where
+    P: Implements<HealthProtocol>,
+    S: SenderAndReceiver<P>,
+{
+    let mut unhealthy: Vec<P::Key> = pool
+        .send_and_receive(ids.into_iter().map(HealthRequest))
+        .expect("pool to be available")
+        .filter(|response: &HealthResponse<P::Key>| !response.result)
+        .map(|response| response.id)
+        .collect();
+    unhealthy.sort_by_key(|id| format!("{id:?}"));
+    unhealthy
+}
+
+#[test]
+pub fn example_protocol() {
+    let disks = ThreadPool::<Disk>::new(2);
+    disks
+        .send_and_receive((1..=3u64).map(DiskInit))
+        .expect("pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+    let caches = ThreadPool::<Cache>::new(2);
+    caches
+        .send_and_receive(
+            ["eu", "us"]
+                .map(|name| CacheInit(name.to_string()))
+                .into_iter(),
+        )
+        .expect("pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    disks
+        .send_and_receive_once(FailRequest(2, "bad sector".to_string()))
+        .expect("pool to be available");
+    caches
+        .send_and_receive_once(FailRequest("us".to_string(), "evicted".to_string()))
+        .expect("pool to be available");
+
+    assert_eq!(vec![2], unhealthy(&disks, 1..=3));
+    assert_eq!(
+        vec!["us".to_string()],
+        unhealthy(&caches, ["eu".to_string(), "us".to_string()])
+    );
+
+    // the pool item's own requests and the protocols' requests go to the same pool items
+    let client = DiskClient::new(&disks);
+    client
+        .write(1, "a.txt".to_string())
+        .expect("pool to be available");
+    client
+        .write(1, "b.txt".to_string())
+        .expect("pool to be available");
+    let response = disks
+        .send_and_receive_once(SnapshotRequest { id: 1, limit: 1 })
+        .expect("pool to be available");
+    assert_eq!(vec!["a.txt"], response.result);
+
+    // one batch can mix the pool item's own requests with those of its protocols
+    let requests = [
+        DiskApi::from(WriteRequest(3, "c.txt".to_string())),
+        DiskApi::Health(HealthApi::HealthRequest(HealthRequest(3))),
+    ];
+    let mut responses = 0;
+    for response in disks
+        .send_and_receive_api(requests.into_iter())
+        .expect("pool to be available")
+    {
+        match response {
+            DiskResponse::WriteResponse(response) => assert_eq!(1, response.result),
+            DiskResponse::Health(HealthApi::HealthResponse(response)) => assert!(response.result),
+            response => panic!("unexpected response {response:?}"),
+        }
+        responses += 1;
+    }
+    assert_eq!(2, responses);
+}
+
+#[test]
+pub fn example_protocol_with_a_mock() {
+    let mock = SenderAndReceiverMock::<Disk, HealthRequest>::new_with_expected_requests(
+        vec![HealthRequest(1), HealthRequest(2)],
+        vec![
+            HealthResponse {
+                id: 1,
+                result: false,
+            },
+            HealthResponse {
+                id: 2,
+                result: true,
+            },
+        ],
+    );
+
+    assert_eq!(vec![1], unhealthy(&mock, [1, 2]));
+    mock.assert_is_complete();
+}
+
+/// Reserves capacity; a reservation that does not fit fails
+#[pool_protocol]
+pub trait Quota {
+    #[messaging(ReserveRequest, ReserveResponse)]
+    fn reserve(&mut self, amount: usize) -> Result<usize, String>;
+}
+
+/// The errors seen by the `ErrorResponse` hook of `Bucket`
+static BUCKET_ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A bucket of limited capacity
+#[derive(Debug)]
+pub struct Bucket {
+    free: usize,
+}
+
+impl Quota for Bucket {
+    fn reserve(&mut self, amount: usize) -> Result<usize, String> {
+        self.free = self
+            .free
+            .checked_sub(amount)
+            .ok_or_else(|| format!("only {} free", self.free))?;
+        Ok(self.free)
+    }
+}
+
+#[pool_item(Implements = Quota, ErrorResponse = "error_response")]
+impl Bucket {
+    pub fn new(_id: u64) -> Self {
+        Self { free: 10 }
+    }
+
+    fn error_response(
+        pool_item_id: &u64,
+        message_type: &'static str,
+        error: &dyn Debug,
+        _thread_start_info: Option<&mut ()>,
+    ) {
+        BUCKET_ERRORS
+            .lock()
+            .unwrap()
+            .push(format!("{message_type} {pool_item_id}: {error:?}"));
+    }
+}
+
+#[test]
+pub fn example_protocol_errors_are_reported() {
+    let pool = ThreadPool::<Bucket>::new(1);
+    pool.send_and_receive_once(BucketInit(1))
+        .expect("pool to be available");
+
+    let reserve = |amount| {
+        pool.send_and_receive_once(ReserveRequest(1, amount))
+            .expect("pool to be available")
+            .result
+    };
+    assert_eq!(Ok(4), reserve(6));
+    assert_eq!(Err("only 4 free".to_string()), reserve(6));
+
+    // the protocol's error reaches the pool item's error hook
+    assert_eq!(
+        vec!["ReserveRequest 1: \"only 4 free\"".to_string()],
+        *BUCKET_ERRORS.lock().unwrap()
+    );
+}
@@ -0,0 +1,129 @@
+use std::sync::Mutex;
+
+use messaging_thread_pool::{RequestResponse, ThreadPool, ThreadRequestResponse, pool_item};
+
+/// The pool items that each message was processed for, recorded before and after processing
+static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(event: String) {
+    EVENTS.lock().expect("no poisoned locks").push(event);
+}
+
+/// A shard of a table; shards with ids in the same hundred are kept on the same thread
+#[derive(Debug)]
+pub struct Shard {
+    rows: Vec<String>,
+}
+
+#[pool_item(
+    IdThreadRouter = "route",
+    IdNotFound = "missing",
+    ThreadStartInfo = "usize",
+    ThreadStart = "start",
+    PreProcess = "enter",
+    PostProcess = "exit"
+)]
+impl Shard {
+    pub fn new(_id: u64) -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    #[messaging(InsertRequest, InsertResponse)]
+    pub fn insert(&mut self, row: &str) -> Option<usize> {
+        self.rows.push(row.to_string());
+        Some(self.rows.len())
+    }
+
+    /// The thread that the shard lives on
+    #[messaging(ThreadRequest, ThreadResponse)]
+    pub fn thread(&self) -> Option<String> {
+        Some(format!("{:?}", std::thread::current().id()))
+    }
+
+    pub fn route(id: u64, thread_count: usize) -> u64 {
+        (id / 100) % thread_count as u64
+    }
+
+    /// Requests for a shard that does not exist are answered with `None` rather than a panic
+    pub fn missing(request: &ShardApi) -> ThreadRequestResponse<Self> {
+        match request {
+            ShardApi::InsertRequest(RequestResponse::Request(request)) => InsertResponse {
+                id: request.0,
+                result: None,
+            }
+            .into(),
+            ShardApi::ThreadRequest(RequestResponse::Request(request)) => ThreadResponse {
+                id: request.0,
+                result: None,
+            }
+            .into(),
+            _ => panic!("only requests are sent to pool items"),
+        }
+    }
+
+    /// Each thread counts the messages it has processed
+    pub fn start() -> Option<usize> {
+        Some(0)
+    }
+
+    pub fn enter(pool_item_id: u64, processed: &mut usize) {
+        *processed += 1;
+        record(format!("enter {pool_item_id} ({processed})"));
+    }
+
+    pub fn exit(pool_item_id: u64, _processed: &mut usize) {
+        record(format!("exit {pool_item_id}"));
+    }
+}
+
+#[test]
+pub fn example_routing_hooks() {
+    let pool = ThreadPool::<Shard>::new(2);
+    pool.send_and_receive([1u64, 2, 101].map(ShardInit).into_iter())
+        .expect("pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+    EVENTS.lock().expect("no poisoned locks").clear();
+
+    // shards 1 and 2 share a thread, shard 101 is on the other one
+    let thread = |id| {
+        pool.send_and_receive_once(ThreadRequest(id))
+            .expect("pool to be available")
+            .result
+            .expect("shard to exist")
+    };
+    assert_eq!(thread(1), thread(2));
+    assert_ne!(thread(1), thread(101));
+
+    assert_eq!(
+        Some(1),
+        pool.send_and_receive_once(InsertRequest(2, "row".to_string()))
+            .expect("pool to be available")
+            .result
+    );
+    assert_eq!(
+        None,
+        pool.send_and_receive_once(InsertRequest(3, "row".to_string()))
+            .expect("pool to be available")
+            .result
+    );
+
+    assert_eq!(
+        vec![
+            // two inits and then the thread requests for 1 and 2 were processed on one thread
+            "enter 1 (3)",
+            "exit 1",
+            "enter 2 (4)",
+            "exit 2",
+            "enter 1 (5)",
+            "exit 1",
+            "enter 101 (2)",
+            "exit 101",
+            "enter 2 (6)",
+            "exit 2",
+            // the hooks are called for missing pool items too
+            "enter 3 (7)",
+            "exit 3",
+        ],
+        *EVENTS.lock().expect("no poisoned locks")
+    );
+}
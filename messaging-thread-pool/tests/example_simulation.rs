@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use messaging_thread_pool::{
+    id_provider::id_provider_mutex::IdProviderMutex,
+    samples::*,
+    simulation::{SimulatedPool, Simulation},
+    *,
+};
+
+/// Marker type that makes the Randoms owned by a RandomsBatch live in a simulated pool
+#[derive(Debug)]
+struct SimulatedRandomsPool;
+impl InnerThreadPool for SimulatedRandomsPool {
+    type ThreadPool = SimulatedPool<Randoms>;
+}
+
+/// Runs the two level RandomsBatch example with both levels simulated under a single seed.
+/// Returns the sums of sums and the virtual time taken.
+fn run_nested(seed: u64) -> (Vec<u128>, Duration) {
+    let simulation = Simulation::new(seed);
+
+    // both pools share the simulation so the whole hierarchy is replayed from the one seed
+    let randoms_batch_pool =
+        SimulatedPool::<RandomsBatch<SimulatedRandomsPool>>::new(3, simulation.clone());
+    let randoms_pool = Arc::new(SimulatedPool::<Randoms>::new(4, simulation.clone()));
+    let id_provider = Arc::new(IdProviderMutex::new(0));
+
+    randoms_batch_pool
+        .send_and_receive((0..5).map(|id| RandomsBatchAddRequest {
+            id,
+            number_of_contained_randoms: 10,
+            id_provider: id_provider.clone(),
+            randoms_thread_pool: Arc::clone(&randoms_pool),
+        }))
+        .expect("simulated pool to be available")
+        .for_each(|response: AddResponse| assert!(response.result().is_ok()));
+
+    let sum_of_sums = randoms_batch_pool
+        .send_and_receive((0..5).map(|id| SumOfSumsRequest(id, std::marker::PhantomData)))
+        .expect("simulated pool to be available")
+        .map(|response: SumOfSumsResponse<SimulatedRandomsPool>| response.result)
+        .collect();
+
+    (sum_of_sums, simulation.clock().now())
+}
+
+#[test]
+pub fn example_nested_pools_replay_from_seed() {
+    let first = run_nested(1234);
+    let replay = run_nested(1234);
+
+    assert_eq!(first, replay);
+    // 5 batch adds + 50 randoms adds + 5 sum of sums + 50 sums
+    assert_eq!(
+        110 * messaging_thread_pool::simulation::DEFAULT_TICK,
+        first.1
+    );
+}
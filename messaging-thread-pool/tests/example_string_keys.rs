@@ -0,0 +1,76 @@
+use messaging_thread_pool::{
+    RemovePoolItemRequest, ThreadPool, id_being_processed, key_being_processed, pool_item,
+};
+
+/// A document identified by its path rather than by a number
+#[derive(Debug)]
+pub struct Document {
+    path: String,
+    lines: Vec<String>,
+}
+
+impl Document {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            lines: Vec::new(),
+        }
+    }
+}
+
+#[pool_item(Key = "String")]
+impl Document {
+    #[messaging(AppendRequest, AppendResponse)]
+    pub fn append(&mut self, line: String) -> usize {
+        self.lines.push(line);
+        self.lines.len()
+    }
+
+    #[messaging(PathRequest, PathResponse)]
+    pub fn path(&self) -> (Option<String>, Option<u64>) {
+        assert_eq!(Some(&self.path), key_being_processed::<String>().as_ref());
+        (key_being_processed::<String>(), id_being_processed())
+    }
+}
+
+#[test]
+pub fn example_string_keys() {
+    let thread_pool = ThreadPool::<Document>::new(3);
+
+    let readme = "docs/readme.md".to_string();
+    let changelog = "docs/changelog.md".to_string();
+
+    thread_pool
+        .send_and_receive(
+            [
+                DocumentInit(readme.clone()),
+                DocumentInit(changelog.clone()),
+            ]
+            .into_iter(),
+        )
+        .expect("thread pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    let response = thread_pool
+        .send_and_receive_once(AppendRequest(readme.clone(), "# Readme".to_string()))
+        .expect("thread pool to be available");
+    assert_eq!(readme, response.id);
+    assert_eq!(1, response.result);
+
+    let response = thread_pool
+        .send_and_receive_once(AppendRequest(readme.clone(), "more".to_string()))
+        .expect("thread pool to be available");
+    assert_eq!(2, response.result);
+
+    // the key of the pool item is available while it is being processed; the u64 id is not
+    let response = thread_pool
+        .send_and_receive_once(PathRequest(changelog.clone()))
+        .expect("thread pool to be available");
+    assert_eq!((Some(changelog.clone()), None), response.result);
+
+    let response = thread_pool
+        .send_and_receive_once(RemovePoolItemRequest(changelog.clone()))
+        .expect("thread pool to be available");
+    assert_eq!(changelog, response.id());
+    assert!(response.item_existed());
+}
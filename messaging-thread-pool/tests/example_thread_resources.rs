@@ -0,0 +1,89 @@
+use std::{collections::HashSet, rc::Rc};
+
+use messaging_thread_pool::{InlinePool, SenderAndReceiver, ThreadPool, pool_item};
+
+/// Interns strings for every pool item on a thread; `Rc` means it cannot be shared between threads
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Rc<str>>,
+}
+
+impl Interner {
+    fn intern(&mut self, value: &str) -> Rc<str> {
+        if let Some(interned) = self.strings.get(value) {
+            return Rc::clone(interned);
+        }
+        let interned: Rc<str> = Rc::from(value);
+        self.strings.insert(Rc::clone(&interned));
+        interned
+    }
+}
+
+/// A document that holds its words as strings interned on its thread
+#[derive(Debug)]
+pub struct Document {
+    words: Vec<Rc<str>>,
+}
+
+#[pool_item]
+impl Document {
+    pub fn new(_id: u64) -> Self {
+        Self { words: Vec::new() }
+    }
+
+    /// Adds the words to the document, returning the number of distinct words on its thread
+    #[messaging(WriteRequest, WriteResponse)]
+    pub fn write(&mut self, text: String, #[thread_resource] interner: &mut Interner) -> usize {
+        self.words
+            .extend(text.split_whitespace().map(|word| interner.intern(word)));
+        interner.strings.len()
+    }
+
+    /// Returns the number of the document's words that are shared with another document
+    #[messaging(SharedWordsRequest, SharedWordsResponse)]
+    pub fn shared_words(&self) -> usize {
+        // one reference from the interner and one from this document
+        self.words
+            .iter()
+            .filter(|word| Rc::strong_count(word) > 2)
+            .count()
+    }
+}
+
+fn write_documents(pool: &impl SenderAndReceiver<Document>) -> Vec<usize> {
+    pool.send_and_receive((0..4u64).map(DocumentInit))
+        .expect("pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    pool.send_and_receive(
+        [
+            WriteRequest(0, "the quick brown fox".to_string()),
+            WriteRequest(1, "the lazy dog".to_string()),
+            WriteRequest(2, "the quick dog".to_string()),
+            WriteRequest(3, "a lazy fox".to_string()),
+        ]
+        .into_iter(),
+    )
+    .expect("pool to be available")
+    .map(|response| response.result)
+    .collect()
+}
+
+#[test]
+pub fn example_thread_resources() {
+    // documents 0 and 2 share thread 0, documents 1 and 3 share thread 1
+    let pool = ThreadPool::<Document>::new(2);
+    let mut distinct_words = write_documents(&pool);
+    distinct_words.sort();
+    // thread 0 sees "the quick brown fox" and then "dog"; thread 1 "the lazy dog" and then "a fox"
+    assert_eq!(vec![3, 4, 5, 5], distinct_words);
+
+    let response = pool
+        .send_and_receive_once(SharedWordsRequest(2))
+        .expect("pool to be available");
+    assert_eq!(2, response.result);
+
+    // an inline pool has a single thread, so every document shares one interner
+    let pool = InlinePool::<Document>::new();
+    assert_eq!(vec![4, 6, 6, 7], write_documents(&pool));
+}
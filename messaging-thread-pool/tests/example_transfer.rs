@@ -0,0 +1,127 @@
+use messaging_thread_pool::{MultiItemError, PoolKey, Routed, ThreadPool, pool_item};
+
+/// A bank account; all of the accounts belonging to a customer are co-located on one thread
+///
+/// Account ids are of the form `customer * 100 + n`
+#[derive(Debug)]
+pub struct Account {
+    id: u64,
+    balance: i64,
+}
+
+impl Account {
+    pub fn new(id: u64) -> Self {
+        Self { id, balance: 100 }
+    }
+}
+
+#[pool_item]
+impl Account {
+    #[messaging(BalanceRequest, BalanceResponse, routing_key = customer)]
+    pub fn balance(&self, customer: u64) -> i64 {
+        assert_eq!(customer, self.id / 100);
+        self.balance
+    }
+
+    /// Moves money between the first and second of the targeted accounts in one step
+    #[messaging(TransferRequest, TransferResponse, multi, routing_key = customer)]
+    pub fn transfer(accounts: &mut [&mut Self], customer: u64, amount: i64) -> bool {
+        assert!(accounts.iter().all(|account| account.id / 100 == customer));
+        if accounts[0].balance < amount {
+            return false;
+        }
+        accounts[0].balance -= amount;
+        accounts[1].balance += amount;
+        true
+    }
+}
+
+#[test]
+pub fn example_transfer_between_co_located_accounts() {
+    let thread_pool = ThreadPool::<Account>::new(4);
+
+    // accounts 701 and 702 belong to customer 7; account 801 belongs to customer 8
+    for (id, customer) in [(701, 7), (702, 7), (801, 8)] {
+        thread_pool
+            .send_and_receive_once(Routed::new(customer, AccountInit(id)))
+            .expect("thread pool to be available");
+    }
+
+    let response = thread_pool
+        .send_and_receive_once(TransferRequest(vec![701, 702], 7, 30))
+        .expect("thread pool to be available");
+    assert_eq!(701, response.id);
+    assert_eq!(Ok(true), response.result);
+
+    let balances: Vec<i64> = thread_pool
+        .send_and_receive([BalanceRequest(701, 7), BalanceRequest(702, 7)].into_iter())
+        .expect("thread pool to be available")
+        .map(|response| response.result)
+        .collect();
+    assert_eq!(vec![70, 130], balances);
+
+    // account 801 lives on a different thread so the transfer is rejected and nothing changes
+    let response = thread_pool
+        .send_and_receive_once(TransferRequest(vec![701, 801], 7, 30))
+        .expect("thread pool to be available");
+    assert_eq!(Err(MultiItemError::NotCoLocated(801)), response.result);
+
+    let response = thread_pool
+        .send_and_receive_once(BalanceRequest(701, 7))
+        .expect("thread pool to be available");
+    assert_eq!(70, response.result);
+}
+
+/// An account number; deliberately not `Default`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AccountNumber(&'static str);
+
+impl PoolKey for AccountNumber {}
+
+/// A ledger keyed by an account number
+#[derive(Debug)]
+pub struct Ledger {
+    number: AccountNumber,
+}
+
+impl Ledger {
+    pub fn new(number: AccountNumber) -> Self {
+        Self { number }
+    }
+}
+
+#[pool_item(Key = "AccountNumber")]
+impl Ledger {
+    #[messaging(ReconcileRequest, ReconcileResponse, multi)]
+    pub fn reconcile(ledgers: &mut [&mut Self]) -> Vec<&'static str> {
+        ledgers.iter().map(|ledger| ledger.number.0).collect()
+    }
+}
+
+#[test]
+pub fn example_multi_item_request_without_a_default_key() {
+    let thread_pool = ThreadPool::<Ledger>::new(1);
+
+    thread_pool
+        .send_and_receive(
+            [
+                LedgerInit(AccountNumber("a")),
+                LedgerInit(AccountNumber("b")),
+            ]
+            .into_iter(),
+        )
+        .expect("thread pool to be available")
+        .for_each(|response| assert!(response.result().is_ok()));
+
+    // the request is identified by the first of its ids
+    let request = ReconcileRequest::new(vec![AccountNumber("b"), AccountNumber("a")])
+        .expect("the request targets pool items");
+    let response = thread_pool
+        .send_and_receive_once(request)
+        .expect("thread pool to be available");
+    assert_eq!(AccountNumber("b"), response.id);
+    assert_eq!(Ok(vec!["b", "a"]), response.result);
+
+    // a multi item request must target at least one pool item
+    assert_eq!(Err(MultiItemError::NoIds), ReconcileRequest::new(vec![]));
+}
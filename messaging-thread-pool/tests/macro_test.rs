@@ -1,3 +1,4 @@
+use messaging_thread_pool::StreamSink;
 use messaging_thread_pool_macros::pool_item;
 
 #[derive(Debug)]
@@ -17,6 +18,28 @@ impl MacroTest {
     pub fn test_method(&self, _arg: u64) -> u64 {
         self.id
     }
+
+    #[messaging(UrgentRequest, UrgentResponse, priority = high)]
+    pub fn urgent_method(&self) -> u64 {
+        self.id
+    }
+
+    #[messaging(GroupThreadRequest, GroupThreadResponse, routing_key = group)]
+    pub fn group_thread(&self, group: u64) -> String {
+        format!(
+            "group {group} on {}",
+            std::thread::current().name().unwrap_or_default()
+        )
+    }
+
+    #[messaging(CountdownRequest, CountdownResponse, stream)]
+    pub fn countdown(&self, from: u64, sink: &mut StreamSink<u64>) {
+        for i in (0..=from).rev() {
+            if !sink.push(i) {
+                return;
+            }
+        }
+    }
 }
 
 #[test]
@@ -43,3 +66,74 @@ fn test_macro_generation() {
     assert_eq!(response.id, 1);
     assert_eq!(response.result, 1);
 }
+
+#[test]
+fn test_macro_priority() {
+    use messaging_thread_pool::{Priority, RequestWithResponse, ThreadPool};
+
+    assert_eq!(
+        Priority::Normal,
+        <TestRequest as RequestWithResponse<MacroTest>>::PRIORITY
+    );
+    assert_eq!(
+        Priority::High,
+        <UrgentRequest as RequestWithResponse<MacroTest>>::PRIORITY
+    );
+
+    let pool = ThreadPool::<MacroTest>::new(1);
+    pool.send_and_receive_once(MacroTestInit(3)).unwrap();
+    let response = pool.send_and_receive_once(UrgentRequest(3)).unwrap();
+    assert_eq!(3, response.result);
+}
+
+#[test]
+fn test_macro_stream_sink() {
+    use messaging_thread_pool::{InlinePool, SenderAndReceiver, StreamChunk, ThreadPool};
+
+    let pool = ThreadPool::<MacroTest>::new(2);
+    pool.send_and_receive_once(MacroTestInit(5)).unwrap();
+
+    let items: Vec<u64> = pool
+        .send_and_receive_stream(CountdownRequest(5, 3))
+        .unwrap()
+        .collect();
+    assert_eq!(vec![3, 2, 1, 0], items);
+
+    // the raw responses are the chunks followed by the end-of-stream marker
+    let inline_pool = InlinePool::<MacroTest>::new();
+    inline_pool.send_and_receive_one(MacroTestInit(5)).unwrap();
+    let chunks: Vec<StreamChunk<u64>> = inline_pool
+        .send_and_receive(std::iter::once(CountdownRequest(5, 1)))
+        .unwrap()
+        .map(|response: CountdownResponse| response.result)
+        .collect();
+    assert_eq!(
+        vec![StreamChunk::Item(1), StreamChunk::Item(0), StreamChunk::End],
+        chunks
+    );
+}
+
+#[test]
+fn test_macro_routing_key() {
+    use messaging_thread_pool::{IdTargeted, Routed, ThreadPool};
+
+    let request = GroupThreadRequest(4, 1);
+    assert_eq!(4, request.id());
+    assert_eq!(1, request.routing_key());
+
+    // id 4 would be routed to thread 0; the routing key places it on thread 1
+    let pool = ThreadPool::<MacroTest>::new(2);
+    pool.send_and_receive_once(Routed::new(1, MacroTestInit(4)))
+        .unwrap();
+    let response = pool.send_and_receive_once(request).unwrap();
+    assert_eq!(4, response.id);
+    assert!(response.result.starts_with("group 1 on "));
+    assert!(response.result.ends_with("-1"));
+
+    // the routing key is kept when the request is wrapped in the api or in a request response
+    let api = MacroTestApi::GroupThreadRequest(messaging_thread_pool::RequestResponse::Request(
+        GroupThreadRequest(4, 1),
+    ));
+    assert_eq!(4, api.id());
+    assert_eq!(1, api.routing_key());
+}